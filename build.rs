@@ -2,18 +2,22 @@ fn main() {
     let proto_dir = "proto";
     println!("cargo:rerun-if-changed={}", proto_dir);
 
+    // the dial-out service is implemented by collectors, so only it needs server stubs
+    let dialout = std::env::var_os("CARGO_FEATURE_DIALOUT").is_some();
+    let mut protos = vec![
+        "proto/gnmi/gnmi.proto",
+        "proto/gnmi_ext/gnmi_ext.proto",
+        "proto/target/target.proto",
+        "proto/collector/collector.proto",
+        "proto/google.proto",
+    ];
+    if dialout {
+        protos.push("proto/dialout/dialout.proto");
+    }
+
     tonic_build::configure()
-        .build_server(false)
+        .build_server(dialout)
         .compile_well_known_types(true)
-        .compile(
-            &[
-                "proto/gnmi/gnmi.proto",
-                "proto/gnmi_ext/gnmi_ext.proto",
-                "proto/target/target.proto",
-                "proto/collector/collector.proto",
-                "proto/google.proto",
-            ],
-            &[proto_dir],
-        )
+        .compile(&protos, &[proto_dir])
         .expect("Failed to compile protobuf files");
 }
@@ -1,30 +1,203 @@
-use tonic::metadata::AsciiMetadataValue;
+use std::fmt;
+use std::fmt::Write;
+use std::sync::{Arc, RwLock};
+use tonic::metadata::{AsciiMetadataKey, AsciiMetadataValue};
 use tonic::service::Interceptor;
 use tonic::{Request, Status};
 
-#[derive(Debug, Clone)]
-pub struct AuthInterceptor {
+struct AuthMetadata {
     username: AsciiMetadataValue,
     password: AsciiMetadataValue,
 }
 
+impl fmt::Debug for AuthMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthMetadata")
+            .field("username", &self.username)
+            .field("password", &"***")
+            .finish()
+    }
+}
+
+/// Name of the header that carries the correlation id of a request.
+pub(crate) const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Interceptor that adds the credentials and static metadata to the metadata of every
+/// request, and optionally a newly generated correlation id.
+///
+/// The credentials are shared between all clones of the interceptor, so updating them
+/// affects every clone.
+#[derive(Debug, Clone)]
+pub struct AuthInterceptor {
+    metadata: Arc<RwLock<AuthMetadata>>,
+    headers: Arc<Vec<(AsciiMetadataKey, AsciiMetadataValue)>>,
+    correlation_ids: bool,
+}
+
 impl AuthInterceptor {
     pub fn new(username: Option<AsciiMetadataValue>, password: Option<AsciiMetadataValue>) -> Self {
         Self {
-            username: username.unwrap_or(AsciiMetadataValue::from_static("")),
-            password: password.unwrap_or(AsciiMetadataValue::from_static("")),
+            metadata: Arc::new(RwLock::new(AuthMetadata {
+                username: username.unwrap_or(AsciiMetadataValue::from_static("")),
+                password: password.unwrap_or(AsciiMetadataValue::from_static("")),
+            })),
+            headers: Arc::new(Vec::new()),
+            correlation_ids: false,
+        }
+    }
+
+    /// Add a newly generated correlation id to every request that does not carry one yet.
+    pub fn with_correlation_ids(mut self, enabled: bool) -> Self {
+        self.correlation_ids = enabled;
+        self
+    }
+
+    /// Generate a new correlation id, if correlation ids are enabled.
+    ///
+    /// Requests that already carry the id are not assigned another one, so the caller can
+    /// attach it to a request to learn the id of that request.
+    pub fn request_id(&self) -> Result<Option<AsciiMetadataValue>, Status> {
+        if !self.correlation_ids {
+            return Ok(None);
         }
+
+        let id = request_id()
+            .map_err(|e| Status::internal(format!("failed to generate request id: {}", e)))?;
+        AsciiMetadataValue::try_from(id.as_str())
+            .map(Some)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+
+    /// Add the given metadata to every request.
+    pub fn with_headers(mut self, headers: Vec<(AsciiMetadataKey, AsciiMetadataValue)>) -> Self {
+        self.headers = Arc::new(headers);
+        self
+    }
+
+    /// Replace the credentials used for all subsequent requests.
+    pub fn set_credentials(&self, username: AsciiMetadataValue, password: AsciiMetadataValue) {
+        let mut metadata = self
+            .metadata
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        metadata.username = username;
+        metadata.password = password;
     }
 }
 
 impl Interceptor for AuthInterceptor {
     fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let metadata = self
+            .metadata
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (key, value) in self.headers.iter() {
+            request.metadata_mut().append(key.clone(), value.clone());
+        }
         request
             .metadata_mut()
-            .insert("username", self.username.clone());
+            .insert("username", metadata.username.clone());
         request
             .metadata_mut()
-            .insert("password", self.password.clone());
+            .insert("password", metadata.password.clone());
+
+        if !request.metadata().contains_key(REQUEST_ID_HEADER) {
+            if let Some(id) = self.request_id()? {
+                request.metadata_mut().insert(REQUEST_ID_HEADER, id);
+            }
+        }
+
         Ok(request)
     }
 }
+
+/// Generate a random (version 4) UUID.
+fn request_id() -> Result<String, getrandom::Error> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes)?;
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let mut id = String::with_capacity(36);
+    for (i, byte) in bytes.iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            id.push('-');
+        }
+        let _ = write!(id, "{:02x}", byte);
+    }
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updated_credentials_apply_to_clones() {
+        let interceptor = AuthInterceptor::new(
+            Some(AsciiMetadataValue::from_static("admin")),
+            Some(AsciiMetadataValue::from_static("old")),
+        );
+        let mut clone = interceptor.clone();
+
+        interceptor.set_credentials(
+            AsciiMetadataValue::from_static("admin"),
+            AsciiMetadataValue::from_static("new"),
+        );
+
+        let request = clone.call(Request::new(())).unwrap();
+        assert_eq!(request.metadata().get("username").unwrap(), "admin");
+        assert_eq!(request.metadata().get("password").unwrap(), "new");
+    }
+
+    #[test]
+    fn static_headers() {
+        let mut interceptor = AuthInterceptor::new(None, None).with_headers(vec![(
+            AsciiMetadataKey::from_static("x-request-source"),
+            AsciiMetadataValue::from_static("collector"),
+        )]);
+
+        let request = interceptor.call(Request::new(())).unwrap();
+        assert_eq!(
+            request.metadata().get("x-request-source").unwrap(),
+            "collector"
+        );
+    }
+
+    #[test]
+    fn correlation_ids() {
+        let mut interceptor = AuthInterceptor::new(None, None).with_correlation_ids(true);
+
+        let first = interceptor.call(Request::new(())).unwrap();
+        let second = interceptor.call(Request::new(())).unwrap();
+        let first = first.metadata().get(REQUEST_ID_HEADER).unwrap();
+        let second = second.metadata().get(REQUEST_ID_HEADER).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(first.len(), 36);
+
+        // an id that was attached by the caller is kept
+        let id = interceptor.request_id().unwrap().unwrap();
+        let mut request = Request::new(());
+        request.metadata_mut().insert(REQUEST_ID_HEADER, id.clone());
+        let request = interceptor.call(request).unwrap();
+        assert_eq!(request.metadata().get(REQUEST_ID_HEADER), Some(&id));
+
+        let mut interceptor = AuthInterceptor::new(None, None);
+        let request = interceptor.call(Request::new(())).unwrap();
+        assert!(request.metadata().get(REQUEST_ID_HEADER).is_none());
+        assert_eq!(interceptor.request_id().unwrap(), None);
+    }
+
+    #[test]
+    fn debug_redacts_password() {
+        let interceptor = AuthInterceptor::new(
+            Some(AsciiMetadataValue::from_static("admin")),
+            Some(AsciiMetadataValue::from_static("secret")),
+        );
+
+        let debug = format!("{:?}", interceptor);
+        assert!(debug.contains("admin"));
+        assert!(!debug.contains("secret"));
+    }
+}
@@ -0,0 +1,103 @@
+//! Delay between failed attempts to connect to a target device.
+//!
+//! The channel of a [`Client`](super::Client) reconnects with the next request after its
+//! connection broke, without any delay. [`Backoff`] delays every attempt that follows a
+//! failed one, doubling the delay after each failure up to a maximum, so a fleet of clients
+//! does not hammer a flapping device.
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tonic::transport::Uri;
+use tower_service::Service;
+
+/// Connector that delays connection attempts following a failed one.
+#[derive(Debug, Clone)]
+pub(crate) struct Backoff<C> {
+    inner: C,
+    range: Option<(Duration, Duration)>,
+    delay: Arc<Mutex<Option<Duration>>>,
+}
+
+impl<C> Backoff<C> {
+    /// Delay attempts by `min` after the first failure, up to `max`. Without a range,
+    /// attempts are not delayed at all.
+    pub(crate) fn new(inner: C, range: Option<(Duration, Duration)>) -> Self {
+        Self {
+            inner,
+            range,
+            delay: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl<C> Service<Uri> for Backoff<C>
+where
+    C: Service<Uri>,
+    C::Future: Send + 'static,
+    C::Response: Send + 'static,
+    C::Error: Send + 'static,
+{
+    type Response = C::Response;
+    type Error = C::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connecting = self.inner.call(uri);
+        let (min, max) = match self.range {
+            Some(range) => range,
+            None => return Box::pin(connecting),
+        };
+
+        let delay = Arc::clone(&self.delay);
+        let wait = *delay
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        Box::pin(async move {
+            if let Some(wait) = wait {
+                tokio::time::sleep(wait).await;
+            }
+
+            let result = connecting.await;
+            let next = match &result {
+                Ok(_) => None,
+                Err(_) => Some(wait.map_or(min, |wait| (wait * 2).clamp(min, max))),
+            };
+            *delay
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = next;
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::client::HttpConnector;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn failed_attempts_are_delayed() {
+        let min = Duration::from_millis(50);
+        let mut connector = Backoff::new(HttpConnector::new(), Some((min, min * 3)));
+        let unreachable = || Uri::from_static("http://127.0.0.1:1");
+
+        assert!(connector.call(unreachable()).await.is_err());
+        assert_eq!(*connector.delay.lock().unwrap(), Some(min));
+
+        let start = Instant::now();
+        assert!(connector.call(unreachable()).await.is_err());
+        assert!(start.elapsed() >= min);
+        assert_eq!(*connector.delay.lock().unwrap(), Some(min * 2));
+
+        assert!(connector.call(unreachable()).await.is_err());
+        assert_eq!(*connector.delay.lock().unwrap(), Some(min * 3));
+    }
+}
@@ -1,7 +1,38 @@
+use crate::error::GinmiError;
 use crate::gen::gnmi::CapabilityResponse;
-use crate::gen::gnmi::ModelData;
+use std::fmt;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
 
-pub use crate::gen::gnmi::Encoding;
+pub use crate::gen::gnmi::{Encoding, ModelData};
+
+impl fmt::Display for Encoding {
+    /// Formats the [`Encoding`] by its canonical lowercase name, e.g. `json_ietf`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.as_str_name().to_ascii_lowercase())
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = GinmiError;
+
+    /// Parse an [`Encoding`] from its name, ignoring case, e.g. `json_ietf` or `JSON_IETF`.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::InvalidEncodingError`] if the name is not one of `json`, `bytes`,
+    ///   `proto`, `ascii` or `json_ietf`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Encoding::from_str_name(&s.trim().to_ascii_uppercase()).ok_or_else(|| {
+            GinmiError::InvalidEncodingError(format!(
+                "unknown encoding '{}', expected one of json, bytes, proto, ascii or json_ietf",
+                s
+            ))
+        })
+    }
+}
 
 /// Capabilities of a given gNMI Target device.
 ///
@@ -36,6 +67,39 @@ impl<'a> Capabilities {
         self.0.g_nmi_version.as_str()
     }
 
+    /// Retrieve the gNMI Version that the target device supports as a [`semver::Version`],
+    /// e.g. to compare it against a minimum version.
+    ///
+    /// Returns `None` if the version reported by the target device is not a valid semantic
+    /// version.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::{Client, Capabilities};
+    /// # fn main() -> std::io::Result<()> {
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// let mut client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .tls(CERT, "clab-srl01-srl")
+    ///     .credentials("admin", "admin")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let capabilities = client.capabilities().await.unwrap();
+    /// let supports_0_8 = capabilities
+    ///     .gnmi_version_semver()
+    ///     .is_some_and(|version| version >= semver::Version::new(0, 8, 0));
+    /// # });
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "semver")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "semver")))]
+    pub fn gnmi_version_semver(&self) -> Option<semver::Version> {
+        semver::Version::parse(self.gnmi_version().trim()).ok()
+    }
+
     /// Check if target device supports a given model.
     ///
     /// # Arguments
@@ -74,6 +138,44 @@ impl<'a> Capabilities {
         })
     }
 
+    /// Iterate over the supported models whose name matches a glob `pattern`, e.g.
+    /// `srl_nokia-*` to find all models of a vendor.
+    ///
+    /// In the pattern, `*` matches any sequence of characters and `?` matches a single
+    /// character. All other characters match themselves, so `openconfig-interfaces`
+    /// only matches the model of that name.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::{Client, Capabilities};
+    /// # fn main() -> std::io::Result<()> {
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// let mut client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .tls(CERT, "clab-srl01-srl")
+    ///     .credentials("admin", "admin")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let capabilities = client.capabilities().await.unwrap();
+    /// for model in capabilities.models_matching("*srl_nokia-*") {
+    ///     println!("{} {}", model.name, model.version);
+    /// }
+    /// # });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn models_matching<'p>(
+        &'p self,
+        pattern: &'p str,
+    ) -> impl Iterator<Item = &'p ModelData> + 'p {
+        self.0
+            .supported_models
+            .iter()
+            .filter(move |model| glob_matches(pattern, &model.name))
+    }
+
     /// Check if a target device supports a given [`Encoding`].
     ///
     /// # Arguments
@@ -110,4 +212,206 @@ impl<'a> Capabilities {
 
         self.0.supported_encodings.contains(&enc)
     }
+
+    /// Check if the version, models or encodings differ from `other`, ignoring their order.
+    pub(crate) fn differs_from(&self, other: &Capabilities) -> bool {
+        fn models(c: &Capabilities) -> Vec<(&str, &str, &str)> {
+            let mut models: Vec<_> =
+                c.0.supported_models
+                    .iter()
+                    .map(|m| (m.name.as_str(), m.organization.as_str(), m.version.as_str()))
+                    .collect();
+            models.sort();
+            models
+        }
+        fn encodings(c: &Capabilities) -> Vec<i32> {
+            let mut encodings = c.0.supported_encodings.clone();
+            encodings.sort();
+            encodings
+        }
+
+        self.gnmi_version() != other.gnmi_version()
+            || models(self) != models(other)
+            || encodings(self) != encodings(other)
+    }
+}
+
+/// Check if `text` matches the glob `pattern`, where `*` matches any sequence of characters
+/// and `?` matches any single character.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` in the pattern and the text it started to match at
+    let mut star = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // let the last `*` match one more character and retry
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Change of the capabilities of a target device, e.g. after an upgrade of its software.
+#[derive(Debug, Clone)]
+pub struct CapabilitiesChange {
+    /// The capabilities before the change.
+    pub previous: Capabilities,
+    /// The capabilities after the change.
+    pub current: Capabilities,
+}
+
+/// Stream of [`CapabilitiesChange`]s of a target device.
+///
+/// Returned by [`Client::watch_capabilities`](super::Client::watch_capabilities). Dropping the
+/// stream stops polling the capabilities.
+#[derive(Debug)]
+pub struct CapabilitiesStream {
+    pub(crate) changes: mpsc::Receiver<Result<CapabilitiesChange, GinmiError>>,
+}
+
+impl Stream for CapabilitiesStream {
+    type Item = Result<CapabilitiesChange, GinmiError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.changes.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding_names() {
+        let encodings = [
+            Encoding::Json,
+            Encoding::Bytes,
+            Encoding::Proto,
+            Encoding::Ascii,
+            Encoding::JsonIetf,
+        ];
+
+        for encoding in encodings {
+            assert_eq!(encoding.to_string().parse::<Encoding>().unwrap(), encoding);
+        }
+        assert_eq!(Encoding::JsonIetf.to_string(), "json_ietf");
+        assert_eq!("JSON_IETF".parse::<Encoding>().unwrap(), Encoding::JsonIetf);
+        assert!(matches!(
+            "xml".parse::<Encoding>(),
+            Err(GinmiError::InvalidEncodingError(_))
+        ));
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_matches("srl_nokia-*", "srl_nokia-interfaces"));
+        assert!(glob_matches("*-interfaces", "openconfig-interfaces"));
+        assert!(glob_matches("openconfig-?cl", "openconfig-acl"));
+        assert!(glob_matches("*a*b*", "xaybzb"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("srl_nokia-*", "openconfig-interfaces"));
+        assert!(!glob_matches("openconfig-?", "openconfig-acl"));
+        assert!(!glob_matches("openconfig", "openconfig-acl"));
+    }
+
+    #[test]
+    fn models_matching_pattern() {
+        let model = |name: &str| ModelData {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let capabilities = Capabilities(CapabilityResponse {
+            supported_models: vec![
+                model("urn:srl_nokia/aaa:srl_nokia-aaa"),
+                model("openconfig-interfaces"),
+                model("urn:srl_nokia/interfaces:srl_nokia-interfaces"),
+            ],
+            ..Default::default()
+        });
+
+        let names: Vec<&str> = capabilities
+            .models_matching("*srl_nokia-*")
+            .map(|model| model.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "urn:srl_nokia/aaa:srl_nokia-aaa",
+                "urn:srl_nokia/interfaces:srl_nokia-interfaces"
+            ]
+        );
+    }
+
+    #[test]
+    fn capabilities_differ() {
+        let model = |name: &str| ModelData {
+            name: name.to_string(),
+            organization: "OpenConfig".to_string(),
+            version: "1.0.0".to_string(),
+        };
+        let capabilities = |version: &str, models: &[&str], encodings: &[i32]| {
+            Capabilities(CapabilityResponse {
+                supported_models: models.iter().map(|name| model(name)).collect(),
+                supported_encodings: encodings.to_vec(),
+                g_nmi_version: version.to_string(),
+                ..Default::default()
+            })
+        };
+
+        let current = capabilities("0.10.0", &["openconfig-system", "openconfig-acl"], &[0, 4]);
+        assert!(!current.differs_from(&capabilities(
+            "0.10.0",
+            &["openconfig-acl", "openconfig-system"],
+            &[4, 0]
+        )));
+        assert!(current.differs_from(&capabilities(
+            "0.8.0",
+            &["openconfig-system", "openconfig-acl"],
+            &[0, 4]
+        )));
+        assert!(current.differs_from(&capabilities("0.10.0", &["openconfig-system"], &[0, 4])));
+        assert!(current.differs_from(&capabilities(
+            "0.10.0",
+            &["openconfig-system", "openconfig-acl"],
+            &[4]
+        )));
+    }
+
+    #[cfg(feature = "semver")]
+    #[test]
+    fn gnmi_version_semver() {
+        let capabilities = |version: &str| {
+            Capabilities(CapabilityResponse {
+                g_nmi_version: version.to_string(),
+                ..Default::default()
+            })
+        };
+
+        assert_eq!(
+            capabilities("0.10.0").gnmi_version_semver(),
+            Some(semver::Version::new(0, 10, 0))
+        );
+        assert!(capabilities("0.10.0").gnmi_version_semver() > Some(semver::Version::new(0, 8, 0)));
+        assert_eq!(capabilities("0.7").gnmi_version_semver(), None);
+        assert_eq!(capabilities("").gnmi_version_semver(), None);
+    }
 }
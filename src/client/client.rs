@@ -1,43 +1,346 @@
-use super::capabilities::Capabilities;
+use super::backoff::Backoff;
+use super::capabilities::{Capabilities, CapabilitiesChange, CapabilitiesStream, Encoding};
+use super::clock::ClockSkew;
+use super::connectivity::{ConnectivityConnector, ConnectivityState, ConnectivityStream};
 #[cfg(feature = "dangerous_configuration")]
 use super::dangerous::DangerousClientBuilder;
-use crate::auth::AuthInterceptor;
+use super::get::{group_by_path, list_entries, values_by_key, GetRequestBuilder, SubtreeSnapshots};
+#[cfg(feature = "grpc_web")]
+use super::grpc_web::GrpcWebClientBuilder;
+use super::resolve::StaticResolver;
+use super::set::SetRequestBuilder;
+use super::state::SubscriptionState;
+use super::stats;
+use super::subscribe::{
+    wildcard_list, SubscribeEvent, SubscribeStream, Subscription, SubscriptionList,
+    SubscriptionListMode,
+};
+use super::tls::{
+    root_store, SharedTlsDetails, SupportedCipherSuite, SupportedProtocolVersion, TlsDetails,
+    TlsOptions, TlsSession,
+};
+use super::transport::{BoxStream, GnmiTransport, TonicTransport};
+use crate::auth::{AuthInterceptor, REQUEST_ID_HEADER};
 use crate::error::GinmiError;
+use crate::extension::Extension;
 use crate::gen::gnmi::g_nmi_client::GNmiClient;
-use crate::gen::gnmi::CapabilityRequest;
+use crate::gen::gnmi::subscribe_request::Request;
+use crate::gen::gnmi::{
+    CapabilityRequest, SubscribeRequest, Subscription as GnmiSubscription,
+    SubscriptionList as GnmiSubscriptionList,
+};
+use crate::notification::{Notifications, Update};
+use crate::path::{Path, PathElement};
+use crate::value::TypedValue;
 use hyper::body::Bytes;
+use hyper::client::HttpConnector;
+use prost::Message;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, watch, Mutex, OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::MissedTickBehavior;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tonic::body::BoxBody;
 use tonic::codegen::{Body, InterceptedService, StdError};
-use tonic::metadata::AsciiMetadataValue;
-use tonic::transport::{Certificate, Channel, ClientTlsConfig, Uri};
+use tonic::metadata::{AsciiMetadataKey, AsciiMetadataValue, MetadataMap};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Uri};
+
+/// Security of the connection between a [`Client`] and its target device.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConnectionSecurity {
+    /// The connection uses TLS and the certificate of the target device is verified.
+    Tls,
+    /// The connection uses plaintext HTTP/2 without any encryption.
+    Insecure,
+    /// The connection uses TLS, but the certificate of the target device is not verified.
+    DangerousNoVerify,
+    /// The connection uses TLS and the certificate of the target device is verified against
+    /// a pinned fingerprint instead of a CA, see [`ClientBuilder::tls_pin_sha256`].
+    Pinned,
+    /// The connection uses a channel, service or transport that was configured by the user,
+    /// see [`Client::from_channel`], [`Client::from_service`] and [`Client::from_transport`].
+    /// Its security is not known.
+    Custom,
+}
 
 /// Provides the main functionality of connection to a target device
 /// and manipulating configuration or querying telemetry.
+///
+/// The requests are sent through the [`GnmiTransport`] `T`, which is a [`TonicTransport`]
+/// for all clients created by the [`ClientBuilder`].
 #[derive(Debug, Clone)]
 pub struct Client<T> {
-    pub(crate) inner: GNmiClient<T>,
+    pub(crate) inner: T,
+    pub(crate) capabilities_cache: Arc<Mutex<Option<Capabilities>>>,
+    pub(crate) security: ConnectionSecurity,
+    pub(crate) auth: AuthInterceptor,
+    pub(crate) subscribe_buffer: usize,
+    pub(crate) endpoints: Vec<Endpoint>,
+    pub(crate) local_address: Option<IpAddr>,
+    pub(crate) resolver: StaticResolver,
+    pub(crate) captured_request: Option<Arc<std::sync::Mutex<Vec<u8>>>>,
+    pub(crate) connectivity: Option<watch::Receiver<ConnectivityState>>,
+    pub(crate) reconnect_backoff: Option<(Duration, Duration)>,
+    pub(crate) default_encoding: Encoding,
+    pub(crate) request_limit: Option<Arc<Semaphore>>,
+    pub(crate) tls_session: Option<TlsSession>,
+    pub(crate) tls_details: SharedTlsDetails,
 }
 
-impl<'a> Client<InterceptedService<Channel, AuthInterceptor>> {
+/// [`Encoding`] of get requests and subscriptions that do not set one, unless configured
+/// otherwise with [`ClientBuilder::default_encoding`].
+pub const DEFAULT_ENCODING: Encoding = Encoding::JsonIetf;
+
+/// Number of subscribe responses that are buffered if the consumer of a [`SubscribeStream`]
+/// is slower than the target device, unless configured otherwise with
+/// [`ClientBuilder::subscribe_buffer`].
+pub const DEFAULT_SUBSCRIBE_BUFFER: usize = 64;
+
+impl<'a> Client<TonicTransport<InterceptedService<Channel, AuthInterceptor>>> {
     /// Create a [`ClientBuilder`] that can create [`Client`]s.
     pub fn builder(target: &'a str) -> ClientBuilder<'a> {
         ClientBuilder::new(target)
     }
+
+    /// Create a [`Client`] that uses an already configured [`Channel`].
+    ///
+    /// This allows full control over the transport, e.g. to use custom load balancing or
+    /// TLS settings that the [`ClientBuilder`] does not support. The credentials, if any,
+    /// are sent with every request just like with a [`Client`] created by the builder.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::Client;
+    /// # use tonic::transport::Endpoint;
+    /// # tokio_test::block_on(async {
+    /// let channel = Endpoint::from_static("http://clab-srl01-srl:57400")
+    ///     .tcp_nodelay(true)
+    ///     .connect()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let mut client = Client::from_channel(channel, Some(("admin", "admin"))).unwrap();
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::InvalidCredentialsError`] if the credentials contain characters
+    ///   that are not allowed in gRPC metadata.
+    pub fn from_channel(
+        channel: Channel,
+        credentials: Option<(&str, &str)>,
+    ) -> Result<Self, GinmiError> {
+        Client::from_service(channel, credentials)
+    }
+
+    /// Establish a new connection to the target device, replacing the current one.
+    ///
+    /// The channel already re-establishes broken connections on its own. Use this method to
+    /// force a fresh connection with the settings of the [`ClientBuilder`], e.g. if requests
+    /// keep failing after the target device has been restarted. The capabilities cache is
+    /// cleared, as the target device may have changed. Clones of this [`Client`] keep using
+    /// the previous connection.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::ReconnectError`] if the [`Client`] was created with
+    ///   [`Client::from_channel`], as the settings of the channel are not known.
+    /// - Returns [`GinmiError::TransportError`] if a connection to the target could not be
+    ///   established.
+    pub async fn reconnect(&mut self) -> Result<(), GinmiError> {
+        if self.endpoints.is_empty() {
+            return Err(GinmiError::ReconnectError(
+                "client was created from a channel, so its settings are unknown".to_string(),
+            ));
+        }
+
+        let (channel, connectivity) = connect(
+            &self.endpoints,
+            self.local_address,
+            self.resolver.clone(),
+            self.reconnect_backoff,
+            self.tls_session.as_ref(),
+        )
+        .await?;
+        self.inner = TonicTransport::new(GNmiClient::with_interceptor(channel, self.auth.clone()));
+        self.connectivity = connectivity;
+        *self.capabilities_cache.lock().await = None;
+        Ok(())
+    }
 }
 
-impl<T> Client<T>
-where
-    T: tonic::client::GrpcService<tonic::body::BoxBody>,
-    T::Error: Into<StdError>,
-    T::ResponseBody: Body<Data = Bytes> + Send + 'static,
-    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
-{
-    /// Returns information from the target device about its capabilities
-    /// according to the [gNMI Specification Section 3.2.2](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-specification.md#322-the-capabilityresponse-message)
+impl<T: GnmiTransport> Client<T> {
+    /// Create a [`Client`] that sends its requests through an arbitrary `transport`, e.g. a
+    /// fake that returns canned responses to test code built on top of the [`Client`]
+    /// without a target device, see the [`transport`](super::transport) module.
+    ///
+    /// The [`Client`] has no credentials, so [`Client::set_credentials`] has no effect:
+    /// authenticating the requests is up to the transport.
+    pub fn from_transport(transport: T) -> Self {
+        Client {
+            inner: transport,
+            capabilities_cache: Arc::new(Mutex::new(None)),
+            security: ConnectionSecurity::Custom,
+            auth: AuthInterceptor::new(None, None),
+            subscribe_buffer: DEFAULT_SUBSCRIBE_BUFFER,
+            endpoints: Vec::new(),
+            local_address: None,
+            resolver: StaticResolver::default(),
+            captured_request: None,
+            connectivity: None,
+            reconnect_backoff: None,
+            default_encoding: DEFAULT_ENCODING,
+            request_limit: None,
+            tls_session: None,
+            tls_details: Default::default(),
+        }
+    }
+
+    /// Poll the capabilities of the target device every `interval` and report whenever its
+    /// gNMI version, supported models or encodings change, e.g. after a software upgrade.
+    ///
+    /// The first successful poll is the baseline, so changes relative to it are reported.
+    /// Failed polls are reported as errors and polling continues, so the stream only ends
+    /// once it is dropped. On a change, the capabilities returned by
+    /// [`Client::capabilities_cached`] are replaced as well.
     ///
     /// # Examples
     /// ```rust
-    /// # use ginmi::Client;
+    /// # use ginmi::client::Client;
+    /// # use std::time::Duration;
+    /// # use tokio_stream::StreamExt;
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// let client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .tls(CERT, "clab-srl01-srl")
+    ///     .credentials("admin", "admin")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let mut changes = client.watch_capabilities(Duration::from_secs(300));
+    /// while let Some(Ok(change)) = changes.next().await {
+    ///     println!("upgraded to gNMI {}", change.current.gnmi_version());
+    /// }
+    /// # });
+    /// ```
+    pub fn watch_capabilities(&self, interval: Duration) -> CapabilitiesStream {
+        let mut client = self.clone();
+        let (sender, changes) = mpsc::channel(1);
+
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut previous: Option<Capabilities> = None;
+
+            loop {
+                let result = tokio::select! {
+                    // the stream has been dropped, so stop polling
+                    _ = sender.closed() => break,
+                    _ = ticks.tick() => client.capabilities().await,
+                };
+
+                let change = match (result, &previous) {
+                    (Ok(current), Some(last)) if current.differs_from(last) => {
+                        *client.capabilities_cache.lock().await = Some(current.clone());
+                        Ok(CapabilitiesChange {
+                            previous: previous.replace(current.clone()).unwrap(),
+                            current,
+                        })
+                    }
+                    (Ok(current), None) => {
+                        previous = Some(current);
+                        continue;
+                    }
+                    (Ok(_), Some(_)) => continue,
+                    (Err(e), _) => Err(e),
+                };
+
+                if sender.send(change).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        CapabilitiesStream { changes }
+    }
+
+    /// Retrieve the whole subtree at `path` every `interval` with a get request and yield
+    /// each complete snapshot, e.g. for dashboards that redraw from a fresh picture on every
+    /// cycle.
+    ///
+    /// If a get request or the consumer of the stream takes longer than `interval`, the ticks
+    /// that were missed in the meantime are skipped instead of sending the requests late, so
+    /// requests never pile up. Failed requests are yielded as errors and polling continues,
+    /// so the stream only ends once it is dropped.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::Client;
+    /// # use std::time::Duration;
+    /// # use tokio_stream::StreamExt;
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// let client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .tls(CERT, "clab-srl01-srl")
+    ///     .credentials("admin", "admin")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let path = "/interfaces/interface[name=*]/state".parse().unwrap();
+    /// let mut snapshots = client.poll_subtree(path, Duration::from_secs(10));
+    /// while let Some(Ok(snapshot)) = snapshots.next().await {
+    ///     println!("{} notifications", snapshot.iter().count());
+    /// }
+    /// # });
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero.
+    pub fn poll_subtree(&self, path: Path, interval: Duration) -> SubtreeSnapshots {
+        let mut client = self.clone();
+        let (sender, snapshots) = mpsc::channel(1);
+
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                let snapshot = tokio::select! {
+                    // the stream has been dropped, so stop polling
+                    _ = sender.closed() => break,
+                    _ = ticks.tick() => client.get().path(path.clone()).send().await,
+                };
+
+                if sender.send(snapshot).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        SubtreeSnapshots { snapshots }
+    }
+
+    /// Retrieve all entries of a list with one get request per entry, sending at most
+    /// `concurrency` requests at the same time.
+    ///
+    /// The last element of `list_path` must name the keys of the list with wildcards, e.g.
+    /// `/interfaces/interface[name=*]`. The keys of all entries are retrieved first, by
+    /// requesting the leaf that mirrors the first key. Retrieving the entries one by one
+    /// avoids a single huge response for long lists. The returned notifications are ordered
+    /// like the entries of the list.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::Client;
     /// # tokio_test::block_on(async {
     /// # const CERT: &str = "CA Certificate";
     /// let mut client = Client::builder("https://clab-srl01-srl:57400")
@@ -47,118 +350,2370 @@ where
     ///     .await
     ///     .unwrap();
     ///
-    /// let capabilities = client.capabilities().await.unwrap();
+    /// let interfaces = "/interfaces/interface[name=*]".parse().unwrap();
+    /// let notifications = client.get_list_concurrent(interfaces, 8).await.unwrap();
     /// # });
     /// ```
-    pub async fn capabilities(&mut self) -> Result<Capabilities, GinmiError> {
-        let req = CapabilityRequest::default();
-        let res = self.inner.capabilities(req).await?;
-        Ok(Capabilities(res.into_inner()))
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::PathParseError`] if the last element of `list_path` has no keys.
+    /// - Returns the first error of any of the get requests.
+    pub async fn get_list_concurrent(
+        &mut self,
+        list_path: Path,
+        concurrency: usize,
+    ) -> Result<Notifications, GinmiError> {
+        let key = match list_path
+            .elements()
+            .last()
+            .and_then(|e| e.keys().keys().next())
+        {
+            Some(key) => key.clone(),
+            None => {
+                return Err(GinmiError::PathParseError(format!(
+                    "last element of list path {} does not specify the keys of the list",
+                    list_path
+                )))
+            }
+        };
+
+        let mut key_path = list_path.clone();
+        key_path.push(PathElement::new(key));
+        let keys = self.get().path(key_path).send().await?;
+        let entries = list_entries(&list_path, keys.paths());
+
+        let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+        let mut requests = JoinSet::new();
+        for (index, entry) in entries.into_iter().enumerate() {
+            let mut client = self.clone();
+            let permits = Arc::clone(&permits);
+            requests.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                (index, client.get().path(entry).send().await)
+            });
+        }
+
+        let mut responses = Vec::new();
+        while let Some(response) = requests.join_next().await {
+            match response {
+                Ok((index, response)) => responses.push((index, response?)),
+                Err(e) => std::panic::resume_unwind(e.into_panic()),
+            }
+        }
+        responses.sort_by_key(|(index, _)| *index);
+
+        let mut notifications = Notifications::default();
+        for (_, response) in responses {
+            notifications.append(response);
+        }
+        Ok(notifications)
     }
 }
 
-#[derive(Debug, Copy, Clone)]
-pub struct Credentials<'a> {
-    pub(crate) username: &'a str,
-    pub(crate) password: &'a str,
-}
+impl<S> Client<TonicTransport<InterceptedService<S, AuthInterceptor>>> {
+    /// Create a [`Client`] that sends its requests to an arbitrary gRPC `service`.
+    ///
+    /// Every method of the client sends a gRPC request to the service and decodes the gRPC
+    /// response. It may be any [`Service`](tower_service::Service) of HTTP requests, e.g. a
+    /// custom connection. The credentials are sent with every request just like with a
+    /// [`Client`] created by the builder. To fake the responses of a target device, use
+    /// [`Client::from_transport`] instead, which does not involve HTTP or gRPC framing.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::InvalidCredentialsError`] if the credentials contain characters
+    ///   that are not allowed in gRPC metadata.
+    pub fn from_service<B>(
+        service: S,
+        credentials: Option<(&str, &str)>,
+    ) -> Result<Self, GinmiError>
+    where
+        S: tower_service::Service<http::Request<BoxBody>, Response = http::Response<B>>,
+        S: Clone + Send + Sync + 'static,
+        S::Future: Send,
+        S::Error: Into<StdError> + Send + Sync,
+        B: Body<Data = Bytes> + Default + Send + 'static,
+        B::Error: Into<StdError> + Send,
+    {
+        let credentials = credentials.map(|(username, password)| Credentials {
+            username: username.into(),
+            password: password.into(),
+        });
+        let auth = auth_interceptor(credentials)?;
 
-/// Builder for [`Client`]s
-///
-/// Used to configure and create instances of [`Client`].
-#[derive(Debug, Clone)]
-pub struct ClientBuilder<'a> {
-    pub(crate) target: &'a str,
-    pub(crate) creds: Option<Credentials<'a>>,
-    tls_settings: Option<ClientTlsConfig>,
+        Ok(Client {
+            inner: TonicTransport::new(GNmiClient::with_interceptor(service, auth.clone())),
+            capabilities_cache: Arc::new(Mutex::new(None)),
+            security: ConnectionSecurity::Custom,
+            auth,
+            subscribe_buffer: DEFAULT_SUBSCRIBE_BUFFER,
+            endpoints: Vec::new(),
+            local_address: None,
+            resolver: StaticResolver::default(),
+            captured_request: None,
+            connectivity: None,
+            reconnect_backoff: None,
+            default_encoding: DEFAULT_ENCODING,
+            request_limit: None,
+            tls_session: None,
+            tls_details: Default::default(),
+        })
+    }
 }
 
-impl<'a> ClientBuilder<'a> {
-    pub fn new(target: &'a str) -> Self {
-        Self {
-            target,
-            creds: None,
-            tls_settings: None,
+impl<T: GnmiTransport> Client<T> {
+    /// Returns the security of the connection to the target device.
+    ///
+    /// The value is determined by the scheme of the target and the builder that was used to
+    /// create the [`Client`], and can be used to warn about connections that are not properly
+    /// secured.
+    pub fn connection_security(&self) -> ConnectionSecurity {
+        self.security
+    }
+
+    /// Details of the TLS session of the most recent connection to the target device, e.g.
+    /// to check that HTTP/2 and the expected TLS version were negotiated.
+    ///
+    /// Only sessions that ginmi establishes itself are recorded, i.e. those of clients with
+    /// [restricted](ClientBuilder::tls_min_version) TLS versions or cipher suites and of
+    /// clients created with `ClientBuilder::dangerous` or `ClientBuilder::grpc_web`. Returns
+    /// `None` for all other clients, whose TLS session is established by tonic, before the
+    /// first connection has been established and if the target uses `http`.
+    pub fn tls_details(&self) -> Option<TlsDetails> {
+        self.tls_details
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Replace the credentials used for all subsequent requests to the target device.
+    ///
+    /// The existing connection is kept. The new credentials also apply to all clones
+    /// of this [`Client`].
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::InvalidCredentialsError`] if the username or password contain
+    ///   characters that are not allowed in gRPC metadata.
+    pub fn set_credentials(&self, username: &str, password: &str) -> Result<(), GinmiError> {
+        let credentials = Credentials {
+            username: username.into(),
+            password: password.into(),
+        };
+        let (username, password) = credentials.to_metadata()?;
+        self.auth.set_credentials(username, password);
+        Ok(())
+    }
+
+    /// Watch the state of the connection to the target device, e.g. to show the health of
+    /// the connections of many devices without sending requests to them.
+    ///
+    /// The connection state is only tracked for clients created with a single target by
+    /// [`ClientBuilder::build`]. For all other clients, the stream ends immediately.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::Client;
+    /// # use tokio_stream::StreamExt;
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// let client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .tls(CERT, "clab-srl01-srl")
+    ///     .credentials("admin", "admin")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let mut states = client.connectivity();
+    /// while let Some(state) = states.next().await {
+    ///     println!("clab-srl01-srl is {:?}", state);
+    /// }
+    /// # });
+    /// ```
+    pub fn connectivity(&self) -> ConnectivityStream {
+        ConnectivityStream::new(self.connectivity.clone())
+    }
+
+    /// Close the client and wait until its connection to the target device has been closed.
+    ///
+    /// The connection is shared by all clones of the client and the [`SubscribeStream`]s
+    /// created by them, so it is closed once all of them have been closed or dropped. Their
+    /// requests and subscriptions are not interrupted, but drained, so this only returns
+    /// once they have finished. Without a tracked connection, see [`Client::connectivity`],
+    /// the client is dropped without waiting for its connection.
+    pub async fn close(self) {
+        let connectivity = self.connectivity.clone();
+        drop(self);
+
+        if let Some(mut state) = connectivity {
+            // the state is sent until the last user of the connection is gone
+            while state.changed().await.is_ok() {}
         }
     }
 
-    /// Configure credentials to use for connecting to the target device.
-    pub fn credentials(mut self, username: &'a str, password: &'a str) -> Self {
-        self.creds = Some(Credentials { username, password });
-        self
+    /// Returns the encoded protobuf message of the last request of this [`Client`] or one
+    /// of its clones, or `None` if [`ClientBuilder::debug_capture`] is not enabled.
+    ///
+    /// For subscriptions, this is the request that created the subscription. The bytes can
+    /// be decoded with any protobuf tooling, e.g. to compare them with the requests of other
+    /// gNMI clients.
+    pub fn last_request_bytes(&self) -> Option<Vec<u8>> {
+        self.captured_request.as_ref().map(|captured| {
+            captured
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clone()
+        })
     }
 
-    /// Configure TLS to use for connecting to the target device.
-    pub fn tls(mut self, ca_certificate: impl AsRef<[u8]>, domain_name: impl Into<String>) -> Self {
-        let cert = Certificate::from_pem(ca_certificate);
-        let settings = ClientTlsConfig::new()
-            .ca_certificate(cert)
-            .domain_name(domain_name);
-        self.tls_settings = Some(settings);
-        self
+    /// Wrap `message` into a request that carries a newly generated correlation id, if
+    /// [`ClientBuilder::correlation_ids`] is enabled. The id is returned to echo it into the
+    /// response with [`echo_request_id`].
+    pub(crate) fn correlate<M>(
+        &self,
+        message: M,
+    ) -> Result<(tonic::Request<M>, Option<AsciiMetadataValue>), GinmiError> {
+        let mut request = tonic::Request::new(message);
+        let id = self.auth.request_id()?;
+        if let Some(id) = &id {
+            request.metadata_mut().insert(REQUEST_ID_HEADER, id.clone());
+        }
+        Ok((request, id))
     }
 
-    #[cfg(feature = "dangerous_configuration")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "dangerous_configuration")))]
-    /// Access configuration options that are dangerous and require extra care.
-    pub fn dangerous(self) -> DangerousClientBuilder<'a> {
-        DangerousClientBuilder::from(self)
+    /// Wait until a request may be sent, if the number of concurrent requests is limited with
+    /// [`ClientBuilder::max_concurrent_requests`]. The request may be sent while the returned
+    /// permit is held.
+    pub(crate) async fn request_permit(&self) -> Option<OwnedSemaphorePermit> {
+        match &self.request_limit {
+            Some(limit) => Arc::clone(limit).acquire_owned().await.ok(),
+            None => None,
+        }
     }
 
-    /// Consume the [`ClientBuilder`] and return a [`Client`].
+    /// Store the encoded `request` if [`ClientBuilder::debug_capture`] is enabled.
+    pub(crate) fn capture(&self, request: &impl Message) {
+        if let Some(captured) = &self.captured_request {
+            *captured
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = request.encode_to_vec();
+        }
+    }
+
+    /// Check that the target device accepts the credentials of the client by sending a
+    /// capability request, the cheapest authenticated RPC.
+    ///
+    /// Together with [`ClientBuilder::connect_only`], this tells apart problems of the
+    /// network from rejected credentials: if the connection can be established, but this
+    /// check fails with [`GinmiError::Unauthenticated`] or [`GinmiError::PermissionDenied`],
+    /// the credentials are at fault.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::Client;
+    /// # use ginmi::error::GinmiError;
+    /// # tokio_test::block_on(async {
+    /// let builder = Client::builder("http://clab-srl01-srl:57400").credentials("admin", "admin");
+    /// builder.connect_only().await.expect("target device is not reachable");
+    ///
+    /// let mut client = builder.build().await.unwrap();
+    /// match client.authenticate_check().await {
+    ///     Ok(()) => println!("credentials accepted"),
+    ///     Err(GinmiError::Unauthenticated(_)) => println!("credentials rejected"),
+    ///     Err(e) => println!("check failed: {}", e),
+    /// }
+    /// # });
+    /// ```
     ///
     /// # Errors
-    /// - Returns [`GinmiError::InvalidUriError`] if specified target is not a valid URI.
-    /// - Returns [`GinmiError::TransportError`] if the TLS-Settings are invalid.
-    /// - Returns [`GinmiError::TransportError`] if a connection to the target could not be
-    /// established.
-    pub async fn build(
-        self,
-    ) -> Result<Client<InterceptedService<Channel, AuthInterceptor>>, GinmiError> {
-        let uri = match Uri::from_str(self.target) {
-            Ok(u) => u,
-            Err(e) => return Err(GinmiError::InvalidUriError(e.to_string())),
-        };
+    /// - Returns [`GinmiError::Unauthenticated`] if the target device rejected the
+    ///   credentials.
+    /// - Returns [`GinmiError::PermissionDenied`] if the user may not query the capabilities.
+    /// - Returns any other error of [`Client::capabilities`], e.g. if the target device is not
+    ///   reachable.
+    pub async fn authenticate_check(&mut self) -> Result<(), GinmiError> {
+        self.capabilities().await.map(|_| ())
+    }
 
-        let mut endpoint = Channel::builder(uri);
+    /// Returns information from the target device about its capabilities
+    /// according to the [gNMI Specification Section 3.2.2](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-specification.md#322-the-capabilityresponse-message)
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::Client;
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// let mut client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .tls(CERT, "clab-srl01-srl")
+    ///     .credentials("admin", "admin")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let capabilities = client.capabilities().await.unwrap();
+    /// # });
+    /// ```
+    pub async fn capabilities(&mut self) -> Result<Capabilities, GinmiError> {
+        self.capabilities_with_metadata()
+            .await
+            .map(|(capabilities, _)| capabilities)
+    }
 
-        if self.tls_settings.is_some() {
-            endpoint = endpoint.tls_config(self.tls_settings.unwrap())?;
-        }
+    /// Returns the capabilities of the target device, together with the metadata of the
+    /// response.
+    ///
+    /// The metadata contains both the headers and the trailers the target device sent,
+    /// e.g. vendor-specific information that is not part of the protobuf messages.
+    pub async fn capabilities_with_metadata(
+        &mut self,
+    ) -> Result<(Capabilities, MetadataMap), GinmiError> {
+        self.request_capabilities(CapabilityRequest::default())
+            .await
+    }
 
-        let channel = endpoint.connect().await?;
-        let (username, password) = match self.creds {
-            Some(c) => (
-                Some(AsciiMetadataValue::from_str(c.username)?),
-                Some(AsciiMetadataValue::from_str(c.password)?),
-            ),
-            None => (None, None),
+    /// Returns the capabilities of the target device, attaching `extensions` to the request.
+    ///
+    /// Some target devices accept extensions with the capability request, e.g. a vendor
+    /// specific [`Extension::Registered`] that selects a datastore or requests extended
+    /// information.
+    pub async fn capabilities_with(
+        &mut self,
+        extensions: Vec<Extension>,
+    ) -> Result<Capabilities, GinmiError> {
+        let req = CapabilityRequest {
+            extension: extensions.into_iter().map(Into::into).collect(),
         };
+        self.request_capabilities(req)
+            .await
+            .map(|(capabilities, _)| capabilities)
+    }
 
-        Ok(Client {
-            inner: GNmiClient::with_interceptor(channel, AuthInterceptor::new(username, password)),
-        })
+    async fn request_capabilities(
+        &mut self,
+        req: CapabilityRequest,
+    ) -> Result<(Capabilities, MetadataMap), GinmiError> {
+        self.capture(&req);
+        let (req, id) = self.correlate(req)?;
+        let _permit = self.request_permit().await;
+        let res = stats::observe("capabilities", self.inner.capabilities(req)).await;
+        let res = echo_request_id(res, id)?;
+        stats::received("capabilities", res.get_ref().encoded_len());
+        let (metadata, res, _) = res.into_parts();
+        Ok((Capabilities(res), metadata))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Returns the capabilities of the target device, querying them only on first use.
+    ///
+    /// The result is cached on the [`Client`] and shared between all of its clones, so
+    /// repeated calls do not cause additional round trips to the target device.
+    /// Use [`Client::refresh_capabilities`] to update the cached value.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::Client;
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// let mut client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .tls(CERT, "clab-srl01-srl")
+    ///     .credentials("admin", "admin")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// // only the first call queries the target device
+    /// let capabilities = client.capabilities_cached().await.unwrap();
+    /// let capabilities = client.capabilities_cached().await.unwrap();
+    /// # });
+    /// ```
+    pub async fn capabilities_cached(&mut self) -> Result<Capabilities, GinmiError> {
+        let cache = Arc::clone(&self.capabilities_cache);
+        let mut cached = cache.lock().await;
 
-    #[tokio::test]
-    async fn invalid_uri() {
-        let client = Client::<InterceptedService<Channel, AuthInterceptor>>::builder("$$$$")
-            .build()
-            .await;
-        assert!(client.is_err());
+        if let Some(capabilities) = cached.as_ref() {
+            return Ok(capabilities.clone());
+        }
+
+        let capabilities = self.capabilities().await?;
+        *cached = Some(capabilities.clone());
+        Ok(capabilities)
     }
 
-    #[tokio::test]
-    async fn invalid_tls_settings() {
-        let client = Client::builder("https://test:57400")
-            .tls("invalid cert", "invalid domain")
-            .build()
-            .await;
-        assert!(client.is_err());
+    /// Create a [`GetRequestBuilder`] to retrieve a snapshot of data elements from the target
+    /// device according to the [gNMI Specification Section 3.3](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-specification.md#33-retrieving-snapshots-of-state-information).
+    ///
+    /// Get requests have no equivalent of the `updates_only` option of subscriptions: the
+    /// target device always returns the current values. Large trees are returned in a single
+    /// response, which the target device may assemble from data collected at different times.
+    /// Use [`Client::snapshot`] to retrieve the values through a subscription instead.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::{Client, DataType, Encoding};
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// let mut client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .tls(CERT, "clab-srl01-srl")
+    ///     .credentials("admin", "admin")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let notifications = client
+    ///     .get()
+    ///     .path("/interface[name=mgmt0]/description".parse().unwrap())
+    ///     .data_type(DataType::Config)
+    ///     .encoding(Encoding::JsonIetf)
+    ///     .send()
+    ///     .await
+    ///     .unwrap();
+    /// # });
+    /// ```
+    pub fn get(&mut self) -> GetRequestBuilder<'_, T> {
+        GetRequestBuilder::new(self)
+    }
+
+    /// Retrieve the given paths and group the returned updates by the requested path they
+    /// belong to.
+    ///
+    /// The paths of the returned updates are absolute, i.e. the prefix of the notification
+    /// they were contained in has been prepended. If a path yields multiple notifications,
+    /// the updates of all of them are collected. Every requested path is contained in the
+    /// result, even if the target device did not return any updates for it. Updates that can
+    /// not be attributed to one of multiple requested paths are omitted.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::Client;
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// let mut client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .tls(CERT, "clab-srl01-srl")
+    ///     .credentials("admin", "admin")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let eth0 = "/interfaces/interface[name=eth0]/state".parse().unwrap();
+    /// let eth1 = "/interfaces/interface[name=eth1]/state".parse().unwrap();
+    /// let groups = client.get_grouped([eth0, eth1]).await.unwrap();
+    /// # });
+    /// ```
+    pub async fn get_grouped(
+        &mut self,
+        paths: impl IntoIterator<Item = Path>,
+    ) -> Result<HashMap<Path, Vec<Update>>, GinmiError> {
+        let paths: Vec<Path> = paths.into_iter().collect();
+
+        let mut req = self.get();
+        for path in &paths {
+            req = req.path(path.clone());
+        }
+
+        let notifications = req.send().await?;
+        Ok(group_by_path(&paths, notifications))
+    }
+
+    /// Retrieve the values at a wildcard `path` and map them by the value of the list `key`
+    /// they belong to, e.g. the status of all interfaces by the name of the interface.
+    ///
+    /// The key is taken from the first element of the path of each returned update that has
+    /// it, including the prefix of its notification. Updates whose path does not contain the
+    /// key are skipped and a warning is logged. If multiple updates share a key value, the
+    /// last one wins.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::Client;
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// let mut client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .tls(CERT, "clab-srl01-srl")
+    ///     .credentials("admin", "admin")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let path = "/interfaces/interface[name=*]/state/oper-status".parse().unwrap();
+    /// let status = client.get_keyed(path, "name").await.unwrap();
+    /// # });
+    /// ```
+    pub async fn get_keyed(
+        &mut self,
+        path: Path,
+        key: &str,
+    ) -> Result<HashMap<String, TypedValue>, GinmiError> {
+        let notifications = self.get().path(path).send().await?;
+        Ok(values_by_key(key, notifications))
+    }
+
+    /// Create a [`SetRequestBuilder`] to modify data elements of the target device according
+    /// to the [gNMI Specification Section 3.4](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-specification.md#34-modifying-state).
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::Client;
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// let mut client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .tls(CERT, "clab-srl01-srl")
+    ///     .credentials("admin", "admin")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let config = std::fs::read("interface.json").unwrap();
+    /// let config: serde_json::Value = serde_json::from_slice(&config).unwrap();
+    ///
+    /// let response = client
+    ///     .set()
+    ///     .replace_json("/interface[name=ethernet-1/1]".parse().unwrap(), config)
+    ///     .unwrap()
+    ///     .send()
+    ///     .await
+    ///     .unwrap();
+    /// # });
+    /// ```
+    pub fn set(&mut self) -> SetRequestBuilder<'_, T> {
+        SetRequestBuilder::new(self)
+    }
+
+    /// Create a subscription to the data elements in the [`SubscriptionList`]
+    /// according to the [gNMI Specification Section 3.5](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-specification.md#35-subscribing-to-telemetry-updates).
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::{Client, Subscription, SubscriptionList};
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// let mut client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .tls(CERT, "clab-srl01-srl")
+    ///     .credentials("admin", "admin")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let subscriptions = SubscriptionList::builder()
+    ///     .subscription(Subscription::new("/system/name".parse().unwrap()))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let stream = client.subscribe(subscriptions).await.unwrap();
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::GrpcError`] if the target device rejected the subscription.
+    /// - Returns the error of the get request if the wildcards of a subscription could not be
+    ///   [expanded](super::SubscriptionBuilder::expand_wildcards).
+    /// - Returns [`GinmiError::InvalidSubscriptionError`] if the expanded wildcards match no
+    ///   data elements at all.
+    /// - Returns [`GinmiError::InvalidEncodingError`] if the encoding is
+    ///   [verified](super::SubscriptionBuilder::verify_encoding), but not supported by the
+    ///   target device.
+    pub async fn subscribe(
+        &mut self,
+        mut subscriptions: SubscriptionList,
+    ) -> Result<SubscribeStream, GinmiError> {
+        if !subscriptions.explicit_encoding {
+            subscriptions.list.encoding = self.default_encoding as i32;
+        }
+        if subscriptions.verify_encoding {
+            subscriptions.check_encoding(&self.capabilities_cached().await?)?;
+        }
+        if subscriptions.expand_wildcards {
+            subscriptions.list = self.expand_wildcards(subscriptions.list).await?;
+        }
+
+        let paths = subscriptions.paths();
+        let (sender, receiver) = mpsc::channel(1);
+        let request = SubscribeRequest {
+            request: Some(Request::Subscribe(subscriptions.list)),
+            ..Default::default()
+        };
+        self.capture(&request);
+
+        let requests = tokio_stream::once(request).chain(ReceiverStream::new(receiver));
+        let (requests, id) = self.correlate(Box::pin(requests) as BoxStream<_>)?;
+        let permit = self.request_permit().await;
+        let res = stats::observe("subscribe", self.inner.subscribe(requests)).await;
+        let res = echo_request_id(res, id)?;
+        drop(permit);
+        let (metadata, responses, _) = res.into_parts();
+        Ok(SubscribeStream::new(
+            sender,
+            metadata,
+            responses,
+            self.subscribe_buffer,
+            paths,
+            subscriptions.detect_out_of_order,
+        ))
+    }
+
+    /// Replace every subscription whose path contains wildcards with subscriptions to the
+    /// entries of the list they select, as queried from the target device.
+    async fn expand_wildcards(
+        &mut self,
+        mut list: GnmiSubscriptionList,
+    ) -> Result<GnmiSubscriptionList, GinmiError> {
+        let prefix = list.prefix.clone().map(Path::from).unwrap_or_default();
+        let encoding = Encoding::try_from(list.encoding).unwrap_or_default();
+        let mut subscriptions = Vec::new();
+
+        for subscription in std::mem::take(&mut list.subscription) {
+            let path = subscription
+                .path
+                .clone()
+                .map(Path::from)
+                .unwrap_or_default();
+            let full = path.clone().with_prefix(&prefix);
+            // wildcards of the prefix can not be expanded without splitting the list
+            let (list_path, key_path) = match wildcard_list(&full) {
+                Some(list) if list.0.elements().len() > prefix.elements().len() => list,
+                _ => {
+                    subscriptions.push(subscription);
+                    continue;
+                }
+            };
+
+            let mut request = self.get().path(key_path).encoding(encoding);
+            if let Some(target) = full.target() {
+                request = request.prefix(Path::new().with_target(target));
+            }
+            let keys = request.send().await?;
+
+            let rest = &full.elements()[list_path.elements().len()..];
+            for entry in list_entries(&list_path, keys.paths()) {
+                let mut expanded = Path::new();
+                if let Some(origin) = path.origin() {
+                    expanded = expanded.with_origin(origin);
+                }
+                for element in entry.elements()[prefix.elements().len()..]
+                    .iter()
+                    .chain(rest)
+                {
+                    expanded.push(element.clone());
+                }
+
+                subscriptions.push(GnmiSubscription {
+                    path: Some(expanded.into()),
+                    ..subscription.clone()
+                });
+            }
+        }
+
+        if subscriptions.is_empty() {
+            return Err(GinmiError::InvalidSubscriptionError(
+                "no data elements match the wildcards of the subscriptions".to_string(),
+            ));
+        }
+        list.subscription = subscriptions;
+        Ok(list)
+    }
+
+    /// Subscribe to data elements of the target device and keep their current values in a
+    /// [`SubscriptionState`].
+    ///
+    /// Updates are applied as they arrive, deletes remove the deleted data elements and all
+    /// of their children. Use [`SubscriptionState::changed`] to wait for changes.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::GrpcError`] if the target device rejected the subscription.
+    pub async fn subscribe_state(
+        &mut self,
+        subscriptions: SubscriptionList,
+    ) -> Result<SubscriptionState, GinmiError> {
+        let stream = self.subscribe(subscriptions).await?;
+        Ok(SubscriptionState::new(stream))
+    }
+
+    /// Retrieve the values of the data elements at `path` with a
+    /// [`SubscriptionListMode::Once`](super::SubscriptionListMode::Once) subscription.
+    ///
+    /// Unlike [`Client::get`], the target device streams the values as they are collected,
+    /// each notification carrying the time its values were sampled, and signals with a sync
+    /// response once all of them have been sent. This is preferable for large trees and for
+    /// target devices that serve telemetry through subscriptions only. The returned
+    /// notifications carry the values received up to the sync response.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::Client;
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// let mut client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .tls(CERT, "clab-srl01-srl")
+    ///     .credentials("admin", "admin")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let path = "/interfaces/interface[name=ethernet-1/1]/state".parse().unwrap();
+    /// let notifications = client.snapshot(path).await.unwrap();
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::GrpcError`] if the target device rejected the subscription.
+    /// - Returns the error the target device reported for the subscription, if any.
+    pub async fn snapshot(&mut self, path: Path) -> Result<Notifications, GinmiError> {
+        let subscriptions = SubscriptionList::builder()
+            .mode(SubscriptionListMode::Once)
+            .subscription(Subscription::new(path))
+            .build()?;
+        let mut stream = self.subscribe(subscriptions).await?;
+
+        let mut notifications = Vec::new();
+        while let Some(response) = stream.next().await {
+            match response?.event {
+                SubscribeEvent::Update(notification)
+                | SubscribeEvent::OutOfOrder { notification, .. } => {
+                    notifications.push(notification)
+                }
+                SubscribeEvent::SyncResponse => break,
+                SubscribeEvent::Error(error) => {
+                    return Err(tonic::Status::new(error.code, error.message).into())
+                }
+            }
+        }
+
+        stream.close().await;
+        Ok(notifications.into())
+    }
+
+    /// Determine how far the clock of the target device is off from the local clock, e.g. to
+    /// correlate the timestamps of notifications from multiple devices.
+    ///
+    /// Sends a get request for `path` and compares the timestamp of the response with the
+    /// local time halfway through the request, so the skew is only accurate up to half the
+    /// round trip time. Any path works, as long as the target device answers quickly and
+    /// timestamps its response with the current time. A small leaf is preferable, e.g.
+    /// `/system/state/current-datetime` on devices supporting OpenConfig.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::Client;
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// let mut client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .tls(CERT, "clab-srl01-srl")
+    ///     .credentials("admin", "admin")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let skew = client
+    ///     .clock_skew("/system/state/current-datetime".parse().unwrap())
+    ///     .await
+    ///     .unwrap();
+    /// println!("clock is off by {:?}", skew.offset());
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::GrpcError`] if the target device rejected the request.
+    /// - Returns [`GinmiError::DecodeError`] if the response has no timestamp.
+    pub async fn clock_skew(&mut self, path: Path) -> Result<ClockSkew, GinmiError> {
+        let sent = SystemTime::now();
+        let notifications = self.get().path(path).send().await?;
+        let round_trip = sent.elapsed().unwrap_or_default();
+
+        let timestamp = notifications
+            .iter()
+            .map(|notification| notification.timestamp)
+            .max()
+            .filter(|timestamp| *timestamp > 0)
+            .ok_or_else(|| {
+                GinmiError::DecodeError(
+                    "response of the target device has no timestamp".to_string(),
+                )
+            })?;
+
+        let device = UNIX_EPOCH + Duration::from_nanos(timestamp.unsigned_abs());
+        Ok(ClockSkew::between(device, sent + round_trip / 2))
+    }
+
+    /// Query the capabilities of the target device and replace the cached value.
+    ///
+    /// Subsequent calls to [`Client::capabilities_cached`] on this [`Client`] and
+    /// all of its clones return the refreshed capabilities.
+    pub async fn refresh_capabilities(&mut self) -> Result<Capabilities, GinmiError> {
+        let cache = Arc::clone(&self.capabilities_cache);
+        let mut cached = cache.lock().await;
+
+        let capabilities = self.capabilities().await?;
+        *cached = Some(capabilities.clone());
+        Ok(capabilities)
+    }
+}
+
+#[derive(Clone)]
+pub struct Credentials<'a> {
+    pub(crate) username: Cow<'a, str>,
+    pub(crate) password: Cow<'a, str>,
+}
+
+impl fmt::Debug for Credentials<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &"***")
+            .finish()
+    }
+}
+
+impl Credentials<'_> {
+    /// Convert the credentials into values that can be sent as gRPC metadata.
+    pub(crate) fn to_metadata(
+        &self,
+    ) -> Result<(AsciiMetadataValue, AsciiMetadataValue), GinmiError> {
+        Ok((
+            metadata_value("username", &self.username)?,
+            metadata_value("password", &self.password)?,
+        ))
+    }
+}
+
+/// Connect to `endpoints`, binding the socket to `local_address` if set and resolving host
+/// names with `resolver`, and return the channel together with the state of its connection.
+///
+/// Multiple endpoints are balanced by a channel that connects lazily. The state of their
+/// connections is not tracked and their attempts are not delayed by `backoff`. If `tls` is
+/// set, the TLS session is established by the connector instead of tonic, which is only
+/// supported for a single endpoint.
+async fn connect(
+    endpoints: &[Endpoint],
+    local_address: Option<IpAddr>,
+    resolver: StaticResolver,
+    backoff: Option<(Duration, Duration)>,
+    tls: Option<&TlsSession>,
+) -> Result<(Channel, Option<watch::Receiver<ConnectivityState>>), tonic::transport::Error> {
+    let endpoint = match endpoints {
+        [endpoint] => endpoint,
+        _ => return Ok((Channel::balance_list(endpoints.iter().cloned()), None)),
+    };
+
+    // mirror the connector that tonic creates by default
+    let mut http = HttpConnector::new_with_resolver(resolver);
+    http.enforce_http(false);
+    http.set_nodelay(true);
+    http.set_local_address(local_address);
+
+    let (channel, connectivity) = match tls {
+        Some(tls) => {
+            let connector = Backoff::new(tls.connector(http), backoff);
+            let (connector, connectivity) = ConnectivityConnector::new(connector);
+            let channel = endpoint.connect_with_connector(connector).await?;
+            (channel, connectivity)
+        }
+        None => {
+            let (connector, connectivity) = ConnectivityConnector::new(Backoff::new(http, backoff));
+            let channel = endpoint.connect_with_connector(connector).await?;
+            (channel, connectivity)
+        }
+    };
+    Ok((channel, Some(connectivity)))
+}
+
+/// Echo the correlation id of a request into the metadata of its response, or of the status
+/// it failed with, so the caller learns the id of every request, even of concurrent ones.
+pub(crate) fn echo_request_id<T>(
+    result: Result<tonic::Response<T>, tonic::Status>,
+    id: Option<AsciiMetadataValue>,
+) -> Result<tonic::Response<T>, tonic::Status> {
+    let id = match id {
+        Some(id) => id,
+        None => return result,
+    };
+
+    match result {
+        Ok(mut response) => {
+            response.metadata_mut().insert(REQUEST_ID_HEADER, id);
+            Ok(response)
+        }
+        Err(mut status) => {
+            status.metadata_mut().insert(REQUEST_ID_HEADER, id);
+            Err(status)
+        }
+    }
+}
+
+/// Create the semaphore that limits the number of concurrent requests, if any.
+pub(crate) fn request_limit(max_concurrent_requests: Option<usize>) -> Option<Arc<Semaphore>> {
+    max_concurrent_requests.map(|limit| Arc::new(Semaphore::new(limit)))
+}
+
+/// Create the interceptor that authenticates all requests with the given credentials.
+pub(crate) fn auth_interceptor(
+    credentials: Option<Credentials<'_>>,
+) -> Result<AuthInterceptor, GinmiError> {
+    let (username, password) = match credentials {
+        Some(c) => {
+            let (username, password) = c.to_metadata()?;
+            (Some(username), Some(password))
+        }
+        None => (None, None),
+    };
+
+    Ok(AuthInterceptor::new(username, password))
+}
+
+/// Parse the names and values of headers that are added to every request.
+pub(crate) fn static_headers(
+    headers: &[(&str, &str)],
+) -> Result<Vec<(AsciiMetadataKey, AsciiMetadataValue)>, GinmiError> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            Ok((
+                AsciiMetadataKey::from_str(name)?,
+                AsciiMetadataValue::from_str(value)?,
+            ))
+        })
+        .collect()
+}
+
+/// gRPC metadata only allows visible ASCII characters, spaces and tabs. Non-ASCII characters
+/// would otherwise be sent as raw bytes and line breaks would corrupt the header.
+fn metadata_value(name: &str, value: &str) -> Result<AsciiMetadataValue, GinmiError> {
+    let error = || {
+        GinmiError::InvalidCredentialsError(format!(
+            "{} must only contain visible ASCII characters because gRPC metadata is ASCII, \
+             consider percent-encoding it or using an ASCII token instead",
+            name
+        ))
+    };
+
+    if !value.is_ascii() {
+        return Err(error());
+    }
+    AsciiMetadataValue::from_str(value).map_err(|_| error())
+}
+
+/// Builder for [`Client`]s
+///
+/// Used to configure and create instances of [`Client`].
+#[derive(Debug, Clone)]
+pub struct ClientBuilder<'a> {
+    pub(crate) target: Cow<'a, str>,
+    targets: Vec<&'a str>,
+    pub(crate) creds: Option<Credentials<'a>>,
+    pub(crate) subscribe_buffer: usize,
+    pub(crate) origin: Option<&'a str>,
+    authority: Option<&'a str>,
+    pub(crate) local_address: Option<IpAddr>,
+    pub(crate) resolver: StaticResolver,
+    pub(crate) user_agent: Option<&'a str>,
+    pub(crate) headers: Vec<(&'a str, &'a str)>,
+    pub(crate) correlation_ids: bool,
+    pub(crate) debug_capture: bool,
+    reconnect_backoff: Option<(Duration, Duration)>,
+    pub(crate) default_encoding: Encoding,
+    pub(crate) max_concurrent_requests: Option<usize>,
+    pub(crate) tls_settings: Option<ClientTlsConfig>,
+    tls_domain_from_target: bool,
+    pub(crate) tls_ca: Option<Vec<u8>>,
+    tls_domain: Option<String>,
+    pub(crate) tls_options: TlsOptions,
+}
+
+impl ClientBuilder<'static> {
+    /// Create a [`ClientBuilder`] that is configured by environment variables, e.g. for
+    /// collectors deployed in containers. The builder can then be customized further.
+    ///
+    /// | Variable          | Setting                                                    |
+    /// |-------------------|------------------------------------------------------------|
+    /// | `GNMI_TARGET`     | URI of the target device, required                         |
+    /// | `GNMI_USERNAME`   | Username, requires `GNMI_PASSWORD`                         |
+    /// | `GNMI_PASSWORD`   | Password, requires `GNMI_USERNAME`                         |
+    /// | `GNMI_TLS_CA`     | Path of the PEM encoded CA certificate to verify TLS with  |
+    /// | `GNMI_TLS_DOMAIN` | Domain name to verify, defaults to the host of the target  |
+    /// | `GNMI_INSECURE`   | `true` or `1` to connect without TLS                       |
+    ///
+    /// Unless `GNMI_INSECURE` is set, the target must use `https`, and the certificate of the
+    /// target device is verified against `GNMI_TLS_CA`, or against the trust store of the
+    /// operating system if no CA is given, see [`ClientBuilder::tls_native_roots`]. With
+    /// `GNMI_INSECURE`, the target must use `http`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::ClientBuilder;
+    /// # tokio_test::block_on(async {
+    /// let mut client = ClientBuilder::from_env()
+    ///     .unwrap()
+    ///     .user_agent("collector/1.0")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::EnvironmentError`] if `GNMI_TARGET` is not set, only one of
+    ///   `GNMI_USERNAME` and `GNMI_PASSWORD` is set, `GNMI_INSECURE` is not a boolean, does
+    ///   not match the scheme of `GNMI_TARGET` or is combined with `GNMI_TLS_CA` or
+    ///   `GNMI_TLS_DOMAIN`, or the CA certificate can not be read.
+    pub fn from_env() -> Result<Self, GinmiError> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Configure the builder with the variables returned by `var`.
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, GinmiError> {
+        let error = |message: String| GinmiError::EnvironmentError(message);
+
+        let target = var("GNMI_TARGET").ok_or_else(|| error("GNMI_TARGET is not set".into()))?;
+        let uri = Uri::from_str(&target).ok();
+        let https = uri.as_ref().and_then(Uri::scheme_str) == Some("https");
+        let mut builder = ClientBuilder::new("");
+        builder.target = target.into();
+
+        match (var("GNMI_USERNAME"), var("GNMI_PASSWORD")) {
+            (Some(username), Some(password)) => {
+                builder.creds = Some(Credentials {
+                    username: username.into(),
+                    password: password.into(),
+                });
+            }
+            (None, None) => {}
+            _ => {
+                return Err(error(
+                    "GNMI_USERNAME and GNMI_PASSWORD must be set together".into(),
+                ))
+            }
+        }
+
+        let insecure = match var("GNMI_INSECURE").as_deref() {
+            None | Some("") | Some("0") | Some("false") => false,
+            Some("1") | Some("true") => true,
+            Some(value) => {
+                return Err(error(format!(
+                    "GNMI_INSECURE must be true or false, not {}",
+                    value
+                )))
+            }
+        };
+
+        // tonic only uses TLS for targets that use https, regardless of the TLS-Settings
+        match (insecure, https) {
+            (true, true) => {
+                return Err(error(
+                    "GNMI_INSECURE can not be combined with a GNMI_TARGET that uses https".into(),
+                ))
+            }
+            (false, false) => {
+                return Err(error(format!(
+                    "GNMI_TARGET {} must use https unless GNMI_INSECURE is set",
+                    builder.target
+                )))
+            }
+            _ => {}
+        }
+
+        let domain = var("GNMI_TLS_DOMAIN");
+        match (var("GNMI_TLS_CA"), insecure) {
+            (Some(_), true) => Err(error(
+                "GNMI_TLS_CA can not be combined with GNMI_INSECURE".into(),
+            )),
+            (Some(path), false) => {
+                let ca = std::fs::read(&path)
+                    .map_err(|e| error(format!("can not read GNMI_TLS_CA {}: {}", path, e)))?;
+                Ok(match domain {
+                    Some(domain) => builder.tls(ca, domain),
+                    None => builder.tls_ca_only(ca),
+                })
+            }
+            (None, true) if domain.is_some() => Err(error(
+                "GNMI_TLS_DOMAIN can not be combined with GNMI_INSECURE".into(),
+            )),
+            (None, true) => Ok(builder),
+            (None, false) => Ok(match domain {
+                Some(domain) => builder.tls_native_roots_for(&domain),
+                None => builder.tls_native_roots(),
+            }),
+        }
+    }
+}
+
+impl<'a> ClientBuilder<'a> {
+    pub fn new(target: &'a str) -> Self {
+        Self {
+            target: target.into(),
+            targets: Vec::new(),
+            creds: None,
+            subscribe_buffer: DEFAULT_SUBSCRIBE_BUFFER,
+            origin: None,
+            authority: None,
+            local_address: None,
+            resolver: StaticResolver::default(),
+            user_agent: None,
+            headers: Vec::new(),
+            correlation_ids: false,
+            debug_capture: false,
+            reconnect_backoff: None,
+            default_encoding: DEFAULT_ENCODING,
+            max_concurrent_requests: None,
+            tls_settings: None,
+            tls_domain_from_target: false,
+            tls_ca: None,
+            tls_domain: None,
+            tls_options: TlsOptions::default(),
+        }
+    }
+
+    /// Connect to all of the given targets instead of the target passed to
+    /// [`Client::builder`], e.g. redundant gNMI gateways in front of the same devices.
+    ///
+    /// Requests are balanced across all targets that are reachable, all other settings of
+    /// the builder apply to each of them. With multiple targets, the connections are
+    /// established lazily on first use.
+    pub fn targets(mut self, targets: &[&'a str]) -> Self {
+        self.targets = targets.to_vec();
+        self
+    }
+
+    /// Configure credentials to use for connecting to the target device.
+    pub fn credentials(mut self, username: &'a str, password: &'a str) -> Self {
+        self.creds = Some(Credentials {
+            username: username.into(),
+            password: password.into(),
+        });
+        self
+    }
+
+    /// Configure TLS to use for connecting to the target device.
+    ///
+    /// The domain name may also be an IP address, IPv6 addresses may be enclosed in brackets.
+    pub fn tls(mut self, ca_certificate: impl AsRef<[u8]>, domain_name: impl Into<String>) -> Self {
+        let ca_certificate = ca_certificate.as_ref().to_vec();
+        let domain_name = domain_name.into();
+        let settings = ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(&ca_certificate))
+            .domain_name(unbracket(&domain_name));
+        self.tls_settings = Some(settings);
+        self.tls_domain_from_target = false;
+        self.tls_ca = Some(ca_certificate);
+        self.tls_domain = Some(unbracket(&domain_name).to_string());
+        self
+    }
+
+    /// Configure TLS to use for connecting to the target device, verifying its certificate
+    /// against the given CA certificate.
+    ///
+    /// Unlike [`ClientBuilder::tls`], the domain name is taken from the target URI.
+    pub fn tls_ca_only(mut self, ca_certificate: impl AsRef<[u8]>) -> Self {
+        let ca_certificate = ca_certificate.as_ref().to_vec();
+        let cert = Certificate::from_pem(&ca_certificate);
+        self.tls_settings = Some(ClientTlsConfig::new().ca_certificate(cert));
+        self.tls_domain_from_target = true;
+        self.tls_ca = Some(ca_certificate);
+        self.tls_domain = None;
+        self
+    }
+
+    /// Override the origin that requests are addressed to, i.e. the `:authority` of the
+    /// HTTP/2 requests, while connecting to the target.
+    ///
+    /// Useful if the target device is reached through an API gateway or load balancer,
+    /// e.g. Envoy, that routes requests based on their authority.
+    pub fn origin(mut self, origin: &'a str) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Override only the `:authority` of the requests, e.g. `router1.example.com:57400`,
+    /// keeping the scheme of the origin or target.
+    ///
+    /// The connection is still established to the address of the target, and the name that
+    /// is used for TLS is set with [`ClientBuilder::tls`]. This allows to configure all three
+    /// independently, e.g. to dial an IP address of a gateway that routes on the authority,
+    /// while the certificate is issued for yet another name.
+    pub fn authority(mut self, authority: &'a str) -> Self {
+        self.authority = Some(authority);
+        self
+    }
+
+    /// Bind the connection to the target device to a local address, e.g. to send it from a
+    /// specific interface of a multi-homed host.
+    ///
+    /// Not supported in combination with [`ClientBuilder::targets`].
+    pub fn bind_local_address(mut self, address: IpAddr) -> Self {
+        self.local_address = Some(address);
+        self
+    }
+
+    /// Connect to `address` whenever the target is addressed by `host`, instead of resolving
+    /// `host` via DNS, e.g. for lab devices whose certificate is issued for a name that does
+    /// not resolve.
+    ///
+    /// The name is still used for TLS and the `:authority` of the requests, so the target can
+    /// be given by the name of its certificate while connecting to a known IP address. Host
+    /// names are compared case-insensitively. As with DNS, the port of the target is used and
+    /// the port of `address` is ignored. Not supported in combination with
+    /// [`ClientBuilder::targets`].
+    pub fn resolve(mut self, host: &str, address: SocketAddr) -> Self {
+        self.resolver.insert(host, address);
+        self
+    }
+
+    /// Set the `user-agent` that is sent to the target device, e.g. for proxies that only
+    /// admit known clients.
+    pub fn user_agent(mut self, user_agent: &'a str) -> Self {
+        self.user_agent = Some(user_agent);
+        self
+    }
+
+    /// Add a header with the given name and value to the metadata of every request.
+    pub fn header(mut self, name: &'a str, value: &'a str) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    /// Add a newly generated UUID as `x-request-id` header to every request, e.g. to trace
+    /// requests across a gNMI proxy and the target device. Disabled by default.
+    ///
+    /// The id of each request is echoed as `x-request-id` into the metadata returned by
+    /// [`Client::capabilities_with_metadata`], [`GetRequestBuilder::send_with_metadata`],
+    /// [`SetRequestBuilder::send_with_metadata`] and [`SubscribeStream::metadata`], and into
+    /// the metadata of the status of a failed request, see [`GinmiError::status`].
+    pub fn correlation_ids(mut self, enabled: bool) -> Self {
+        self.correlation_ids = enabled;
+        self
+    }
+
+    /// Keep the encoded protobuf message of the last request, so it can be inspected with
+    /// [`Client::last_request_bytes`] if the target device rejects it. Disabled by default,
+    /// as every request is encoded twice.
+    pub fn debug_capture(mut self, enabled: bool) -> Self {
+        self.debug_capture = enabled;
+        self
+    }
+
+    /// Delay attempts to reconnect to the target device after a failed attempt, starting
+    /// with `min` and doubling the delay after each further failure up to `max`.
+    ///
+    /// By default, the connection is established again with the next request without any
+    /// delay. A backoff avoids reconnection storms if many clients connect to a flapping
+    /// device. Only applies to clients with a single target. `max` is raised to `min` if it
+    /// is smaller.
+    pub fn reconnect_backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.reconnect_backoff = Some((min, max.max(min)));
+        self
+    }
+
+    /// Set the [`Encoding`] of get requests and subscriptions that do not set one. Defaults
+    /// to [`DEFAULT_ENCODING`].
+    ///
+    /// The encoding can still be overridden per call with [`GetRequestBuilder::encoding`]
+    /// and [`SubscriptionBuilder::encoding`](super::SubscriptionBuilder::encoding).
+    pub fn default_encoding(mut self, encoding: Encoding) -> Self {
+        self.default_encoding = encoding;
+        self
+    }
+
+    /// Allow at most `limit` requests of the client and all of its clones to be in flight at
+    /// the same time, e.g. for devices that crash under parallel load. Further requests wait
+    /// until one of them has completed. Unlimited by default.
+    ///
+    /// Subscriptions count as in flight until the target device accepted them, not while
+    /// they are active. A limit of 0 is treated as 1.
+    pub fn max_concurrent_requests(mut self, limit: usize) -> Self {
+        self.max_concurrent_requests = Some(limit.max(1));
+        self
+    }
+
+    /// Configure how many responses of a subscription are buffered if they are not consumed
+    /// as fast as the target device sends them. Defaults to [`DEFAULT_SUBSCRIBE_BUFFER`].
+    ///
+    /// Once the buffer is full, no more responses are read from the connection, so HTTP/2
+    /// flow control slows down the target device instead of buffering an unbounded number
+    /// of responses in memory. A size of 0 is treated as 1.
+    pub fn subscribe_buffer(mut self, size: usize) -> Self {
+        self.subscribe_buffer = size.max(1);
+        self
+    }
+
+    /// Configure TLS to use for connecting to the target device, verifying its certificate
+    /// against the trust store of the operating system.
+    ///
+    /// Use this instead of [`ClientBuilder::tls`] if the target device uses a certificate
+    /// signed by a publicly trusted CA. The domain name is taken from the target URI.
+    pub fn tls_native_roots(mut self) -> Self {
+        // tonic loads the native roots into every TLS config because of its tls-roots feature
+        self.tls_settings = Some(ClientTlsConfig::new());
+        self.tls_domain_from_target = true;
+        self.tls_ca = None;
+        self.tls_domain = None;
+        self
+    }
+
+    /// Like [`ClientBuilder::tls_native_roots`], but verify the certificate against `domain`
+    /// instead of the host of the target URI.
+    fn tls_native_roots_for(mut self, domain: &str) -> Self {
+        let domain = unbracket(domain);
+        self.tls_settings = Some(ClientTlsConfig::new().domain_name(domain));
+        self.tls_domain_from_target = false;
+        self.tls_ca = None;
+        self.tls_domain = Some(domain.to_string());
+        self
+    }
+
+    /// Set the oldest TLS version that may be negotiated with the target device.
+    ///
+    /// By default, TLS 1.2 and TLS 1.3 are allowed. Use [`version::TLS13`] to allow
+    /// TLS 1.3 only. The certificate of the target device is still verified against the CA
+    /// given to [`ClientBuilder::tls`] and the trust store of the operating system. Requires
+    /// a target that uses `https` and is not supported in combination with
+    /// [`ClientBuilder::targets`].
+    ///
+    /// [`version::TLS13`]: super::tls::version::TLS13
+    pub fn tls_min_version(mut self, version: &'static SupportedProtocolVersion) -> Self {
+        self.tls_options.min_version = Some(version);
+        self
+    }
+
+    /// Restrict the cipher suites that may be negotiated with the target device, in order of
+    /// preference. The available cipher suites are contained in
+    /// [`cipher_suite`](super::tls::cipher_suite).
+    ///
+    /// Has the same requirements as [`ClientBuilder::tls_min_version`].
+    pub fn tls_cipher_suites(mut self, suites: &[SupportedCipherSuite]) -> Self {
+        self.tls_options.cipher_suites = Some(suites.to_vec());
+        self
+    }
+
+    #[cfg(feature = "dangerous_configuration")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dangerous_configuration")))]
+    /// Access configuration options that are dangerous and require extra care.
+    pub fn dangerous(self) -> DangerousClientBuilder<'a> {
+        DangerousClientBuilder::from(self)
+    }
+
+    #[cfg(feature = "dangerous_configuration")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dangerous_configuration")))]
+    /// Use TLS and accept only the certificate of the target device with the given SHA-256
+    /// `fingerprint`, e.g. for devices with self-signed certificates whose CA can not be
+    /// installed. Shorthand for [`DangerousClientBuilder::tls_pin_sha256`].
+    pub fn tls_pin_sha256(self, fingerprint: &str) -> DangerousClientBuilder<'a> {
+        self.dangerous().tls_pin_sha256(fingerprint)
+    }
+
+    #[cfg(feature = "grpc_web")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "grpc_web")))]
+    /// Use gRPC-Web over HTTP/1.1 instead of gRPC over HTTP/2, e.g. for proxies that do not
+    /// support HTTP/2. See the [`grpc_web`](super::grpc_web) module for its limitations.
+    pub fn grpc_web(self) -> GrpcWebClientBuilder<'a> {
+        GrpcWebClientBuilder::from(self)
+    }
+
+    /// Check the settings of the builder without connecting to the target device, e.g. to
+    /// report invalid user input right away instead of after a slow connection attempt.
+    ///
+    /// Performs all checks of [`ClientBuilder::build`] except for establishing the
+    /// connection, so `build` can then only fail to connect.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::Client;
+    /// let builder = Client::builder("https://clab-srl01-srl:57400").credentials("admin", "ädmin");
+    /// assert!(builder.validate().is_err());
+    /// ```
+    ///
+    /// # Errors
+    /// - Returns the first error [`ClientBuilder::build`] would return before connecting.
+    pub fn validate(&self) -> Result<(), GinmiError> {
+        self.prepare().map(|_| ())
+    }
+
+    /// Establish a connection to every target with the settings of the builder, without
+    /// sending any request, and close it again, e.g. to check that the target device is
+    /// reachable before looking into its credentials.
+    ///
+    /// Succeeds once the TCP connection and, if TLS is configured, the TLS handshake have
+    /// completed, so the credentials are not checked at all. Use
+    /// [`Client::authenticate_check`] to check them.
+    ///
+    /// # Errors
+    /// - Returns the first error [`ClientBuilder::build`] would return before connecting.
+    /// - Returns [`GinmiError::TransportError`] if a connection to a target could not be
+    ///   established.
+    pub async fn connect_only(&self) -> Result<(), GinmiError> {
+        let (endpoints, tls_session, _) = self.prepare()?;
+        for endpoint in &endpoints {
+            connect(
+                std::slice::from_ref(endpoint),
+                self.local_address,
+                self.resolver.clone(),
+                None,
+                tls_session.as_ref(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Consume the [`ClientBuilder`] and return a [`Client`].
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::InvalidUriError`] if specified target is not a valid URI.
+    /// - Returns [`GinmiError::InvalidUriError`] if specified origin is not a valid URI.
+    /// - Returns [`GinmiError::InvalidUriError`] if specified authority is not valid.
+    /// - Returns [`GinmiError::InvalidUriError`] if the domain name for TLS is taken from the
+    ///   target, but the target has no host name.
+    /// - Returns [`GinmiError::InvalidUriError`] if a local address is bound with multiple
+    ///   targets.
+    /// - Returns [`GinmiError::InvalidUriError`] if hosts are [resolved](ClientBuilder::resolve)
+    ///   statically with multiple targets.
+    /// - Returns [`GinmiError::InvalidCredentialsError`] if the credentials contain characters
+    ///   that are not allowed in gRPC metadata.
+    /// - Returns [`GinmiError::InvalidHeaderName`] or [`GinmiError::InvalidHeaderValue`] if a
+    ///   header is not valid gRPC metadata.
+    /// - Returns [`GinmiError::TlsConfigError`] if TLS has been configured, but a target does
+    ///   not use `https`.
+    /// - Returns [`GinmiError::TransportError`] if the TLS-Settings or the user agent are
+    ///   invalid.
+    /// - Returns [`GinmiError::TransportError`] if a connection to the target could not be
+    ///   established.
+    pub async fn build(
+        self,
+    ) -> Result<Client<TonicTransport<InterceptedService<Channel, AuthInterceptor>>>, GinmiError>
+    {
+        self.connecting().await
+    }
+
+    /// Consume the [`ClientBuilder`] and return the future that connects the [`Client`],
+    /// which, unlike [`ClientBuilder::build`], does not borrow from the builder and can be
+    /// spawned as a task.
+    pub(crate) fn connecting(
+        self,
+    ) -> impl Future<
+        Output = Result<
+            Client<TonicTransport<InterceptedService<Channel, AuthInterceptor>>>,
+            GinmiError,
+        >,
+    > + Send
+           + 'static {
+        let prepared = self.prepare();
+        let ClientBuilder {
+            subscribe_buffer,
+            local_address,
+            resolver,
+            debug_capture,
+            reconnect_backoff,
+            default_encoding,
+            max_concurrent_requests,
+            ..
+        } = self;
+
+        async move {
+            let (endpoints, tls_session, auth) = prepared?;
+            let security = match tls_session {
+                Some(_) => ConnectionSecurity::Tls,
+                None => endpoint_security(&endpoints),
+            };
+
+            let (channel, connectivity) = connect(
+                &endpoints,
+                local_address,
+                resolver.clone(),
+                reconnect_backoff,
+                tls_session.as_ref(),
+            )
+            .await?;
+
+            Ok(Client {
+                inner: TonicTransport::new(GNmiClient::with_interceptor(channel, auth.clone())),
+                capabilities_cache: Arc::new(Mutex::new(None)),
+                security,
+                auth,
+                subscribe_buffer,
+                endpoints,
+                local_address,
+                resolver,
+                captured_request: debug_capture.then(Default::default),
+                connectivity,
+                reconnect_backoff,
+                default_encoding,
+                request_limit: request_limit(max_concurrent_requests),
+                tls_details: tls_session
+                    .as_ref()
+                    .map(TlsSession::details)
+                    .unwrap_or_default(),
+                tls_session,
+            })
+        }
+    }
+
+    /// Create the endpoints of all targets, the TLS session that is established without
+    /// tonic if the TLS versions or cipher suites are restricted, and the interceptor that
+    /// authenticates requests.
+    fn prepare(&self) -> Result<(Vec<Endpoint>, Option<TlsSession>, AuthInterceptor), GinmiError> {
+        let targets = if self.targets.is_empty() {
+            vec![self.target.as_ref()]
+        } else {
+            self.targets.clone()
+        };
+
+        if targets.len() > 1 && self.local_address.is_some() {
+            return Err(GinmiError::InvalidUriError(
+                "binding a local address is not supported with multiple targets".to_string(),
+            ));
+        }
+        if targets.len() > 1 && !self.resolver.is_empty() {
+            return Err(GinmiError::InvalidUriError(
+                "static host overrides are not supported with multiple targets".to_string(),
+            ));
+        }
+
+        if targets.len() > 1 && self.tls_options.is_restricted() {
+            return Err(GinmiError::TlsConfigError(
+                "restricting TLS versions or cipher suites is not supported with multiple targets"
+                    .to_string(),
+            ));
+        }
+
+        let endpoints = targets
+            .iter()
+            .map(|target| self.endpoint(target))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let tls_session = if self.tls_options.is_restricted() {
+            Some(self.tls_session(targets[0])?)
+        } else {
+            None
+        };
+
+        let auth = auth_interceptor(self.creds.clone())?
+            .with_headers(static_headers(&self.headers)?)
+            .with_correlation_ids(self.correlation_ids);
+
+        Ok((endpoints, tls_session, auth))
+    }
+
+    /// Create the TLS session to `target` that is established without tonic, e.g. to restrict
+    /// the TLS versions or cipher suites.
+    pub(crate) fn tls_session(&self, target: &str) -> Result<TlsSession, GinmiError> {
+        let uri = Uri::from_str(target).map_err(|e| GinmiError::InvalidUriError(e.to_string()))?;
+        let domain = match (&self.tls_domain, server_name(&uri)) {
+            (Some(domain), _) => domain.as_str(),
+            (None, Some(domain)) => domain,
+            (None, None) => {
+                return Err(GinmiError::InvalidUriError(format!(
+                    "target {} has no host name to verify the TLS certificate against",
+                    target
+                )))
+            }
+        };
+
+        let roots = root_store(self.tls_ca.as_deref())?;
+        TlsSession::new(self.tls_options.client_config(roots)?, domain)
+    }
+
+    /// Returns the URI requests are addressed to, if it differs from `target`.
+    pub(crate) fn origin_uri(&self, target: &Uri) -> Result<Option<Uri>, GinmiError> {
+        let invalid = |e: &dyn fmt::Display| GinmiError::InvalidUriError(e.to_string());
+
+        let origin = match self.origin {
+            Some(origin) => Some(Uri::from_str(origin).map_err(|e| invalid(&e))?),
+            None => None,
+        };
+
+        let authority = match self.authority {
+            Some(authority) => authority,
+            None => return Ok(origin),
+        };
+
+        let mut parts = origin.unwrap_or_else(|| target.clone()).into_parts();
+        parts.authority = Some(authority.parse().map_err(|e| invalid(&e))?);
+        Uri::from_parts(parts).map(Some).map_err(|e| invalid(&e))
+    }
+
+    /// Create the [`Endpoint`] of a single target with the settings of the builder.
+    fn endpoint(&self, target: &str) -> Result<Endpoint, GinmiError> {
+        let uri = match Uri::from_str(target) {
+            Ok(u) => u,
+            Err(e) => return Err(GinmiError::InvalidUriError(e.to_string())),
+        };
+
+        let domain = match server_name(&uri) {
+            Some(domain) => domain.to_string(),
+            None if self.tls_domain_from_target => {
+                return Err(GinmiError::InvalidUriError(format!(
+                    "target {} has no host name to verify the TLS certificate against",
+                    target
+                )))
+            }
+            None => String::new(),
+        };
+
+        // tonic ignores the TLS-Settings for targets that use `http`
+        let restricted = self.tls_options.is_restricted();
+        if (self.tls_settings.is_some() || restricted) && uri.scheme_str() != Some("https") {
+            return Err(GinmiError::TlsConfigError(format!(
+                "TLS is configured, but target {} does not use https",
+                target
+            )));
+        }
+
+        let mut origin = self.origin_uri(&uri)?;
+        let mut endpoint = if restricted {
+            // the TLS session is established by the connector, so tonic connects with
+            // plaintext, while the requests are still addressed to the https target
+            let mut parts = uri.clone().into_parts();
+            parts.scheme = Some(http::uri::Scheme::HTTP);
+            let plaintext =
+                Uri::from_parts(parts).map_err(|e| GinmiError::InvalidUriError(e.to_string()))?;
+            origin = Some(origin.unwrap_or(uri));
+            Channel::builder(plaintext)
+        } else {
+            Channel::builder(uri)
+        };
+
+        if let Some(origin) = origin {
+            endpoint = endpoint.origin(origin);
+        }
+
+        match &self.tls_settings {
+            Some(tls_settings) if !restricted => {
+                let mut tls_settings = tls_settings.clone();
+                if self.tls_domain_from_target {
+                    tls_settings = tls_settings.domain_name(domain);
+                }
+                endpoint = endpoint.tls_config(tls_settings)?;
+            }
+            _ => {}
+        }
+
+        if let Some(user_agent) = self.user_agent {
+            endpoint = endpoint.user_agent(user_agent)?;
+        }
+
+        Ok(endpoint)
+    }
+}
+
+/// Security of the connections to `endpoints`, which is only [`ConnectionSecurity::Tls`] if
+/// all of them use `https`.
+///
+/// tonic uses TLS for every `https` target, verifying its certificate against the trust store
+/// of the operating system if no TLS-Settings are configured, and plaintext for all others.
+fn endpoint_security(endpoints: &[Endpoint]) -> ConnectionSecurity {
+    if endpoints
+        .iter()
+        .all(|endpoint| endpoint.uri().scheme_str() == Some("https"))
+    {
+        ConnectionSecurity::Tls
+    } else {
+        ConnectionSecurity::Insecure
+    }
+}
+
+/// Host of `uri` to verify the TLS certificate of the target device against.
+fn server_name(uri: &Uri) -> Option<&str> {
+    uri.host().map(unbracket).filter(|host| !host.is_empty())
+}
+
+/// Strip the brackets around an IPv6 address, which are part of the host of a URI but not
+/// of a TLS server name.
+fn unbracket(host: &str) -> &str {
+    host.strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::lazy_client;
+
+    fn assert_send<T: Send + 'static>(_: &T) {}
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn spawn_subscription() {
+        let mut client = lazy_client();
+        assert_send(&client);
+
+        let task = tokio::spawn(async move {
+            let subscriptions = SubscriptionList::builder()
+                .subscription(crate::client::Subscription::new(
+                    "/system/name".parse().unwrap(),
+                ))
+                .build()
+                .unwrap();
+
+            let mut stream = client.subscribe(subscriptions).await?;
+            assert_send(&stream);
+            while let Some(response) = stream.next().await {
+                response?;
+            }
+            Ok::<_, GinmiError>(())
+        });
+
+        // nothing is listening on the target, but the task has run on the runtime
+        assert!(task.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn invalid_uri() {
+        let client =
+            Client::<TonicTransport<InterceptedService<Channel, AuthInterceptor>>>::builder("$$$$")
+                .build()
+                .await;
+        assert!(client.is_err());
+    }
+
+    #[tokio::test]
+    async fn tls_without_host_name() {
+        let client = Client::builder("/gnmi")
+            .tls_ca_only("CA Certificate")
+            .build()
+            .await;
+        assert!(matches!(client, Err(GinmiError::InvalidUriError(_))));
+    }
+
+    #[tokio::test]
+    async fn debug_capture() {
+        let mut client = lazy_client();
+        assert_eq!(client.last_request_bytes(), None);
+
+        client.captured_request = Some(Default::default());
+        let path: Path = "/system/name".parse().unwrap();
+        // nothing is listening on the target, but the request is captured anyway
+        let _ = client.get().path(path.clone()).send().await;
+
+        let bytes = client.last_request_bytes().unwrap();
+        let request = crate::gen::gnmi::GetRequest::decode(bytes.as_slice()).unwrap();
+        assert_eq!(request.path, vec![path.into()]);
+    }
+
+    #[tokio::test]
+    async fn default_encoding() {
+        let mut client = lazy_client();
+        client.captured_request = Some(Default::default());
+        client.default_encoding = Encoding::Proto;
+        let path: Path = "/system/name".parse().unwrap();
+        let requested_encoding = |bytes: Vec<u8>| {
+            crate::gen::gnmi::GetRequest::decode(bytes.as_slice())
+                .unwrap()
+                .encoding
+        };
+
+        let _ = client.get().path(path.clone()).send().await;
+        let bytes = client.last_request_bytes().unwrap();
+        assert_eq!(requested_encoding(bytes), Encoding::Proto as i32);
+
+        let _ = client
+            .get()
+            .path(path.clone())
+            .encoding(Encoding::Json)
+            .send()
+            .await;
+        let bytes = client.last_request_bytes().unwrap();
+        assert_eq!(requested_encoding(bytes), Encoding::Json as i32);
+
+        let subscribed_encoding =
+            |bytes: Vec<u8>| match SubscribeRequest::decode(bytes.as_slice()).unwrap().request {
+                Some(Request::Subscribe(list)) => list.encoding,
+                _ => panic!("not a subscription"),
+            };
+        let subscription = || crate::client::Subscription::new(path.clone());
+
+        let list = SubscriptionList::builder()
+            .subscription(subscription())
+            .build()
+            .unwrap();
+        assert_eq!(list.encoding(), DEFAULT_ENCODING);
+        let _ = client.subscribe(list).await;
+        let bytes = client.last_request_bytes().unwrap();
+        assert_eq!(subscribed_encoding(bytes), Encoding::Proto as i32);
+
+        let list = SubscriptionList::builder()
+            .subscription(subscription())
+            .encoding(Encoding::Json)
+            .build()
+            .unwrap();
+        let _ = client.subscribe(list).await;
+        let bytes = client.last_request_bytes().unwrap();
+        assert_eq!(subscribed_encoding(bytes), Encoding::Json as i32);
+    }
+
+    /// Transport that answers every capability request and subscription with the same
+    /// responses and rejects all other requests.
+    #[derive(Clone, Default)]
+    struct Fake {
+        capabilities: crate::gen::gnmi::CapabilityResponse,
+        subscribe: Vec<crate::gen::gnmi::SubscribeResponse>,
+    }
+
+    #[tonic::async_trait]
+    impl GnmiTransport for Fake {
+        async fn capabilities(
+            &mut self,
+            _: tonic::Request<CapabilityRequest>,
+        ) -> Result<tonic::Response<crate::gen::gnmi::CapabilityResponse>, tonic::Status> {
+            Ok(tonic::Response::new(self.capabilities.clone()))
+        }
+
+        async fn get(
+            &mut self,
+            _: tonic::Request<crate::gen::gnmi::GetRequest>,
+        ) -> Result<tonic::Response<crate::gen::gnmi::GetResponse>, tonic::Status> {
+            Err(tonic::Status::unimplemented("get"))
+        }
+
+        async fn set(
+            &mut self,
+            _: tonic::Request<crate::gen::gnmi::SetRequest>,
+        ) -> Result<tonic::Response<crate::gen::gnmi::SetResponse>, tonic::Status> {
+            Err(tonic::Status::unimplemented("set"))
+        }
+
+        async fn subscribe(
+            &mut self,
+            _: tonic::Request<BoxStream<SubscribeRequest>>,
+        ) -> Result<
+            tonic::Response<BoxStream<Result<crate::gen::gnmi::SubscribeResponse, tonic::Status>>>,
+            tonic::Status,
+        > {
+            let responses = self.subscribe.clone().into_iter().map(Ok);
+            Ok(tonic::Response::new(Box::pin(tokio_stream::iter(
+                responses,
+            ))))
+        }
+    }
+
+    #[tokio::test]
+    async fn snapshot_subscribes_once() {
+        let response = crate::gen::gnmi::SubscribeResponse {
+            response: Some(crate::gen::gnmi::subscribe_response::Response::Update(
+                crate::gen::gnmi::Notification {
+                    timestamp: 42,
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        };
+
+        let mut client = Client::from_transport(Fake {
+            subscribe: vec![response],
+            ..Default::default()
+        });
+        client.captured_request = Some(Default::default());
+        let notifications = client
+            .snapshot("/system/name".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            notifications
+                .iter()
+                .map(|n| n.timestamp)
+                .collect::<Vec<_>>(),
+            [42]
+        );
+
+        let bytes = client.last_request_bytes().unwrap();
+        let request = SubscribeRequest::decode(bytes.as_slice()).unwrap();
+        let Some(Request::Subscribe(list)) = request.request else {
+            panic!("expected a subscription list");
+        };
+        assert_eq!(list.mode, SubscriptionListMode::Once as i32);
+        assert_eq!(list.subscription.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_are_limited() {
+        let mut client = Client::from_transport(Fake::default());
+        let limit = request_limit(Some(1)).unwrap();
+        client.request_limit = Some(Arc::clone(&limit));
+
+        let permit = limit.acquire().await.unwrap();
+        let mut other = client.clone();
+        let blocked = tokio::time::timeout(Duration::from_millis(50), other.capabilities());
+        assert!(blocked.await.is_err());
+
+        drop(permit);
+        assert!(client.capabilities().await.is_ok());
+        assert_eq!(limit.available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn poll_subtree_until_dropped() {
+        let client = lazy_client();
+        let interval = Duration::from_millis(50);
+
+        let start = tokio::time::Instant::now();
+        let mut snapshots = client.poll_subtree("/system".parse().unwrap(), interval);
+        assert!(snapshots.next().await.unwrap().is_err());
+        assert!(snapshots.next().await.unwrap().is_err());
+        assert!(start.elapsed() >= interval);
+
+        drop(snapshots);
+        tokio::time::sleep(interval).await;
+        assert_eq!(Arc::strong_count(&client.capabilities_cache), 1);
+    }
+
+    #[tokio::test]
+    async fn connectivity_and_authentication_checks() {
+        let unreachable = Client::builder("http://127.0.0.1:1");
+        assert!(matches!(
+            unreachable.connect_only().await,
+            Err(GinmiError::TransportError(_))
+        ));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut accepted = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                accepted.push(socket);
+            }
+        });
+        let reachable = Client::builder(&target).credentials("admin", "admin");
+        reachable.connect_only().await.unwrap();
+
+        let mut client = Client::from_transport(Fake::default());
+        client.authenticate_check().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn fake_transport() {
+        let capabilities = crate::gen::gnmi::CapabilityResponse {
+            g_nmi_version: "0.10.0".to_string(),
+            ..Default::default()
+        };
+
+        let mut client = Client::from_transport(Fake {
+            capabilities,
+            ..Default::default()
+        });
+        let capabilities = client.capabilities().await.unwrap();
+        assert_eq!(capabilities.gnmi_version(), "0.10.0");
+        assert_eq!(client.connection_security(), ConnectionSecurity::Custom);
+    }
+
+    #[tokio::test]
+    async fn correlation_ids_are_echoed() {
+        let mut client = Client::from_transport(Fake::default());
+        client.auth = client.auth.clone().with_correlation_ids(true);
+
+        let (_, first) = client.capabilities_with_metadata().await.unwrap();
+        let (_, second) = client.capabilities_with_metadata().await.unwrap();
+        let first = first.get(REQUEST_ID_HEADER).unwrap();
+        assert_eq!(first.len(), 36);
+        assert_ne!(Some(first), second.get(REQUEST_ID_HEADER));
+
+        let list = SubscriptionList::builder()
+            .subscription(crate::client::Subscription::new(
+                "/system/name".parse().unwrap(),
+            ))
+            .build()
+            .unwrap();
+        let stream = client.subscribe(list).await.unwrap();
+        assert_eq!(stream.metadata().get(REQUEST_ID_HEADER).unwrap().len(), 36);
+
+        let id = AsciiMetadataValue::from_static("1b4e28ba-2fa1-11d2-883f-0016d3cca427");
+        let status =
+            echo_request_id::<()>(Err(tonic::Status::internal("")), Some(id.clone())).unwrap_err();
+        assert_eq!(status.metadata().get(REQUEST_ID_HEADER), Some(&id));
+
+        let (_, metadata) = Client::from_transport(Fake::default())
+            .capabilities_with_metadata()
+            .await
+            .unwrap();
+        assert!(metadata.get(REQUEST_ID_HEADER).is_none());
+    }
+
+    #[tokio::test]
+    async fn capabilities_with_extensions() {
+        let mut client = lazy_client();
+        client.captured_request = Some(Default::default());
+
+        let extension = Extension::Registered {
+            id: 42,
+            msg: b"running".to_vec(),
+        };
+        let _ = client.capabilities_with(vec![extension.clone()]).await;
+
+        let bytes = client.last_request_bytes().unwrap();
+        let request = CapabilityRequest::decode(bytes.as_slice()).unwrap();
+        assert_eq!(request.extension, vec![extension.into()]);
+    }
+
+    #[tokio::test]
+    async fn multiple_targets() {
+        let targets = ["http://gateway1:57400", "http://gateway2:57400"];
+
+        let client = Client::builder("").targets(&targets).build().await.unwrap();
+        assert_eq!(client.endpoints.len(), 2);
+
+        let client = Client::builder("")
+            .targets(&targets)
+            .bind_local_address("127.0.0.1".parse().unwrap())
+            .build()
+            .await;
+        assert!(matches!(client, Err(GinmiError::InvalidUriError(_))));
+    }
+
+    #[tokio::test]
+    async fn close_releases_connection() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = format!("http://{}", listener.local_addr().unwrap());
+
+        let client = Client::builder(&target).build().await.unwrap();
+        let mut states = client.connectivity();
+        assert_eq!(states.next().await, Some(ConnectivityState::Ready));
+
+        drop(client.clone());
+        tokio::time::timeout(Duration::from_secs(5), client.close())
+            .await
+            .unwrap();
+        let states: Vec<_> = states.collect().await;
+        assert_eq!(states.last(), Some(&ConnectivityState::Shutdown));
+    }
+
+    #[test]
+    fn validate_without_connecting() {
+        assert!(Client::builder("http://127.0.0.1:1").validate().is_ok());
+        assert!(matches!(
+            Client::builder("not a uri").validate(),
+            Err(GinmiError::InvalidUriError(_))
+        ));
+        assert!(matches!(
+            Client::builder("http://127.0.0.1:1")
+                .credentials("admin", "pass\nword")
+                .validate(),
+            Err(GinmiError::InvalidCredentialsError(_))
+        ));
+    }
+
+    #[test]
+    fn ipv6_targets() {
+        let server = |target| server_name(&Uri::from_static(target)).map(str::to_string);
+        assert_eq!(
+            server("https://[2001:db8::1]:57400"),
+            Some("2001:db8::1".to_string())
+        );
+        assert_eq!(
+            server("https://10.0.0.1:57400"),
+            Some("10.0.0.1".to_string())
+        );
+        assert_eq!(server("https://router1:57400"), Some("router1".to_string()));
+        assert_eq!(unbracket("2001:db8::1"), "2001:db8::1");
+
+        assert!(Client::builder("http://[2001:db8::1]:57400")
+            .validate()
+            .is_ok());
+        assert!(Client::builder("https://[2001:db8::1]:57400")
+            .tls_native_roots()
+            .validate()
+            .is_ok());
+
+        let target = Uri::from_static("https://[2001:db8::1]:57400");
+        let origin = Client::builder("")
+            .authority("[2001:db8::2]:57400")
+            .origin_uri(&target)
+            .unwrap();
+        assert_eq!(
+            origin,
+            Some(Uri::from_static("https://[2001:db8::2]:57400/"))
+        );
+    }
+
+    #[test]
+    fn configure_from_env() {
+        let ca = std::env::temp_dir().join("ginmi-from-env-ca.pem");
+        std::fs::write(&ca, "CA Certificate").unwrap();
+        let ca = ca.to_str().unwrap().to_string();
+
+        let from = |vars: &[(&str, &str)]| {
+            let vars: HashMap<String, String> = vars
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            ClientBuilder::from_vars(|name| vars.get(name).cloned())
+        };
+
+        let builder = from(&[
+            ("GNMI_TARGET", "https://router1:57400"),
+            ("GNMI_USERNAME", "admin"),
+            ("GNMI_PASSWORD", "admin"),
+            ("GNMI_TLS_CA", &ca),
+        ])
+        .unwrap();
+        assert_eq!(builder.target, "https://router1:57400");
+        assert!(builder.creds.is_some());
+        assert!(builder.tls_settings.is_some());
+        assert!(builder.tls_domain_from_target);
+
+        let builder = from(&[
+            ("GNMI_TARGET", "http://router1:57400"),
+            ("GNMI_INSECURE", "true"),
+        ])
+        .unwrap();
+        assert!(builder.creds.is_none());
+        assert!(builder.tls_settings.is_none());
+
+        let builder = from(&[("GNMI_TARGET", "https://router1:57400")]).unwrap();
+        assert!(builder.tls_settings.is_some());
+        assert!(builder.tls_domain_from_target);
+
+        // the domain also applies to the trust store of the operating system
+        let builder = from(&[
+            ("GNMI_TARGET", "https://10.0.0.1:57400"),
+            ("GNMI_TLS_DOMAIN", "router1"),
+        ])
+        .unwrap();
+        assert!(builder.tls_settings.is_some());
+        assert!(!builder.tls_domain_from_target);
+        assert_eq!(builder.tls_domain.as_deref(), Some("router1"));
+
+        for vars in [
+            &[][..],
+            &[
+                ("GNMI_TARGET", "http://router1"),
+                ("GNMI_USERNAME", "admin"),
+            ],
+            &[
+                ("GNMI_TARGET", "http://router1"),
+                ("GNMI_INSECURE", "yes please"),
+            ],
+            &[
+                ("GNMI_TARGET", "http://router1"),
+                ("GNMI_INSECURE", "1"),
+                ("GNMI_TLS_CA", &ca),
+            ],
+            &[
+                ("GNMI_TARGET", "https://router1"),
+                ("GNMI_TLS_CA", "/missing/ca.pem"),
+            ],
+            // TLS is only used for targets that use https
+            &[("GNMI_TARGET", "http://router1")],
+            &[("GNMI_TARGET", "router1:57400")],
+            &[
+                ("GNMI_TARGET", "https://router1"),
+                ("GNMI_INSECURE", "true"),
+            ],
+            &[
+                ("GNMI_TARGET", "http://router1"),
+                ("GNMI_INSECURE", "true"),
+                ("GNMI_TLS_DOMAIN", "router1"),
+            ],
+        ] {
+            assert!(matches!(from(vars), Err(GinmiError::EnvironmentError(_))));
+        }
+    }
+
+    #[test]
+    fn authority_overrides_origin() {
+        let target = Uri::from_static("https://10.0.0.1:57400");
+        let origin = |builder: ClientBuilder| builder.origin_uri(&target).unwrap();
+
+        assert_eq!(origin(Client::builder("")), None);
+        assert_eq!(
+            origin(Client::builder("").authority("router1.example.com:57400")),
+            Some(Uri::from_static("https://router1.example.com:57400/"))
+        );
+        assert_eq!(
+            origin(
+                Client::builder("")
+                    .origin("http://gateway:8080/gnmi")
+                    .authority("router1")
+            ),
+            Some(Uri::from_static("http://router1/gnmi"))
+        );
+        assert!(Client::builder("")
+            .authority("router 1")
+            .origin_uri(&target)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn invalid_origin() {
+        let client = Client::builder("https://test:57400")
+            .origin("http://gateway example")
+            .build()
+            .await;
+        assert!(matches!(client, Err(GinmiError::InvalidUriError(_))));
+    }
+
+    #[tokio::test]
+    async fn bind_local_address() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = format!("http://{}", listener.local_addr().unwrap());
+        let local_address = "127.0.0.1".parse().unwrap();
+
+        let client = Client::builder(&target).bind_local_address(local_address);
+
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (_, peer) = accepted.unwrap();
+                assert_eq!(peer.ip(), local_address);
+            }
+            _ = client.build() => panic!("connection was not accepted"),
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_static_hosts() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let target = format!("http://clab-srl01-srl.invalid:{}", port);
+
+        let client = Client::builder(&target).resolve(
+            "clab-srl01-srl.invalid",
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+        );
+
+        tokio::select! {
+            accepted = listener.accept() => assert!(accepted.is_ok()),
+            _ = client.build() => panic!("connection was not accepted"),
+        }
+
+        let client = Client::builder("http://a:57400")
+            .targets(&["http://a:57400", "http://b:57400"])
+            .resolve("a", SocketAddr::from(([127, 0, 0, 1], 0)));
+        assert!(matches!(
+            client.validate(),
+            Err(GinmiError::InvalidUriError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn invalid_header_name() {
+        let client = Client::builder("https://test:57400")
+            .header("x request", "collector")
+            .build()
+            .await;
+        assert!(matches!(client, Err(GinmiError::InvalidHeaderName(_))));
+    }
+
+    #[tokio::test]
+    async fn non_ascii_credentials() {
+        let client = Client::builder("https://test:57400")
+            .credentials("admin", "pässword")
+            .build()
+            .await;
+        assert!(matches!(
+            client,
+            Err(GinmiError::InvalidCredentialsError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn credentials_with_newline() {
+        let client = Client::builder("https://test:57400")
+            .credentials("admin", "pass\nword")
+            .build()
+            .await;
+        assert!(matches!(
+            client,
+            Err(GinmiError::InvalidCredentialsError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn client_from_channel() {
+        let channel = Channel::from_static("http://test:57400").connect_lazy();
+        let client = Client::from_channel(channel.clone(), Some(("admin", "admin"))).unwrap();
+        assert_eq!(client.connection_security(), ConnectionSecurity::Custom);
+
+        let client = Client::from_channel(channel, Some(("admin", "pässword")));
+        assert!(matches!(
+            client,
+            Err(GinmiError::InvalidCredentialsError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn reconnect_client_from_channel() {
+        let mut client = lazy_client();
+        assert!(matches!(
+            client.reconnect().await,
+            Err(GinmiError::ReconnectError(_))
+        ));
+    }
+
+    #[test]
+    fn debug_redacts_password() {
+        let builder = Client::builder("https://test:57400").credentials("admin", "secret");
+
+        let debug = format!("{:?}", builder);
+        assert!(debug.contains("admin"));
+        assert!(!debug.contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn invalid_tls_settings() {
+        let client = Client::builder("https://test:57400")
+            .tls("invalid cert", "invalid domain")
+            .build()
+            .await;
+        assert!(client.is_err());
+    }
+
+    #[test]
+    fn security_follows_scheme() {
+        let security = |builder: ClientBuilder| endpoint_security(&builder.prepare().unwrap().0);
+
+        // tonic connects to https targets with the native roots even without TLS-Settings
+        assert_eq!(
+            security(Client::builder("https://test:57400")),
+            ConnectionSecurity::Tls
+        );
+        assert_eq!(
+            security(Client::builder("https://test:57400").tls_native_roots()),
+            ConnectionSecurity::Tls
+        );
+        assert_eq!(
+            security(Client::builder("http://test:57400")),
+            ConnectionSecurity::Insecure
+        );
+        assert_eq!(
+            security(
+                Client::builder("https://a:57400").targets(&["https://a:57400", "http://b:57400"])
+            ),
+            ConnectionSecurity::Insecure
+        );
+    }
+
+    #[test]
+    fn restricted_tls_versions() {
+        let builder = Client::builder("https://router1:57400")
+            .tls_min_version(&crate::client::tls::version::TLS13)
+            .tls_cipher_suites(&[crate::client::tls::cipher_suite::TLS13_AES_256_GCM_SHA384]);
+        let (endpoints, tls_session, _) = builder.prepare().unwrap();
+        // the TLS session is established by the connector instead of tonic
+        assert_eq!(endpoints[0].uri().scheme_str(), Some("http"));
+        let tls_session = tls_session.unwrap();
+        assert_eq!(*tls_session.details().lock().unwrap(), None);
+
+        let (endpoints, tls_session, _) =
+            Client::builder("https://router1:57400").prepare().unwrap();
+        assert_eq!(endpoints[0].uri().scheme_str(), Some("https"));
+        assert!(tls_session.is_none());
+
+        for builder in [
+            Client::builder("http://router1:57400"),
+            Client::builder("https://a:57400").targets(&["https://a:57400", "https://b:57400"]),
+            Client::builder("https://router1:57400").tls("CA Certificate", "router1"),
+        ] {
+            assert!(matches!(
+                builder
+                    .tls_min_version(&crate::client::tls::version::TLS13)
+                    .validate(),
+                Err(GinmiError::TlsConfigError(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn tls_with_plaintext_target() {
+        let client = Client::builder("http://test:57400")
+            .tls_native_roots()
+            .build()
+            .await;
+        assert!(matches!(client, Err(GinmiError::TlsConfigError(_))));
+
+        let builder = Client::builder("http://test:57400").tls("CA Certificate", "test");
+        assert!(matches!(
+            builder.validate(),
+            Err(GinmiError::TlsConfigError(_))
+        ));
     }
 }
@@ -0,0 +1,62 @@
+//! Offset of the clock of a target device from the local clock.
+use std::time::{Duration, SystemTime};
+
+/// How far the clock of a target device is off from the local clock.
+///
+/// Returned by [`Client::clock_skew`](super::Client::clock_skew). Timestamps of the target
+/// device can be converted to the local clock with [`ClockSkew::to_local`], e.g. to correlate
+/// events of multiple devices.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClockSkew {
+    /// The clock of the target device is ahead of the local clock by the given duration.
+    Ahead(Duration),
+    /// The clock of the target device is behind the local clock by the given duration.
+    Behind(Duration),
+}
+
+impl ClockSkew {
+    /// Determine the skew from the time of the target device and the local time at the same
+    /// instant.
+    pub(crate) fn between(device: SystemTime, local: SystemTime) -> Self {
+        match device.duration_since(local) {
+            Ok(ahead) => ClockSkew::Ahead(ahead),
+            Err(behind) => ClockSkew::Behind(behind.duration()),
+        }
+    }
+
+    /// Returns the absolute offset between the clocks.
+    pub fn offset(&self) -> Duration {
+        match self {
+            ClockSkew::Ahead(offset) | ClockSkew::Behind(offset) => *offset,
+        }
+    }
+
+    /// Convert a timestamp of the target device into the corresponding local time.
+    pub fn to_local(&self, device_time: SystemTime) -> SystemTime {
+        match self {
+            ClockSkew::Ahead(offset) => device_time - *offset,
+            ClockSkew::Behind(offset) => device_time + *offset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn skew_between_clocks() {
+        let local = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let second = Duration::from_secs(1);
+
+        let ahead = ClockSkew::between(local + second, local);
+        assert_eq!(ahead, ClockSkew::Ahead(second));
+        assert_eq!(ahead.to_local(local + second), local);
+
+        let behind = ClockSkew::between(local - second, local);
+        assert_eq!(behind, ClockSkew::Behind(second));
+        assert_eq!(behind.offset(), second);
+        assert_eq!(behind.to_local(local - second), local);
+    }
+}
@@ -0,0 +1,230 @@
+//! State of the connection of a [`Client`](super::Client) to its target device.
+//!
+//! The states mirror the
+//! [gRPC connectivity semantics](https://github.com/grpc/grpc/blob/master/doc/connectivity-semantics-and-api.md).
+//! They are tracked by the connector of the channel, so observing them does not send any
+//! requests to the target device.
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::watch;
+use tokio_stream::wrappers::WatchStream;
+use tokio_stream::Stream;
+use tonic::transport::Uri;
+use tower_service::Service;
+
+/// State of the connection to the target device.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ConnectivityState {
+    /// There is no connection, it is established with the next request.
+    Idle,
+    /// The connection is being established.
+    Connecting,
+    /// The connection is established and requests can be sent.
+    Ready,
+    /// Establishing the connection failed or the connection broke. It is established again
+    /// with the next request.
+    TransientFailure,
+    /// The client has been dropped, so no connection will be established anymore.
+    Shutdown,
+}
+
+/// Stream of the [`ConnectivityState`]s of a [`Client`](super::Client).
+///
+/// Returned by [`Client::connectivity`](super::Client::connectivity). The first item is the
+/// current state, followed by every transition. Intermediate states may be skipped if the
+/// stream is not polled fast enough. Ends with [`ConnectivityState::Shutdown`] once all
+/// clones of the client have been dropped.
+#[derive(Debug)]
+pub struct ConnectivityStream {
+    states: Option<WatchStream<ConnectivityState>>,
+}
+
+impl ConnectivityStream {
+    pub(crate) fn new(states: Option<watch::Receiver<ConnectivityState>>) -> Self {
+        Self {
+            states: states.map(WatchStream::new),
+        }
+    }
+}
+
+impl Stream for ConnectivityStream {
+    type Item = ConnectivityState;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let states = match &mut self.states {
+            Some(states) => states,
+            None => return Poll::Ready(None),
+        };
+
+        match Pin::new(states).poll_next(cx) {
+            Poll::Ready(None) => {
+                self.states = None;
+                Poll::Ready(Some(ConnectivityState::Shutdown))
+            }
+            poll => poll,
+        }
+    }
+}
+
+/// Connector that records the [`ConnectivityState`] of the connections it establishes.
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectivityConnector<C> {
+    inner: C,
+    state: Arc<watch::Sender<ConnectivityState>>,
+}
+
+impl<C> ConnectivityConnector<C> {
+    pub(crate) fn new(inner: C) -> (Self, watch::Receiver<ConnectivityState>) {
+        let (state, receiver) = watch::channel(ConnectivityState::Idle);
+        let connector = Self {
+            inner,
+            state: Arc::new(state),
+        };
+        (connector, receiver)
+    }
+}
+
+impl<C> Service<Uri> for ConnectivityConnector<C>
+where
+    C: Service<Uri>,
+    C::Future: Send + 'static,
+    C::Response: AsyncRead + AsyncWrite + Unpin,
+{
+    type Response = ConnectivityIo<C::Response>;
+    type Error = C::Error;
+    type Future =
+        Pin<Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        self.state.send_replace(ConnectivityState::Connecting);
+        let state = Arc::clone(&self.state);
+        let connecting = self.inner.call(uri);
+
+        Box::pin(async move {
+            match connecting.await {
+                Ok(io) => {
+                    state.send_replace(ConnectivityState::Ready);
+                    Ok(ConnectivityIo { inner: io, state })
+                }
+                Err(e) => {
+                    state.send_replace(ConnectivityState::TransientFailure);
+                    Err(e)
+                }
+            }
+        })
+    }
+}
+
+/// Connection that records when it is closed or breaks.
+#[derive(Debug)]
+pub(crate) struct ConnectivityIo<T> {
+    inner: T,
+    state: Arc<watch::Sender<ConnectivityState>>,
+}
+
+impl<T> ConnectivityIo<T> {
+    fn record<R>(&self, poll: Poll<io::Result<R>>) -> Poll<io::Result<R>> {
+        if let Poll::Ready(Err(_)) = &poll {
+            self.state.send_replace(ConnectivityState::TransientFailure);
+        }
+        poll
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for ConnectivityIo<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+
+        // reading nothing into a buffer with space left means the target closed the connection
+        if matches!(poll, Poll::Ready(Ok(())))
+            && buf.filled().len() == filled
+            && buf.remaining() > 0
+        {
+            self.state.send_replace(ConnectivityState::Idle);
+        }
+        self.record(poll)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for ConnectivityIo<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        self.record(poll)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let poll = Pin::new(&mut self.inner).poll_flush(cx);
+        self.record(poll)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let poll = Pin::new(&mut self.inner).poll_shutdown(cx);
+        self.record(poll)
+    }
+}
+
+impl<T> Drop for ConnectivityIo<T> {
+    fn drop(&mut self) {
+        self.state.send_if_modified(|state| {
+            let ready = *state == ConnectivityState::Ready;
+            if ready {
+                *state = ConnectivityState::Idle;
+            }
+            ready
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::client::HttpConnector;
+    use tokio::net::TcpListener;
+    use tokio_stream::StreamExt;
+
+    #[tokio::test]
+    async fn connector_records_states() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri: Uri = format!("http://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        let (mut connector, receiver) = ConnectivityConnector::new(HttpConnector::new());
+        let mut states = ConnectivityStream::new(Some(receiver.clone()));
+        assert_eq!(states.next().await, Some(ConnectivityState::Idle));
+
+        let io = connector.call(uri).await.unwrap();
+        assert_eq!(*receiver.borrow(), ConnectivityState::Ready);
+        drop(io);
+        assert_eq!(*receiver.borrow(), ConnectivityState::Idle);
+
+        drop(listener);
+        let unreachable = "http://127.0.0.1:1".parse().unwrap();
+        assert!(connector.call(unreachable).await.is_err());
+        assert_eq!(*receiver.borrow(), ConnectivityState::TransientFailure);
+
+        drop(connector);
+        assert_eq!(
+            states.next().await,
+            Some(ConnectivityState::TransientFailure)
+        );
+        assert_eq!(states.next().await, Some(ConnectivityState::Shutdown));
+        assert_eq!(states.next().await, None);
+    }
+}
@@ -6,6 +6,18 @@
 //! [`ClientConfig`] is configured with a custom [`ServerCertVerifier`]
 //! that will always return a successful validation.
 //!
+//! The TLS versions and cipher suites restricted with [`ClientBuilder::tls_min_version`] and
+//! [`ClientBuilder::tls_cipher_suites`] also apply to the [`ClientConfig`]. Unless verification
+//! is disabled or a fingerprint is pinned, the certificate of the target device is verified
+//! against the CA given to [`ClientBuilder::tls`] and the trust store of the operating system.
+//!
+//! Because the TLS session is established by this module, details like the negotiated
+//! protocol version are available with [`Client::tls_details`], even if TLS is not
+//! restricted.
+//!
+//! With [`DangerousClientBuilder::tls_pin_sha256`], the certificate of the target device is
+//! not verified against a CA, but only accepted if its fingerprint matches a pinned one.
+//!
 //! [Channel]: tonic::transport::Channel
 //!
 //! # Safety
@@ -27,31 +39,48 @@
 //!     .build()
 //!     .await?;
 //! # })}
+use super::resolve::StaticResolver;
+use super::tls::{root_store, SharedTlsDetails};
+use super::transport::TonicTransport;
 use super::ClientBuilder;
 use crate::auth::AuthInterceptor;
-use crate::client::Client;
+use crate::client::client::{auth_interceptor, request_limit, static_headers};
+use crate::client::{Client, ConnectionSecurity};
 use crate::error::GinmiError;
 use crate::gen::gnmi::g_nmi_client::GNmiClient;
 use http::Uri;
 use hyper::client::HttpConnector;
-use hyper_rustls::HttpsConnector;
+use hyper_rustls::{HttpsConnector, MaybeHttpsStream};
 use std::convert::From;
+use std::future::Future;
+use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 use std::time::SystemTime;
+use tokio::sync::Mutex;
 use tokio_rustls::rustls::client::{ServerCertVerified, ServerCertVerifier};
 use tokio_rustls::rustls::{Certificate, ClientConfig, Error, RootCertStore, ServerName};
 use tonic::body::BoxBody;
 use tonic::codegen::InterceptedService;
-use tonic::metadata::AsciiMetadataValue;
+use tower_service::Service;
 
-pub type DangerousConnection =
-    InterceptedService<hyper::Client<HttpsConnector<HttpConnector>, BoxBody>, AuthInterceptor>;
+pub use super::tls::{
+    cipher_suite, version, ProtocolVersion, SupportedCipherSuite, SupportedProtocolVersion,
+    TlsDetails,
+};
+
+pub type DangerousConnection = InterceptedService<
+    hyper::Client<TlsDetailsConnector<HttpsConnector<HttpConnector<StaticResolver>>>, BoxBody>,
+    AuthInterceptor,
+>;
 
 /// Builder for [`Client`]s with extra options that are dangerous and require extra care.
 pub struct DangerousClientBuilder<'a> {
     builder: ClientBuilder<'a>,
-    client_config: Option<ClientConfig>,
+    verify_certificates: bool,
+    warn_insecure: bool,
+    pinned_fingerprint: Option<String>,
 }
 
 impl<'a> DangerousClientBuilder<'a> {
@@ -61,73 +90,177 @@ impl<'a> DangerousClientBuilder<'a> {
     /// Using this option completely disables certificate validation which on turn
     /// makes you susceptible to Man-in-the-Middle attacks. This option can be useful for local
     /// testing purposes, but should be avoided at all cost for any other use case.
+    ///
+    /// A warning is logged with [`tracing`] when the client is built, unless it is
+    /// suppressed with [`DangerousClientBuilder::allow_insecure_silently`].
     pub fn disable_certificate_verification(mut self) -> Self {
-        let roots = RootCertStore::empty();
+        self.verify_certificates = false;
+        self
+    }
 
-        let mut tls = ClientConfig::builder()
-            .with_safe_defaults()
-            .with_root_certificates(roots)
-            .with_no_client_auth();
+    /// Do not log a warning when building a client that does not verify certificates, e.g.
+    /// in tests that deliberately connect to devices with self-signed certificates.
+    pub fn allow_insecure_silently(mut self) -> Self {
+        self.warn_insecure = false;
+        self
+    }
+
+    /// Accept only the certificate of the target device whose SHA-256 fingerprint matches
+    /// `fingerprint`, instead of verifying it against a CA.
+    ///
+    /// The fingerprint is the hex encoded SHA-256 digest of the DER encoded certificate, as
+    /// printed by `openssl x509 -noout -fingerprint -sha256`. Colons between the bytes are
+    /// optional. The certificate must still sign the handshake, but its name and validity
+    /// period are not checked. Takes precedence over
+    /// [`DangerousClientBuilder::disable_certificate_verification`].
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::Client;
+    /// # tokio_test::block_on(async {
+    /// let mut client = Client::builder("https://clab-srl01-srl:57400")
+    ///     .credentials("admin", "password1")
+    ///     .tls_pin_sha256("9F:86:D0:81:88:4C:7D:65:9A:2F:EA:A0:C5:5A:D0:15:A3:BF:4F:1B:2B:0B:82:2C:D1:5D:6C:15:B0:F0:0A:08")
+    ///     .build()
+    ///     .await
+    ///     .unwrap();
+    /// # });
+    /// ```
+    pub fn tls_pin_sha256(mut self, fingerprint: &str) -> Self {
+        self.pinned_fingerprint = Some(fingerprint.to_string());
+        self
+    }
 
-        tls.dangerous()
-            .set_certificate_verifier(Arc::new(NoCertificateVerification {}));
+    /// Set the oldest TLS version that may be negotiated with the target device, see
+    /// [`ClientBuilder::tls_min_version`].
+    pub fn tls_min_version(mut self, version: &'static SupportedProtocolVersion) -> Self {
+        self.builder.tls_options.min_version = Some(version);
+        self
+    }
 
-        self.client_config = Some(tls);
+    /// Restrict the cipher suites that may be negotiated with the target device, see
+    /// [`ClientBuilder::tls_cipher_suites`].
+    pub fn tls_cipher_suites(mut self, suites: &[SupportedCipherSuite]) -> Self {
+        self.builder.tls_options.cipher_suites = Some(suites.to_vec());
         self
     }
 
+    fn client_config(&self) -> Result<ClientConfig, GinmiError> {
+        // the roots are not used if the certificate verifier is replaced
+        let roots = if self.verify_certificates && self.pinned_fingerprint.is_none() {
+            root_store(self.builder.tls_ca.as_deref())?
+        } else {
+            RootCertStore::empty()
+        };
+        let mut tls = self.builder.tls_options.client_config(roots)?;
+
+        if let Some(fingerprint) = &self.pinned_fingerprint {
+            let fingerprint = parse_fingerprint(fingerprint)?;
+            tls.dangerous()
+                .set_certificate_verifier(Arc::new(PinnedCertificateVerification { fingerprint }));
+        } else if !self.verify_certificates {
+            tls.dangerous()
+                .set_certificate_verifier(Arc::new(NoCertificateVerification {}));
+        }
+
+        Ok(tls)
+    }
+
     /// Consume the [`DangerousClientBuilder`] and return a [`Client`].
     ///
     /// # Errors
     /// - Returns [`GinmiError::InvalidUriError`] if specified target is not a valid URI.
-    /// - Returns [`GinmiError::TransportError`] if the TLS-Settings are invalid.
+    /// - Returns [`GinmiError::InvalidCredentialsError`] if the credentials contain characters
+    ///   that are not allowed in gRPC metadata.
+    /// - Returns [`GinmiError::TlsConfigError`] if the TLS versions and cipher suites have
+    ///   no match, or the CA certificate or trust store can not be loaded.
     /// - Returns [`GinmiError::TransportError`] if a connection to the target could not be
-    /// established.
-    pub async fn build(self) -> Result<Client<DangerousConnection>, GinmiError> {
+    ///   established.
+    pub async fn build(self) -> Result<Client<TonicTransport<DangerousConnection>>, GinmiError> {
+        let tls = self.client_config()?;
+
+        if !self.verify_certificates && self.pinned_fingerprint.is_none() && self.warn_insecure {
+            tracing::warn!(
+                device = %self.builder.target,
+                "certificate verification is disabled, the connection to the target device is \
+                 insecure and susceptible to man-in-the-middle attacks"
+            );
+        }
+
         // create a hyper HttpConnector
-        let mut http = HttpConnector::new();
+        let mut http = HttpConnector::new_with_resolver(self.builder.resolver.clone());
         http.enforce_http(false);
+        http.set_local_address(self.builder.local_address);
 
         // specify tls configuration for the http connector to enable https
         let connector = tower::ServiceBuilder::new()
             .layer_fn(move |s| {
-                let tls = self.client_config.clone().unwrap();
-
                 hyper_rustls::HttpsConnectorBuilder::new()
-                    .with_tls_config(tls)
+                    .with_tls_config(tls.clone())
                     .https_or_http()
                     .enable_http2()
                     .wrap_connector(s)
             })
             .service(http);
 
+        // record the details of every TLS session that is established
+        let tls_details = SharedTlsDetails::default();
+        let connector = TlsDetailsConnector {
+            inner: connector,
+            details: Arc::clone(&tls_details),
+        };
+
         // create a hyper client from the connector
         let http_client = hyper::Client::builder().build(connector);
 
-        let uri = match Uri::from_str(self.builder.target) {
+        let uri = match Uri::from_str(&self.builder.target) {
             Ok(u) => u,
             Err(e) => return Err(GinmiError::InvalidUriError(e.to_string())),
         };
 
-        let (username, password) = match self.builder.creds {
-            Some(c) => (
-                Some(AsciiMetadataValue::from_str(c.username)?),
-                Some(AsciiMetadataValue::from_str(c.password)?),
-            ),
-            None => (None, None),
-        };
-
         // add the authentication interceptor to the service.
+        let mut headers = self.builder.headers.clone();
+        if let Some(user_agent) = self.builder.user_agent {
+            headers.push(("user-agent", user_agent));
+        }
+        let auth = auth_interceptor(self.builder.creds.clone())?
+            .with_headers(static_headers(&headers)?)
+            .with_correlation_ids(self.builder.correlation_ids);
         let svc = tower::ServiceBuilder::new()
-            .layer(tonic::service::interceptor(AuthInterceptor::new(
-                username, password,
-            )))
+            .layer(tonic::service::interceptor(auth.clone()))
             .service(http_client);
 
+        // hyper-rustls only uses TLS for targets that use `https`
+        let security = if uri.scheme_str() != Some("https") {
+            ConnectionSecurity::Insecure
+        } else if self.pinned_fingerprint.is_some() {
+            ConnectionSecurity::Pinned
+        } else if self.verify_certificates {
+            ConnectionSecurity::Tls
+        } else {
+            ConnectionSecurity::DangerousNoVerify
+        };
+
         // create a client, overriding the default uri with the uri in the builder
         let client = GNmiClient::with_origin(svc, uri);
 
-        Ok(Client { inner: client })
+        Ok(Client {
+            inner: TonicTransport::new(client),
+            capabilities_cache: Arc::new(Mutex::new(None)),
+            security,
+            auth,
+            subscribe_buffer: self.builder.subscribe_buffer,
+            endpoints: Vec::new(),
+            local_address: self.builder.local_address,
+            resolver: self.builder.resolver,
+            captured_request: self.builder.debug_capture.then(Default::default),
+            connectivity: None,
+            reconnect_backoff: None,
+            default_encoding: self.builder.default_encoding,
+            request_limit: request_limit(self.builder.max_concurrent_requests),
+            tls_session: None,
+            tls_details,
+        })
     }
 }
 
@@ -135,7 +268,9 @@ impl<'a> From<ClientBuilder<'a>> for DangerousClientBuilder<'a> {
     fn from(builder: ClientBuilder<'a>) -> Self {
         DangerousClientBuilder {
             builder,
-            client_config: None,
+            verify_certificates: true,
+            warn_insecure: true,
+            pinned_fingerprint: None,
         }
     }
 }
@@ -159,3 +294,215 @@ impl ServerCertVerifier for NoCertificateVerification {
         Ok(ServerCertVerified::assertion())
     }
 }
+
+/// Connector that records the [`TlsDetails`] of the connections it establishes.
+#[derive(Debug, Clone)]
+pub struct TlsDetailsConnector<C> {
+    inner: C,
+    details: SharedTlsDetails,
+}
+
+impl<C, T> Service<Uri> for TlsDetailsConnector<C>
+where
+    C: Service<Uri, Response = MaybeHttpsStream<T>>,
+    C::Future: Send + 'static,
+    T: Send + 'static,
+{
+    type Response = C::Response;
+    type Error = C::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connecting = self.inner.call(uri);
+        let details = Arc::clone(&self.details);
+
+        Box::pin(async move {
+            let stream = connecting.await?;
+            let session = match &stream {
+                MaybeHttpsStream::Https(tls) => Some(TlsDetails::of(tls.get_ref().1)),
+                MaybeHttpsStream::Http(_) => None,
+            };
+            *details
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = session;
+            Ok(stream)
+        })
+    }
+}
+
+/// Decode a hex encoded SHA-256 fingerprint, whose bytes may be separated by colons.
+fn parse_fingerprint(fingerprint: &str) -> Result<[u8; 32], GinmiError> {
+    let invalid = || {
+        GinmiError::TlsConfigError(format!(
+            "{} is not a hex encoded SHA-256 fingerprint",
+            fingerprint
+        ))
+    };
+
+    let digits: Vec<u8> = fingerprint
+        .chars()
+        .filter(|c| *c != ':')
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+    if digits.len() != 64 {
+        return Err(invalid());
+    }
+
+    let mut bytes = [0; 32];
+    for (byte, pair) in bytes.iter_mut().zip(digits.chunks(2)) {
+        *byte = pair[0] << 4 | pair[1];
+    }
+    Ok(bytes)
+}
+
+/// ServerCertVerifier that only accepts the certificate with a pinned SHA-256 fingerprint.
+///
+/// Signatures of the handshake are still verified with the default implementation, so the
+/// target device has to own the private key of the pinned certificate.
+#[derive(Debug)]
+struct PinnedCertificateVerification {
+    fingerprint: [u8; 32],
+}
+
+impl ServerCertVerifier for PinnedCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, Error> {
+        let digest = ring::digest::digest(&ring::digest::SHA256, &end_entity.0);
+        if digest.as_ref() == self.fingerprint {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(Error::General(
+                "certificate does not match the pinned fingerprint".to_string(),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tls13_only() {
+        let builder = Client::builder("https://test:57400")
+            .dangerous()
+            .tls_min_version(&version::TLS13)
+            .tls_cipher_suites(&[cipher_suite::TLS13_AES_256_GCM_SHA384]);
+        assert!(builder.client_config().is_ok());
+    }
+
+    #[test]
+    fn no_matching_cipher_suite() {
+        let builder = Client::builder("https://test:57400")
+            .dangerous()
+            .tls_min_version(&version::TLS13)
+            .tls_cipher_suites(&[cipher_suite::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256]);
+        assert!(matches!(
+            builder.client_config(),
+            Err(GinmiError::TlsConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn pinned_fingerprint() {
+        let sha256 = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        let with_colons = "9F:86:D0:81:88:4C:7D:65:9A:2F:EA:A0:C5:5A:D0:15:\
+                           A3:BF:4F:1B:2B:0B:82:2C:D1:5D:6C:15:B0:F0:0A:08";
+        assert_eq!(
+            parse_fingerprint(sha256).unwrap(),
+            parse_fingerprint(with_colons).unwrap()
+        );
+        assert!(parse_fingerprint(&sha256[2..]).is_err());
+        assert!(parse_fingerprint(&sha256.replace('f', "g")).is_err());
+
+        // the fingerprint above is the SHA-256 digest of "test"
+        let verifier = PinnedCertificateVerification {
+            fingerprint: parse_fingerprint(sha256).unwrap(),
+        };
+        let verify = |certificate: &[u8]| {
+            verifier.verify_server_cert(
+                &Certificate(certificate.to_vec()),
+                &[],
+                &ServerName::try_from("test").unwrap(),
+                &mut std::iter::empty(),
+                &[],
+                SystemTime::now(),
+            )
+        };
+        assert!(verify(b"test").is_ok());
+        assert!(verify(b"other").is_err());
+
+        let builder = Client::builder("https://test:57400").tls_pin_sha256("not hex");
+        assert!(matches!(
+            builder.client_config(),
+            Err(GinmiError::TlsConfigError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn verified_by_default() {
+        let client = Client::builder("https://test:57400")
+            .tls_min_version(&version::TLS13)
+            .dangerous()
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(client.connection_security(), ConnectionSecurity::Tls);
+
+        let client = Client::builder("https://test:57400")
+            .dangerous()
+            .disable_certificate_verification()
+            .allow_insecure_silently()
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(
+            client.connection_security(),
+            ConnectionSecurity::DangerousNoVerify
+        );
+
+        let client = Client::builder("http://test:57400")
+            .dangerous()
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(client.connection_security(), ConnectionSecurity::Insecure);
+
+        // the CA is only loaded if certificates are verified against it
+        let builder = Client::builder("https://test:57400").tls("CA Certificate", "test");
+        assert!(matches!(
+            builder.clone().dangerous().client_config(),
+            Err(GinmiError::TlsConfigError(_))
+        ));
+        assert!(builder
+            .dangerous()
+            .disable_certificate_verification()
+            .client_config()
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn tls_details_of_plain_connection() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = format!("http://{}", listener.local_addr().unwrap());
+
+        let mut client = Client::builder(&target).dangerous().build().await.unwrap();
+        assert_eq!(client.tls_details(), None);
+
+        tokio::spawn(async move { while listener.accept().await.is_ok() {} });
+        let _ = client.capabilities().await;
+        assert_eq!(client.tls_details(), None);
+    }
+}
@@ -0,0 +1,592 @@
+//! Retrieve snapshots of data elements from a target device.
+//!
+//! Get requests are created with [`Client::get`](super::Client::get), as described in the
+//! [gNMI Specification Section 3.3](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-specification.md#33-retrieving-snapshots-of-state-information).
+use super::client::echo_request_id;
+use super::transport::GnmiTransport;
+use super::{stats, Client};
+use crate::error::GinmiError;
+use crate::extension::Extension;
+use crate::gen::gnmi::get_request::DataType as GnmiDataType;
+use crate::gen::gnmi::{Encoding, GetRequest, ModelData};
+use crate::notification::{Notifications, Update};
+use crate::path::{origins_on_prefix, Path, PathElement};
+use crate::value::TypedValue;
+use prost::Message;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
+use tonic::metadata::MetadataMap;
+
+/// Type of data elements requested by a get request, as described in the
+/// [gNMI Specification Section 3.3.1](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-specification.md#331-the-getrequest-message).
+///
+/// Each variant maps to the value of the `type` field in the `GetRequest` message:
+///
+/// | Variant                     | Protobuf value    |
+/// |-----------------------------|-------------------|
+/// | [`DataType::All`]           | `ALL = 0`         |
+/// | [`DataType::Config`]        | `CONFIG = 1`      |
+/// | [`DataType::State`]         | `STATE = 2`       |
+/// | [`DataType::Operational`]   | `OPERATIONAL = 3` |
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum DataType {
+    /// All data elements.
+    #[default]
+    All,
+    /// Data elements that are read-write, i.e. the configuration.
+    Config,
+    /// Data elements that are read-only, i.e. the operational state and derived values.
+    State,
+    /// Read-only data elements that are not derived from the configuration, e.g. counters.
+    Operational,
+}
+
+impl From<DataType> for GnmiDataType {
+    fn from(data_type: DataType) -> Self {
+        match data_type {
+            DataType::All => GnmiDataType::All,
+            DataType::Config => GnmiDataType::Config,
+            DataType::State => GnmiDataType::State,
+            DataType::Operational => GnmiDataType::Operational,
+        }
+    }
+}
+
+/// Builder for get requests.
+///
+/// Created by [`Client::get`](super::Client::get).
+#[derive(Debug)]
+pub struct GetRequestBuilder<'a, T> {
+    client: &'a mut Client<T>,
+    prefix: Option<Path>,
+    strip_prefix: bool,
+    paths: Vec<Path>,
+    data_type: DataType,
+    encoding: Option<Encoding>,
+    models: Vec<ModelData>,
+    extensions: Vec<Extension>,
+}
+
+impl<'a, T: GnmiTransport> GetRequestBuilder<'a, T> {
+    pub(crate) fn new(client: &'a mut Client<T>) -> Self {
+        Self {
+            client,
+            prefix: None,
+            strip_prefix: false,
+            paths: Vec::new(),
+            data_type: DataType::All,
+            encoding: None,
+            models: Vec::new(),
+            extensions: Vec::new(),
+        }
+    }
+
+    /// Set a prefix that is prepended to all requested paths.
+    pub fn prefix(mut self, prefix: Path) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    /// Set a prefix that is shared by all requested paths.
+    ///
+    /// Paths that start with the prefix are sent relative to it, so the prefix is
+    /// only sent once. All other paths are treated as already relative to the prefix.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::Client;
+    /// # use ginmi::path::Path;
+    /// # tokio_test::block_on(async {
+    /// # const CERT: &str = "CA Certificate";
+    /// # let mut client = Client::builder("https://clab-srl01-srl:57400")
+    /// #     .tls(CERT, "clab-srl01-srl")
+    /// #     .build()
+    /// #     .await
+    /// #     .unwrap();
+    /// let notifications = client
+    ///     .get()
+    ///     .common_prefix(Path::try_parse("/interfaces").unwrap())
+    ///     .path(Path::try_parse("interface[name=eth0]/state").unwrap())
+    ///     .path(Path::try_parse("/interfaces/interface[name=eth1]/state").unwrap())
+    ///     .send()
+    ///     .await
+    ///     .unwrap();
+    /// # });
+    /// ```
+    pub fn common_prefix(mut self, prefix: Path) -> Self {
+        self.prefix = Some(prefix);
+        self.strip_prefix = true;
+        self
+    }
+
+    /// Add a path to the data elements that are requested.
+    pub fn path(mut self, path: Path) -> Self {
+        self.paths.push(path);
+        self
+    }
+
+    #[cfg(feature = "raw")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "raw")))]
+    /// Add a generated protobuf path to the paths that are requested, see [`crate::raw`].
+    pub fn path_raw(self, path: crate::raw::Path) -> Self {
+        self.path(path.into())
+    }
+
+    /// Set the type of data elements that are requested. Defaults to [`DataType::All`].
+    pub fn data_type(mut self, data_type: DataType) -> Self {
+        self.data_type = data_type;
+        self
+    }
+
+    /// Set the [`Encoding`] the target device should use for values. Defaults to the
+    /// encoding configured with
+    /// [`ClientBuilder::default_encoding`](super::ClientBuilder::default_encoding).
+    ///
+    /// Values are decoded according to the encoding they were actually returned in, so a
+    /// target device that answers with a different encoding is handled gracefully.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Restrict the request to the schema of the given model. Can be called multiple times
+    /// to use multiple models.
+    pub fn use_model(mut self, name: &str, organization: &str, version: &str) -> Self {
+        self.models.push(ModelData {
+            name: name.to_string(),
+            organization: organization.to_string(),
+            version: version.to_string(),
+        });
+        self
+    }
+
+    /// Restrict the request to the schema of the model with the given name, taking its
+    /// organization and version from the capabilities of the target device.
+    ///
+    /// The capabilities are queried only once, see [`Client::capabilities_cached`].
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::GrpcError`] if the capabilities could not be queried.
+    /// - Returns [`GinmiError::UnsupportedModelError`] if the target device does not
+    ///   advertise a model with the given name.
+    pub async fn use_model_named(mut self, name: &str) -> Result<Self, GinmiError> {
+        let capabilities = self.client.capabilities_cached().await?;
+        let model = capabilities
+            .0
+            .supported_models
+            .into_iter()
+            .find(|model| model.name == name)
+            .ok_or_else(|| GinmiError::UnsupportedModelError(name.to_string()))?;
+
+        self.models.push(model);
+        Ok(self)
+    }
+
+    /// Attach an [`Extension`] to the request, e.g. [`Extension::History`] to retrieve
+    /// historical data. Can be called multiple times to attach multiple extensions.
+    ///
+    /// Vendor specific extensions, such as the selection of a datastore on some platforms,
+    /// can be attached as [`Extension::Registered`] with the id and payload documented by
+    /// the vendor.
+    pub fn extension(mut self, extension: Extension) -> Self {
+        self.extensions.push(extension);
+        self
+    }
+
+    /// Send the get request to the target device and return the [`Notifications`] it
+    /// responded with.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::GrpcError`] if the target device rejected the request.
+    /// - Returns [`GinmiError::DecodeError`] if the response could not be decoded.
+    pub async fn send(self) -> Result<Notifications, GinmiError> {
+        self.send_with_metadata()
+            .await
+            .map(|(notifications, _)| notifications)
+    }
+
+    /// Send the get request to the target device and return the [`Notifications`] it
+    /// responded with, together with the metadata of the response.
+    ///
+    /// The metadata contains both the headers and the trailers the target device sent,
+    /// e.g. vendor-specific information that is not part of the protobuf messages.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::PathParseError`] if a path has a different origin than the
+    ///   prefix, as only the prefix may have an origin.
+    /// - Returns [`GinmiError::GrpcError`] if the target device rejected the request.
+    /// - Returns [`GinmiError::DecodeError`] if the response could not be decoded.
+    pub async fn send_with_metadata(self) -> Result<(Notifications, MetadataMap), GinmiError> {
+        let mut req = self.request();
+        origins_on_prefix(req.prefix.as_mut(), req.path.iter_mut().collect())?;
+        self.client.capture(&req);
+        let (req, id) = self.client.correlate(req)?;
+        let _permit = self.client.request_permit().await;
+        let res = stats::observe("get", self.client.inner.get(req)).await;
+        let res = echo_request_id(res, id)?;
+        stats::received("get", res.get_ref().encoded_len());
+        let (metadata, res, _) = res.into_parts();
+        Ok((Notifications::try_from(res)?, metadata))
+    }
+
+    fn request(&self) -> GetRequest {
+        let paths = self.paths.iter().map(|path| match &self.prefix {
+            Some(prefix) if self.strip_prefix => {
+                path.strip_prefix(prefix).unwrap_or_else(|| path.clone())
+            }
+            _ => path.clone(),
+        });
+
+        GetRequest {
+            prefix: self.prefix.clone().map(Into::into),
+            path: paths.map(Into::into).collect(),
+            r#type: GnmiDataType::from(self.data_type) as i32,
+            encoding: self.encoding.unwrap_or(self.client.default_encoding) as i32,
+            use_models: self.models.clone(),
+            extension: self.extensions.iter().cloned().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Stream of periodic snapshots of a subtree of a target device.
+///
+/// Returned by [`Client::poll_subtree`](super::Client::poll_subtree). Dropping the stream
+/// stops polling the subtree.
+#[derive(Debug)]
+pub struct SubtreeSnapshots {
+    pub(crate) snapshots: mpsc::Receiver<Result<Notifications, GinmiError>>,
+}
+
+impl Stream for SubtreeSnapshots {
+    type Item = Result<Notifications, GinmiError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.snapshots.poll_recv(cx)
+    }
+}
+
+/// Attribute every update to the requested path it belongs to.
+///
+/// An update belongs to the requested path that shares the most elements with its absolute
+/// path. Updates that can not be attributed to any path are assigned to the only requested path
+/// if there is exactly one and are omitted otherwise.
+pub(crate) fn group_by_path(
+    paths: &[Path],
+    notifications: Notifications,
+) -> HashMap<Path, Vec<Update>> {
+    let mut groups: HashMap<Path, Vec<Update>> =
+        paths.iter().map(|p| (p.clone(), Vec::new())).collect();
+
+    for notification in notifications {
+        for mut update in notification.updates {
+            if let Some(prefix) = &notification.prefix {
+                update.path = update.path.with_prefix(prefix);
+            }
+
+            let best = paths
+                .iter()
+                .filter_map(|p| matching_elements(p, &update.path).map(|n| (n, p)))
+                .max_by_key(|(n, _)| *n)
+                .map(|(_, p)| p);
+
+            let owner = match (best, paths) {
+                (Some(path), _) => path,
+                (None, [path]) => path,
+                (None, _) => continue,
+            };
+
+            if let Some(updates) = groups.get_mut(owner) {
+                updates.push(update);
+            }
+        }
+    }
+
+    groups
+}
+
+/// The distinct entries of the list at `list_path` that the given paths point into, in the
+/// order they first appear.
+pub(crate) fn list_entries(list_path: &Path, paths: Vec<Path>) -> Vec<Path> {
+    let len = list_path.elements().len();
+    let mut entries: Vec<Path> = Vec::new();
+
+    for path in paths {
+        if path.elements().len() < len || matching_elements(list_path, &path) != Some(len) {
+            continue;
+        }
+
+        let mut entry = Path::new();
+        if let Some(origin) = list_path.origin() {
+            entry = entry.with_origin(origin);
+        }
+        if let Some(target) = list_path.target() {
+            entry = entry.with_target(target);
+        }
+        for element in &path.elements()[..len] {
+            entry.push(element.clone());
+        }
+
+        if !entries.contains(&entry) {
+            entries.push(entry);
+        }
+    }
+
+    entries
+}
+
+/// The values of the returned updates, keyed by the value of `key` in the first element of
+/// their path that has it. Updates without the key are skipped with a warning.
+pub(crate) fn values_by_key(
+    key: &str,
+    notifications: Notifications,
+) -> HashMap<String, TypedValue> {
+    let mut values = HashMap::new();
+
+    for notification in notifications {
+        for mut update in notification.updates {
+            if let Some(prefix) = &notification.prefix {
+                update.path = update.path.with_prefix(prefix);
+            }
+
+            match update.path.elements().iter().find_map(|e| e.key(key)) {
+                Some(name) => {
+                    values.insert(name.to_string(), update.value);
+                }
+                None => tracing::warn!(
+                    path = %update.path,
+                    key,
+                    "skipping update whose path does not contain the key"
+                ),
+            }
+        }
+    }
+
+    values
+}
+
+/// Number of leading elements that match if one path is a prefix of the other.
+fn matching_elements(requested: &Path, path: &Path) -> Option<usize> {
+    let mut matched = 0;
+    for (requested, element) in requested.elements().iter().zip(path.elements()) {
+        if requested.name() == "..." {
+            return Some(path.elements().len());
+        }
+        if !element_matches(requested, element) {
+            return None;
+        }
+        matched += 1;
+    }
+    Some(matched)
+}
+
+fn element_matches(requested: &PathElement, element: &PathElement) -> bool {
+    let name_matches = requested.name() == "*"
+        || requested.name() == element.name()
+        || ((requested.module().is_none() || element.module().is_none())
+            && requested.local_name() == element.local_name());
+
+    name_matches
+        && requested
+            .keys()
+            .iter()
+            .all(|(key, value)| value == "*" || element.key(key) == Some(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::lazy_client;
+    use crate::extension::History;
+    use crate::notification::Notification;
+
+    fn path(s: &str) -> Path {
+        s.parse().unwrap()
+    }
+
+    fn notification(prefix: Option<&str>, updates: &[&str]) -> Notification {
+        Notification {
+            timestamp: 0,
+            prefix: prefix.map(path),
+            updates: updates
+                .iter()
+                .map(|p| Update {
+                    path: path(p),
+                    value: TypedValue::Uint(1),
+                    duplicates: 0,
+                })
+                .collect(),
+            deletes: Vec::new(),
+            atomic: false,
+        }
+    }
+
+    #[test]
+    fn entries_of_list() {
+        let list: Path = "/interfaces/interface[name=*]".parse().unwrap();
+        let paths = [
+            "/interfaces/interface[name=eth0]/name",
+            "/interfaces/interface[name=eth1]/name",
+            "/interfaces/interface[name=eth0]/state/name",
+            "/system/name",
+        ]
+        .iter()
+        .map(|p| p.parse().unwrap())
+        .collect();
+
+        let entries: Vec<String> = list_entries(&list, paths)
+            .iter()
+            .map(Path::to_string)
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                "/interfaces/interface[name=eth0]",
+                "/interfaces/interface[name=eth1]"
+            ]
+        );
+    }
+
+    #[test]
+    fn data_type_values() {
+        assert_eq!(DataType::default(), DataType::All);
+        assert_eq!(GnmiDataType::from(DataType::All) as i32, 0);
+        assert_eq!(GnmiDataType::from(DataType::Config) as i32, 1);
+        assert_eq!(GnmiDataType::from(DataType::State) as i32, 2);
+        assert_eq!(GnmiDataType::from(DataType::Operational) as i32, 3);
+    }
+
+    #[tokio::test]
+    async fn get_with_common_prefix() {
+        let mut client = lazy_client();
+
+        let req = client
+            .get()
+            .common_prefix(path("/interfaces"))
+            .path(path("interface[name=eth0]/state"))
+            .path(path("/interfaces/interface[name=eth1]/state"))
+            .extension(Extension::History(History::SnapshotTime(1)))
+            .request();
+
+        assert_eq!(req.prefix, Some(path("/interfaces").into()));
+        assert_eq!(req.path[0], path("/interface[name=eth0]/state").into());
+        assert_eq!(req.path[1], path("/interface[name=eth1]/state").into());
+        assert_eq!(req.extension.len(), 1);
+    }
+
+    #[cfg(feature = "raw")]
+    #[tokio::test]
+    async fn get_with_raw_path() {
+        let mut client = lazy_client();
+        let raw = crate::raw::Path::from(path("/interfaces/interface[name=eth0]/state"));
+
+        let req = client.get().path_raw(raw.clone()).request();
+        assert_eq!(req.path, vec![raw]);
+    }
+
+    #[tokio::test]
+    async fn use_model_from_capabilities() {
+        use crate::client::Capabilities;
+        use crate::gen::gnmi::CapabilityResponse;
+
+        let mut client = lazy_client();
+
+        let model = ModelData {
+            name: "openconfig-interfaces".to_string(),
+            organization: "OpenConfig working group".to_string(),
+            version: "3.0.0".to_string(),
+        };
+        *client.capabilities_cache.lock().await = Some(Capabilities(CapabilityResponse {
+            supported_models: vec![model.clone()],
+            ..Default::default()
+        }));
+
+        let req = client
+            .get()
+            .use_model_named("openconfig-interfaces")
+            .await
+            .unwrap()
+            .request();
+        assert_eq!(req.use_models, vec![model]);
+
+        let unknown = client.get().use_model_named("openconfig-system").await;
+        assert!(matches!(unknown, Err(GinmiError::UnsupportedModelError(_))));
+    }
+
+    #[test]
+    fn group_updates_with_prefix() {
+        let paths = [
+            path("/interfaces/interface[name=eth0]/state"),
+            path("/interfaces/interface[name=eth1]/state"),
+        ];
+        let notifications = Notifications::from(vec![
+            notification(
+                Some("/interfaces/interface[name=eth0]"),
+                &["state/counters/in-octets", "state/counters/out-octets"],
+            ),
+            notification(
+                Some("/interfaces/interface[name=eth1]"),
+                &["state/counters/in-octets"],
+            ),
+        ]);
+
+        let groups = group_by_path(&paths, notifications);
+
+        assert_eq!(groups[&paths[0]].len(), 2);
+        assert_eq!(groups[&paths[1]].len(), 1);
+        assert_eq!(
+            groups[&paths[1]][0].path,
+            path("/interfaces/interface[name=eth1]/state/counters/in-octets")
+        );
+    }
+
+    #[test]
+    fn group_updates_with_wildcards_and_modules() {
+        let paths = [
+            path("/openconfig-interfaces:interfaces/interface[name=*]/state"),
+            path("/system/name"),
+        ];
+        let notifications = Notifications::from(vec![notification(
+            None,
+            &["/interfaces/interface[name=eth0]/state", "/system/name"],
+        )]);
+
+        let groups = group_by_path(&paths, notifications);
+
+        assert_eq!(groups[&paths[0]].len(), 1);
+        assert_eq!(groups[&paths[1]].len(), 1);
+    }
+
+    #[test]
+    fn group_unmatched_updates() {
+        let paths = [path("/system/name")];
+        let notifications =
+            Notifications::from(vec![notification(None, &["/srl_nokia-system:name"])]);
+
+        let groups = group_by_path(&paths, notifications);
+
+        assert_eq!(groups[&paths[0]].len(), 1);
+    }
+
+    #[test]
+    fn values_keyed_by_list_key() {
+        let notifications = Notifications::from(vec![
+            notification(
+                Some("/interfaces"),
+                &[
+                    "interface[name=eth0]/state/oper-status",
+                    "interface[name=eth1]/state/oper-status",
+                ],
+            ),
+            notification(None, &["/system/name"]),
+        ]);
+
+        let values = values_by_key("name", notifications);
+
+        assert_eq!(values.len(), 2);
+        assert_eq!(values["eth0"], TypedValue::Uint(1));
+        assert_eq!(values["eth1"], TypedValue::Uint(1));
+    }
+}
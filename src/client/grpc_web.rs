@@ -0,0 +1,496 @@
+//! Connect to a gNMI-capable Endpoint using gRPC-Web over HTTP/1.1
+//!
+//! Some environments only allow HTTP/1.1 traffic, e.g. because of proxies that are not able
+//! to forward HTTP/2. [gRPC-Web](https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-WEB.md)
+//! carries gRPC messages over HTTP/1.1 by encoding the trailers of a response into its body.
+//! The target device, or a proxy in front of it, needs to accept gRPC-Web requests.
+//!
+//! gRPC-Web does not support streaming requests, so polling a subscription with
+//! [`SubscribeStream::poll`](super::SubscribeStream::poll) only works if all proxies
+//! forward the request body as it is sent. Subscriptions themselves work as usual.
+//!
+//! Targets with a `https` URI are connected to with TLS, negotiating HTTP/1.1 with ALPN. The
+//! certificate of the target device is verified like for any other [`Client`], against the CA
+//! given to [`ClientBuilder::tls`] or the trust store of the operating system, and the TLS
+//! versions and cipher suites can be restricted the same way.
+//!
+//! # Examples
+//! ```rust
+//! # use ginmi::client::Client;
+//! # fn main() -> std::io::Result<()> {
+//! # tokio_test::block_on(async {
+//! # const CA_CERT: &str = "CA Certificate";
+//! let mut client = Client::builder("https://gnmi-proxy:8443")
+//!     .tls(CA_CERT, "gnmi-proxy")
+//!     .credentials("admin", "password1")
+//!     .grpc_web()
+//!     .build()
+//!     .await?;
+//! # })}
+//! ```
+use super::resolve::StaticResolver;
+use super::tls::TlsSession;
+use super::transport::TonicTransport;
+use super::ClientBuilder;
+use crate::auth::AuthInterceptor;
+use crate::client::client::{auth_interceptor, request_limit, static_headers};
+use crate::client::{Client, ConnectionSecurity};
+use crate::error::GinmiError;
+use crate::gen::gnmi::g_nmi_client::GNmiClient;
+use http::header::{HeaderName, ACCEPT, CONTENT_TYPE, HOST, TE};
+use http::{HeaderMap, HeaderValue, Request, Response, Uri, Version};
+use hyper::body::Bytes;
+use hyper::client::connect::{Connected, Connection};
+use hyper::client::HttpConnector;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio_rustls::client::TlsStream;
+use tonic::body::BoxBody;
+use tonic::codegen::{Body, InterceptedService};
+use tonic::Status;
+use tower_service::Service;
+
+const GRPC_WEB: &str = "application/grpc-web+proto";
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Flag of the frame that carries the trailers of a response.
+const TRAILERS_FLAG: u8 = 0x80;
+
+pub type GrpcWebConnection = InterceptedService<GrpcWebChannel, AuthInterceptor>;
+
+/// Builder for [`Client`]s that use gRPC-Web over HTTP/1.1.
+pub struct GrpcWebClientBuilder<'a> {
+    builder: ClientBuilder<'a>,
+}
+
+impl<'a> GrpcWebClientBuilder<'a> {
+    /// Consume the [`GrpcWebClientBuilder`] and return a [`Client`].
+    ///
+    /// The connection to the target device is established with the first request.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::InvalidUriError`] if specified target, origin or authority is
+    ///   not valid.
+    /// - Returns [`GinmiError::InvalidCredentialsError`] if the credentials contain characters
+    ///   that are not allowed in gRPC metadata.
+    /// - Returns [`GinmiError::InvalidUriError`] if the target uses `https`, but has no host
+    ///   name to verify the TLS certificate against.
+    /// - Returns [`GinmiError::TlsConfigError`] if TLS has been configured, but the target does
+    ///   not use `https`, or if the TLS settings are invalid.
+    pub async fn build(self) -> Result<Client<TonicTransport<GrpcWebConnection>>, GinmiError> {
+        let uri = match Uri::from_str(&self.builder.target) {
+            Ok(u) => u,
+            Err(e) => return Err(GinmiError::InvalidUriError(e.to_string())),
+        };
+        // requests are sent to the target, the origin only determines the host header
+        let host = match self.builder.origin_uri(&uri)? {
+            Some(origin) => origin
+                .authority()
+                .map(|authority| HeaderValue::from_str(authority.as_str()))
+                .transpose()
+                .map_err(|e| GinmiError::InvalidUriError(e.to_string()))?,
+            None => None,
+        };
+
+        let configured =
+            self.builder.tls_settings.is_some() || self.builder.tls_options.is_restricted();
+        let tls = match uri.scheme_str() {
+            Some("https") => Some(self.builder.tls_session(&self.builder.target)?.http1()),
+            _ if configured => {
+                return Err(GinmiError::TlsConfigError(format!(
+                    "TLS is configured, but target {} does not use https",
+                    uri
+                )))
+            }
+            _ => None,
+        };
+        let (security, tls_details) = match &tls {
+            Some(tls) => (ConnectionSecurity::Tls, tls.details()),
+            None => (ConnectionSecurity::Insecure, Default::default()),
+        };
+
+        let mut http = HttpConnector::new_with_resolver(self.builder.resolver.clone());
+        http.enforce_http(false);
+        http.set_nodelay(true);
+        http.set_local_address(self.builder.local_address);
+        let channel = GrpcWebChannel {
+            client: hyper::Client::builder().build(GrpcWebConnector { http, tls }),
+            host,
+        };
+
+        let mut headers = self.builder.headers.clone();
+        if let Some(user_agent) = self.builder.user_agent {
+            headers.push(("user-agent", user_agent));
+        }
+        let auth = auth_interceptor(self.builder.creds.clone())?
+            .with_headers(static_headers(&headers)?)
+            .with_correlation_ids(self.builder.correlation_ids);
+        let svc = InterceptedService::new(channel, auth.clone());
+
+        Ok(Client {
+            inner: TonicTransport::new(GNmiClient::with_origin(svc, uri)),
+            capabilities_cache: Arc::new(Mutex::new(None)),
+            security,
+            auth,
+            subscribe_buffer: self.builder.subscribe_buffer,
+            endpoints: Vec::new(),
+            local_address: self.builder.local_address,
+            resolver: self.builder.resolver,
+            captured_request: self.builder.debug_capture.then(Default::default),
+            connectivity: None,
+            reconnect_backoff: None,
+            default_encoding: self.builder.default_encoding,
+            request_limit: request_limit(self.builder.max_concurrent_requests),
+            tls_session: None,
+            tls_details,
+        })
+    }
+}
+
+impl<'a> From<ClientBuilder<'a>> for GrpcWebClientBuilder<'a> {
+    fn from(builder: ClientBuilder<'a>) -> Self {
+        GrpcWebClientBuilder { builder }
+    }
+}
+
+/// Service that sends gRPC requests as gRPC-Web requests over HTTP/1.1.
+#[derive(Debug, Clone)]
+pub struct GrpcWebChannel {
+    client: hyper::Client<GrpcWebConnector, BoxBody>,
+    host: Option<HeaderValue>,
+}
+
+/// Connector that establishes a TLS session on top of its connections if the target uses
+/// `https`.
+#[derive(Debug, Clone)]
+struct GrpcWebConnector {
+    http: HttpConnector<StaticResolver>,
+    tls: Option<TlsSession>,
+}
+
+impl Service<Uri> for GrpcWebConnector {
+    type Response = GrpcWebStream;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.http.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        match &self.tls {
+            Some(tls) => {
+                let connecting = tls.connector(self.http.clone()).call(uri);
+                Box::pin(async move { Ok::<_, BoxError>(GrpcWebStream::Tls(connecting.await?)) })
+            }
+            None => {
+                let connecting = self.http.call(uri);
+                Box::pin(async move { Ok::<_, BoxError>(GrpcWebStream::Plain(connecting.await?)) })
+            }
+        }
+    }
+}
+
+/// Connection of a [`GrpcWebConnector`], with or without TLS.
+enum GrpcWebStream {
+    Plain(TcpStream),
+    Tls(TlsStream<TcpStream>),
+}
+
+impl Connection for GrpcWebStream {
+    fn connected(&self) -> Connected {
+        match self {
+            GrpcWebStream::Plain(stream) => stream.connected(),
+            GrpcWebStream::Tls(stream) => stream.get_ref().0.connected(),
+        }
+    }
+}
+
+impl AsyncRead for GrpcWebStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            GrpcWebStream::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            GrpcWebStream::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for GrpcWebStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            GrpcWebStream::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            GrpcWebStream::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            GrpcWebStream::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            GrpcWebStream::Tls(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            GrpcWebStream::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            GrpcWebStream::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+impl Service<Request<BoxBody>> for GrpcWebChannel {
+    type Response = Response<GrpcWebBody>;
+    type Error = hyper::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.client.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<BoxBody>) -> Self::Future {
+        // the body is framed the same way, only the headers differ
+        *request.version_mut() = Version::HTTP_11;
+        let headers = request.headers_mut();
+        headers.remove(TE);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(GRPC_WEB));
+        headers.insert(ACCEPT, HeaderValue::from_static(GRPC_WEB));
+        headers.insert("x-grpc-web", HeaderValue::from_static("1"));
+        if let Some(host) = &self.host {
+            headers.insert(HOST, host.clone());
+        }
+
+        let response = self.client.request(request);
+        Box::pin(async move {
+            let (mut parts, body) = response.await?.into_parts();
+            parts.headers.insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static("application/grpc+proto"),
+            );
+            Ok(Response::from_parts(parts, GrpcWebBody::new(body)))
+        })
+    }
+}
+
+/// Body of a gRPC-Web response, passing on the messages and decoding the trailers.
+#[derive(Debug, Default)]
+pub struct GrpcWebBody {
+    inner: hyper::Body,
+    buffer: Vec<u8>,
+    trailers: Option<HeaderMap>,
+    done: bool,
+}
+
+impl GrpcWebBody {
+    fn new(inner: hyper::Body) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+            trailers: None,
+            done: false,
+        }
+    }
+
+    /// Take the next complete message frame out of the buffer. Decodes the trailers instead,
+    /// if they follow.
+    fn next_frame(&mut self) -> Result<Option<Bytes>, Status> {
+        if self.buffer.len() < 5 {
+            return Ok(None);
+        }
+
+        let length = u32::from_be_bytes([
+            self.buffer[1],
+            self.buffer[2],
+            self.buffer[3],
+            self.buffer[4],
+        ]) as usize;
+        if self.buffer.len() < 5 + length {
+            return Ok(None);
+        }
+
+        let frame: Vec<u8> = self.buffer.drain(..5 + length).collect();
+        if frame[0] & TRAILERS_FLAG == 0 {
+            return Ok(Some(Bytes::from(frame)));
+        }
+
+        self.trailers = Some(decode_trailers(&frame[5..])?);
+        self.buffer.clear();
+        self.done = true;
+        Ok(None)
+    }
+}
+
+impl Body for GrpcWebBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        loop {
+            if let Some(frame) = self.next_frame().transpose() {
+                return Poll::Ready(Some(frame));
+            }
+            if self.done {
+                return Poll::Ready(None);
+            }
+
+            match ready!(Pin::new(&mut self.inner).poll_data(cx)) {
+                Some(Ok(chunk)) => self.buffer.extend_from_slice(&chunk),
+                Some(Err(e)) => return Poll::Ready(Some(Err(Status::from_error(Box::new(e))))),
+                None => {
+                    self.done = true;
+                    if !self.buffer.is_empty() {
+                        return Poll::Ready(Some(Err(Status::internal(
+                            "gRPC-Web response ended within a message",
+                        ))));
+                    }
+                }
+            }
+        }
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(self.trailers.take()))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.done && self.buffer.is_empty()
+    }
+}
+
+/// Decode the trailers of a gRPC-Web response, which are encoded like HTTP/1.1 headers.
+fn decode_trailers(payload: &[u8]) -> Result<HeaderMap, Status> {
+    let invalid = || Status::internal("invalid trailers in gRPC-Web response");
+    let mut trailers = HeaderMap::new();
+
+    for line in payload.split(|b| *b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+
+        let separator = line.iter().position(|b| *b == b':').ok_or_else(invalid)?;
+        let name = HeaderName::from_bytes(line[..separator].trim_ascii()).map_err(|_| invalid())?;
+        let value =
+            HeaderValue::from_bytes(line[separator + 1..].trim_ascii()).map_err(|_| invalid())?;
+        trailers.append(name, value);
+    }
+
+    Ok(trailers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::gnmi::CapabilityResponse;
+    use prost::Message;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn frame(flag: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![flag];
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /// Accept a single connection and answer its request with `body`, returning the request
+    /// head.
+    async fn serve(listener: TcpListener, body: Vec<u8>) -> String {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        while !request.ends_with(b"\r\n\r\n") {
+            request.push(stream.read_u8().await.unwrap());
+        }
+
+        let head = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: {}\r\ncontent-length: {}\r\n\r\n",
+            GRPC_WEB,
+            body.len()
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(&body).await.unwrap();
+        String::from_utf8(request).unwrap()
+    }
+
+    #[tokio::test]
+    async fn unary_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = format!("http://{}", listener.local_addr().unwrap());
+
+        let response = CapabilityResponse {
+            g_nmi_version: "0.10.0".to_string(),
+            ..Default::default()
+        };
+        let mut body = frame(0, &response.encode_to_vec());
+        body.extend(frame(TRAILERS_FLAG, b"grpc-status:0\r\ngrpc-message:\r\n"));
+
+        let mut client = Client::builder(&target)
+            .credentials("admin", "admin")
+            .authority("router1.example.com")
+            .grpc_web()
+            .build()
+            .await
+            .unwrap();
+
+        let (request, capabilities) = tokio::join!(serve(listener, body), client.capabilities());
+        assert!(request.contains(GRPC_WEB));
+        assert!(request.contains("username: admin"));
+        assert!(request.contains("host: router1.example.com\r\n"));
+        assert_eq!(capabilities.unwrap().gnmi_version(), "0.10.0");
+    }
+
+    #[tokio::test]
+    async fn status_in_trailers() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = format!("http://{}", listener.local_addr().unwrap());
+        let body = frame(
+            TRAILERS_FLAG,
+            b"grpc-status: 12\r\ngrpc-message: not here\r\n",
+        );
+
+        let mut client = Client::builder(&target).grpc_web().build().await.unwrap();
+
+        let (_, capabilities) = tokio::join!(serve(listener, body), client.capabilities());
+        assert!(capabilities.unwrap_err().is_unsupported());
+    }
+
+    #[tokio::test]
+    async fn tls_follows_scheme() {
+        let client = Client::builder("https://test:57400")
+            .grpc_web()
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(client.connection_security(), ConnectionSecurity::Tls);
+        assert_eq!(client.tls_details(), None);
+
+        let client = Client::builder("http://test:57400")
+            .tls_native_roots()
+            .grpc_web()
+            .build()
+            .await;
+        assert!(matches!(client, Err(GinmiError::TlsConfigError(_))));
+    }
+}
@@ -17,12 +17,93 @@
 //!     .await?;
 //! # })}
 //! ```
+//!
+//! # Spawning tasks
+//! [`Client`], [`SubscribeStream`] and the futures of all requests are `Send + 'static`, so
+//! they can be moved into tasks spawned on a multi-threaded runtime, e.g. to run one
+//! telemetry collector per device:
+//! ```rust
+//! # use ginmi::client::{Client, Subscription, SubscriptionList};
+//! # use tokio_stream::StreamExt;
+//! # tokio_test::block_on(async {
+//! # const CA_CERT: &str = "CA Certificate";
+//! let mut client = Client::builder("https://clab-srl01-srl:57400")
+//!     .tls(CA_CERT, "clab-srl01-srl")
+//!     .credentials("admin", "password1")
+//!     .build()
+//!     .await
+//!     .unwrap();
+//!
+//! tokio::spawn(async move {
+//!     let subscriptions = SubscriptionList::builder()
+//!         .subscription(Subscription::new("/system/name".parse().unwrap()))
+//!         .build()
+//!         .unwrap();
+//!
+//!     let mut stream = client.subscribe(subscriptions).await.unwrap();
+//!     while let Some(response) = stream.next().await {
+//!         println!("{:?}", response);
+//!     }
+//! });
+//! # });
+//! ```
+//!
+//! # Metrics
+//!
+//! If the `metrics` feature is enabled, the following metrics are recorded with the
+//! [`metrics`](https://docs.rs/metrics) facade, labeled by the `method` of the RPC:
+//!
+//! | Metric                                 | Type      | Description                              |
+//! |----------------------------------------|-----------|------------------------------------------|
+//! | `gnmi_client_requests_total`           | counter   | Requests sent                            |
+//! | `gnmi_client_errors_total`             | counter   | Failed requests, labeled by their `code` |
+//! | `gnmi_client_request_duration_seconds` | histogram | Time until the response was received     |
+//! | `gnmi_client_received_bytes_total`     | counter   | Encoded size of all received messages    |
+//!
+//! Without the feature, recording compiles to nothing.
+mod backoff;
 mod capabilities;
+#[allow(clippy::module_inception)]
 mod client;
+mod clock;
+mod connectivity;
 #[cfg(feature = "dangerous_configuration")]
 #[cfg_attr(docsrs, doc(cfg(feature = "dangerous_configuration")))]
 pub mod dangerous;
+mod get;
+#[cfg(feature = "grpc_web")]
+#[cfg_attr(docsrs, doc(cfg(feature = "grpc_web")))]
+pub mod grpc_web;
+mod resolve;
+mod set;
+mod state;
+mod stats;
+mod subscribe;
+pub mod tls;
+pub mod transport;
+
+pub use client::{
+    Client, ClientBuilder, ConnectionSecurity, DEFAULT_ENCODING, DEFAULT_SUBSCRIBE_BUFFER,
+};
+pub use clock::ClockSkew;
+pub use connectivity::{ConnectivityState, ConnectivityStream};
 
-pub use client::{Client, ClientBuilder};
+pub use capabilities::{Capabilities, CapabilitiesChange, CapabilitiesStream, Encoding, ModelData};
+pub use get::{DataType, GetRequestBuilder, SubtreeSnapshots};
+pub use set::{Operation, SetRequest, SetRequestBuilder, SetResponse, UpdateResult};
+pub use state::SubscriptionState;
+#[cfg(feature = "dialout")]
+pub(crate) use subscribe::decode_response;
+pub use subscribe::{
+    FlatUpdate, FlatUpdates, SubscribeEvent, SubscribeResponse, SubscribeStream, Subscription,
+    SubscriptionBuilder, SubscriptionError, SubscriptionList, SubscriptionListMode,
+    SubscriptionMode, ThrottledUpdates,
+};
 
-pub use capabilities::{Capabilities, Encoding};
+/// Create a [`Client`] whose channel only connects once a request is sent. Nothing listens on
+/// the target, so requests fail right away. `connect_lazy` needs a tokio runtime.
+#[cfg(test)]
+pub(crate) fn lazy_client() -> crate::inventory::PooledClient {
+    let channel = tonic::transport::Channel::from_static("http://127.0.0.1:1").connect_lazy();
+    Client::from_channel(channel, None).unwrap()
+}
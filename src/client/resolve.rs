@@ -0,0 +1,86 @@
+//! Static host overrides, so target devices can be addressed by names that DNS does not know.
+//!
+//! Lab devices are often addressed by the name their certificate was issued for, e.g. the
+//! node names of [Containerlab](https://containerlab.dev/), without a DNS entry for it.
+//! [`StaticResolver`] resolves such names to the configured addresses and all other names
+//! with the resolver of the operating system, so the name can still be used for TLS.
+use hyper::client::connect::dns::{GaiResolver, Name};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower_service::Service;
+
+/// Resolver that looks up host names in a static table before asking the operating system.
+#[derive(Debug, Clone)]
+pub struct StaticResolver {
+    hosts: Arc<HashMap<String, SocketAddr>>,
+    system: GaiResolver,
+}
+
+impl Default for StaticResolver {
+    fn default() -> Self {
+        Self {
+            hosts: Arc::default(),
+            system: GaiResolver::new(),
+        }
+    }
+}
+
+impl StaticResolver {
+    /// Resolve `host` to `address`, replacing an earlier address of the same host.
+    pub(crate) fn insert(&mut self, host: &str, address: SocketAddr) {
+        Arc::make_mut(&mut self.hosts).insert(host.to_ascii_lowercase(), address);
+    }
+
+    /// Whether any host has been added.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.hosts.is_empty()
+    }
+}
+
+impl Service<Name> for StaticResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.system.poll_ready(cx)
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        if let Some(address) = self.hosts.get(&name.as_str().to_ascii_lowercase()) {
+            return Box::pin(std::future::ready(Ok(vec![*address].into_iter())));
+        }
+
+        let resolving = self.system.call(name);
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> = resolving.await?.collect();
+            Ok(addresses.into_iter())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn static_hosts_take_precedence() {
+        let address = "192.0.2.10:57400".parse().unwrap();
+        let mut resolver = StaticResolver::default();
+        resolver.insert("Clab-SRL01-SRL", address);
+
+        let name = Name::from_str("clab-srl01-srl").unwrap();
+        let resolved: Vec<_> = resolver.call(name).await.unwrap().collect();
+        assert_eq!(resolved, [address]);
+
+        let name = Name::from_str("localhost").unwrap();
+        let resolved: Vec<_> = resolver.call(name).await.unwrap().collect();
+        assert!(resolved.iter().all(|address| address.ip().is_loopback()));
+    }
+}
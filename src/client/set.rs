@@ -0,0 +1,1080 @@
+//! Modify the state of data elements on a target device.
+//!
+//! Set requests are created with [`Client::set`](super::Client::set), as described in the
+//! [gNMI Specification Section 3.4](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-specification.md#34-modifying-state).
+use super::client::echo_request_id;
+use super::transport::GnmiTransport;
+use super::{stats, Client};
+use crate::error::{self, GinmiError};
+use crate::extension::{self, Extension};
+use crate::gen::gnmi::{
+    SetRequest as GnmiSetRequest, SetResponse as GnmiSetResponse, UpdateResult as GnmiUpdateResult,
+};
+use crate::notification::Update;
+use crate::path::{origins_on_prefix, Path, PathElement};
+use crate::value::TypedValue;
+use prost::Message;
+use tonic::metadata::MetadataMap;
+
+pub use crate::gen::gnmi::update_result::Operation;
+
+/// Builder for set requests.
+///
+/// All deletes, replaces and updates of a set request are applied by the target device as
+/// a single transaction. Created by [`Client::set`](super::Client::set).
+///
+/// Regardless of the order in which the builder methods are called, the request carries all
+/// deletes first, followed by the replaces, the updates and the union replaces, which is the
+/// order in which the
+/// [gNMI Specification Section 3.4.3](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-specification.md#343-transactions)
+/// requires the target device to apply them. Operations of the same kind keep the order in
+/// which they were added.
+#[derive(Debug)]
+pub struct SetRequestBuilder<'a, T> {
+    client: &'a mut Client<T>,
+    prefix: Option<Path>,
+    deletes: Vec<Path>,
+    replaces: Vec<Update>,
+    updates: Vec<Update>,
+    union_replaces: Vec<Update>,
+    chunk_json_at_lists: bool,
+}
+
+impl<'a, T: GnmiTransport> SetRequestBuilder<'a, T> {
+    pub(crate) fn new(client: &'a mut Client<T>) -> Self {
+        Self {
+            client,
+            prefix: None,
+            deletes: Vec::new(),
+            replaces: Vec::new(),
+            updates: Vec::new(),
+            union_replaces: Vec::new(),
+            chunk_json_at_lists: false,
+        }
+    }
+
+    /// Set a prefix that is prepended to all paths of the request.
+    pub fn prefix(mut self, prefix: Path) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    /// Delete the data element at `path` and all of its children.
+    pub fn delete(mut self, path: Path) -> Self {
+        self.deletes.push(path);
+        self
+    }
+
+    /// Replace the data element at `path` with `value`, removing all children
+    /// that are not contained in `value`.
+    pub fn replace(mut self, path: Path, value: TypedValue) -> Self {
+        self.replaces.push(update(path, value));
+        self
+    }
+
+    /// Update the data element at `path` with `value`, leaving all children that
+    /// are not contained in `value` untouched.
+    pub fn update(mut self, path: Path, value: TypedValue) -> Self {
+        self.updates.push(update(path, value));
+        self
+    }
+
+    #[cfg(feature = "raw")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "raw")))]
+    /// Delete the data element at a generated protobuf path, see [`crate::raw`].
+    pub fn delete_raw(self, path: crate::raw::Path) -> Self {
+        self.delete(path.into())
+    }
+
+    #[cfg(feature = "raw")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "raw")))]
+    /// Replace the data element at a generated protobuf path, see [`crate::raw`].
+    pub fn replace_raw(self, path: crate::raw::Path, value: TypedValue) -> Self {
+        self.replace(path.into(), value)
+    }
+
+    #[cfg(feature = "raw")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "raw")))]
+    /// Update the data element at a generated protobuf path, see [`crate::raw`].
+    pub fn update_raw(self, path: crate::raw::Path, value: TypedValue) -> Self {
+        self.update(path.into(), value)
+    }
+
+    /// Merge `value` with the values of all other union replaces of the request and replace
+    /// the data element at `path` with the result, as described in the
+    /// [gNMI Union Replace Specification](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-union_replace.md).
+    ///
+    /// Union replaces were introduced in gNMI 0.10.0. If the request contains any, the
+    /// capabilities of the target device are checked before the request is sent, see
+    /// [`SetRequestBuilder::send`].
+    pub fn union_replace(mut self, path: Path, value: TypedValue) -> Self {
+        self.union_replaces.push(update(path, value));
+        self
+    }
+
+    /// Replace the data element at `path` with the given JSON document.
+    ///
+    /// The document is sent as a [`TypedValue::JsonIetf`] value.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::DecodeError`] if the document can not be serialized.
+    pub fn replace_json(self, path: Path, value: serde_json::Value) -> Result<Self, GinmiError> {
+        let value = json_ietf_value(&value)?;
+        Ok(self.replace(path, value))
+    }
+
+    /// Update the data element at `path` with the given JSON document.
+    ///
+    /// The document is sent as a [`TypedValue::JsonIetf`] value.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::DecodeError`] if the document can not be serialized.
+    pub fn update_json(self, path: Path, value: serde_json::Value) -> Result<Self, GinmiError> {
+        let value = json_ietf_value(&value)?;
+        Ok(self.update(path, value))
+    }
+
+    /// Add the operations that converge the configuration at `base_path` from the
+    /// `current` to the `desired` JSON document.
+    ///
+    /// Both documents are compared member by member:
+    /// - members that are missing in `desired` are deleted.
+    /// - members that were added or changed are updated with their new value.
+    /// - arrays and members that changed their type are replaced as a whole, as their
+    ///   elements can not be addressed individually.
+    ///
+    /// Members that are equal in both documents are left untouched, so an empty request
+    /// is produced if the documents match.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::DecodeError`] if a value can not be serialized.
+    pub fn from_diff(
+        mut self,
+        current: &serde_json::Value,
+        desired: &serde_json::Value,
+        base_path: Path,
+    ) -> Result<Self, GinmiError> {
+        self.push_diff(current, desired, base_path)?;
+        Ok(self)
+    }
+
+    fn push_diff(
+        &mut self,
+        current: &serde_json::Value,
+        desired: &serde_json::Value,
+        path: Path,
+    ) -> Result<(), GinmiError> {
+        use serde_json::Value;
+
+        match (current, desired) {
+            (current, desired) if current == desired => {}
+            (Value::Object(current), Value::Object(desired)) => {
+                for key in current.keys() {
+                    if !desired.contains_key(key) {
+                        self.deletes.push(child(&path, key));
+                    }
+                }
+                for (key, value) in desired {
+                    match current.get(key) {
+                        Some(old) => self.push_diff(old, value, child(&path, key))?,
+                        None => self
+                            .updates
+                            .push(update(child(&path, key), json_ietf_value(value)?)),
+                    }
+                }
+            }
+            (Value::Object(_), _) | (_, Value::Object(_)) | (_, Value::Array(_)) => {
+                self.replaces.push(update(path, json_ietf_value(desired)?));
+            }
+            _ => self.updates.push(update(path, json_ietf_value(desired)?)),
+        }
+
+        Ok(())
+    }
+
+    /// Split replaces and updates with large JSON documents into multiple set requests at
+    /// the lists they contain, e.g. to stay below the maximum message size of the target
+    /// device when replacing the configuration of all interfaces.
+    ///
+    /// Every JSON array of objects is treated as a list. The first request carries all
+    /// operations of the builder with the lists removed from their JSON documents, followed
+    /// by one update request per list. Replacing a container therefore first clears its
+    /// lists and then adds their entries again, so the result is the same as that of a single
+    /// request. The requests are sent one after another, use
+    /// [`SetRequestBuilder::send_with_progress`] to follow them.
+    ///
+    /// The requests are not applied as a single transaction. If one of them fails, the
+    /// requests preceding it remain applied.
+    pub fn chunk_json_at_lists(mut self, chunk_json_at_lists: bool) -> Self {
+        self.chunk_json_at_lists = chunk_json_at_lists;
+        self
+    }
+
+    /// Add all operations of `request`, e.g. of a request that was stored with
+    /// [`SetRequestBuilder::to_json`], to replay it. The prefix of `request` replaces the
+    /// prefix of the builder, if it has one.
+    pub fn extend(mut self, request: SetRequest) -> Self {
+        if request.prefix.is_some() {
+            self.prefix = request.prefix;
+        }
+        self.deletes.extend(request.deletes);
+        self.replaces.extend(request.replaces);
+        self.updates.extend(request.updates);
+        self.union_replaces.extend(request.union_replaces);
+        self
+    }
+
+    /// Returns the operations of the builder as a [`SetRequest`], e.g. to review or store
+    /// them before they are sent.
+    pub fn to_request(&self) -> SetRequest {
+        SetRequest {
+            prefix: self.prefix.clone(),
+            deletes: self.deletes.clone(),
+            replaces: self.replaces.clone(),
+            updates: self.updates.clone(),
+            union_replaces: self.union_replaces.clone(),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    /// Serialize the operations of the builder, see [`SetRequest::to_json`].
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::DecodeError`] if a JSON value is not valid JSON.
+    pub fn to_json(&self) -> Result<serde_json::Value, GinmiError> {
+        self.to_request().to_json()
+    }
+
+    /// Send the set request to the target device and return its [`SetResponse`].
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::GrpcError`] if the target device rejected the request. Use
+    ///   [`GinmiError::is_unsupported`] to check if the device does not support set requests
+    ///   at all.
+    /// - Returns [`GinmiError::UnsupportedOperationError`] if the request contains union
+    ///   replaces, but the target device supports a gNMI version older than 0.10.0.
+    /// - Returns [`GinmiError::PathParseError`] if a path has a different origin than the
+    ///   prefix, as only the prefix may have an origin.
+    /// - Returns [`GinmiError::DecodeError`] if the response could not be decoded.
+    pub async fn send(self) -> Result<SetResponse, GinmiError> {
+        self.send_with_metadata()
+            .await
+            .map(|(response, _)| response)
+    }
+
+    /// Send the set request to the target device and return its [`SetResponse`], together
+    /// with the metadata of the response.
+    ///
+    /// The metadata contains both the headers and the trailers the target device sent,
+    /// e.g. vendor-specific information that is not part of the protobuf messages.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::GrpcError`] if the target device rejected the request.
+    /// - Returns [`GinmiError::UnsupportedOperationError`] if the request contains union
+    ///   replaces, but the target device supports a gNMI version older than 0.10.0.
+    /// - Returns [`GinmiError::PathParseError`] if a path has a different origin than the
+    ///   prefix, as only the prefix may have an origin.
+    /// - Returns [`GinmiError::DecodeError`] if the response could not be decoded.
+    pub async fn send_with_metadata(self) -> Result<(SetResponse, MetadataMap), GinmiError> {
+        self.send_chunks(|_, _| {}).await
+    }
+
+    /// Send the set request to the target device like [`SetRequestBuilder::send`], calling
+    /// `progress` with the number of sent and of all requests after every request, if the
+    /// request is [chunked](SetRequestBuilder::chunk_json_at_lists).
+    ///
+    /// The [`SetResponse`] combines the responses to all requests: it has the timestamp of the
+    /// last response and the results and extensions of all of them.
+    ///
+    /// # Errors
+    /// - Returns the first error of any of the requests, see [`SetRequestBuilder::send`].
+    pub async fn send_with_progress(
+        self,
+        progress: impl FnMut(usize, usize),
+    ) -> Result<SetResponse, GinmiError> {
+        self.send_chunks(progress)
+            .await
+            .map(|(response, _)| response)
+    }
+
+    async fn send_chunks(
+        self,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<(SetResponse, MetadataMap), GinmiError> {
+        if !self.union_replaces.is_empty() {
+            let capabilities = self.client.capabilities_cached().await?;
+            if !supports_union_replace(capabilities.gnmi_version()) {
+                return Err(GinmiError::UnsupportedOperationError(format!(
+                    "union replace requires gNMI 0.10.0, but target device supports {}",
+                    capabilities.gnmi_version()
+                )));
+            }
+        }
+
+        let mut requests = if self.chunk_json_at_lists {
+            self.chunked_requests()?
+        } else {
+            vec![self.request()]
+        };
+        for req in &mut requests {
+            set_origins_on_prefix(req)?;
+        }
+        let total = requests.len();
+
+        let mut combined: Option<(SetResponse, MetadataMap)> = None;
+        for (sent, req) in requests.into_iter().enumerate() {
+            self.client.capture(&req);
+            let (req, id) = self.client.correlate(req)?;
+            let _permit = self.client.request_permit().await;
+            let res = stats::observe("set", self.client.inner.set(req)).await;
+            let res = echo_request_id(res, id)?;
+            stats::received("set", res.get_ref().encoded_len());
+            let (metadata, res, _) = res.into_parts();
+            let mut response = SetResponse::try_from(res)?;
+
+            if let Some((mut previous, _)) = combined.take() {
+                previous.results.append(&mut response.results);
+                previous.extensions.append(&mut response.extensions);
+                previous.timestamp = response.timestamp;
+                response = previous;
+            }
+            combined = Some((response, metadata));
+            progress(sent + 1, total);
+        }
+
+        Ok(combined.expect("at least one request is sent"))
+    }
+
+    /// Split the request at the lists of its JSON documents, see
+    /// [`SetRequestBuilder::chunk_json_at_lists`].
+    fn chunked_requests(&self) -> Result<Vec<GnmiSetRequest>, GinmiError> {
+        let mut first = self.request();
+        let mut lists = Vec::new();
+
+        first.replace = Vec::new();
+        for replace in &self.replaces {
+            let (stripped, extracted) = split_lists(replace)?;
+            first
+                .replace
+                .push(stripped.unwrap_or_else(|| replace.clone()).into());
+            lists.extend(extracted);
+        }
+
+        first.update = Vec::new();
+        for update in &self.updates {
+            let (stripped, extracted) = split_lists(update)?;
+            match stripped {
+                // an update with an empty document does not change anything
+                Some(stripped) if is_empty_json(&stripped.value) => {}
+                Some(stripped) => first.update.push(stripped.into()),
+                None => first.update.push(update.clone().into()),
+            }
+            lists.extend(extracted);
+        }
+
+        let mut requests = vec![first];
+        requests.extend(lists.into_iter().map(|list| GnmiSetRequest {
+            prefix: self.prefix.clone().map(Into::into),
+            update: vec![list.into()],
+            ..Default::default()
+        }));
+        Ok(requests)
+    }
+
+    fn request(&self) -> GnmiSetRequest {
+        let updates = |updates: &[Update]| updates.iter().cloned().map(Into::into).collect();
+
+        GnmiSetRequest {
+            prefix: self.prefix.clone().map(Into::into),
+            delete: self.deletes.iter().cloned().map(Into::into).collect(),
+            replace: updates(&self.replaces),
+            update: updates(&self.updates),
+            union_replace: updates(&self.union_replaces),
+            ..Default::default()
+        }
+    }
+}
+
+/// Move the origins of all paths of `request` to its prefix, see [`origins_on_prefix`].
+fn set_origins_on_prefix(request: &mut GnmiSetRequest) -> Result<(), GinmiError> {
+    let updates = request
+        .replace
+        .iter_mut()
+        .chain(&mut request.update)
+        .chain(&mut request.union_replace)
+        .filter_map(|update| update.path.as_mut());
+    let paths = request.delete.iter_mut().chain(updates).collect();
+    origins_on_prefix(request.prefix.as_mut(), paths)
+}
+
+fn update(path: Path, value: TypedValue) -> Update {
+    Update {
+        path,
+        value,
+        duplicates: 0,
+    }
+}
+
+fn child(path: &Path, name: &str) -> Path {
+    let mut path = path.clone();
+    path.push(PathElement::new(name));
+    path
+}
+
+/// Check if the gNMI version of a target device is at least 0.10.0. Versions that can not
+/// be parsed are assumed to support union replaces, leaving the decision to the device.
+fn supports_union_replace(version: &str) -> bool {
+    let mut parts = version.trim().split('.').map(str::parse::<u64>);
+    match (parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor))) => (major, minor) >= (0, 10),
+        _ => true,
+    }
+}
+
+/// Remove all lists from the JSON document of `update`, returning the update without them and
+/// one update per list. Returns `None` instead of the update if it has no lists.
+fn split_lists(update: &Update) -> Result<(Option<Update>, Vec<Update>), GinmiError> {
+    let (json, ietf) = match &update.value {
+        TypedValue::Json(json) => (json, false),
+        TypedValue::JsonIetf(json) => (json, true),
+        _ => return Ok((None, Vec::new())),
+    };
+    let mut document: serde_json::Value = match serde_json::from_slice(json) {
+        Ok(document) => document,
+        Err(_) => return Ok((None, Vec::new())),
+    };
+
+    let mut extracted = Vec::new();
+    if let serde_json::Value::Object(members) = &mut document {
+        extract_lists(members, &update.path, &mut extracted);
+    }
+    if extracted.is_empty() {
+        return Ok((None, Vec::new()));
+    }
+
+    let encode = |value: &serde_json::Value| {
+        serde_json::to_vec(value)
+            .map(|json| {
+                if ietf {
+                    TypedValue::JsonIetf(json)
+                } else {
+                    TypedValue::Json(json)
+                }
+            })
+            .map_err(|e| GinmiError::DecodeError(e.to_string()))
+    };
+    let lists = extracted
+        .into_iter()
+        .map(|(path, list)| Ok(self::update(path, encode(&list)?)))
+        .collect::<Result<_, GinmiError>>()?;
+    Ok((
+        Some(self::update(update.path.clone(), encode(&document)?)),
+        lists,
+    ))
+}
+
+/// Move all arrays of objects out of `members` and the objects nested in it. Lists nested
+/// in the entries of a list are kept, as entries can not be addressed without their keys.
+fn extract_lists(
+    members: &mut serde_json::Map<String, serde_json::Value>,
+    path: &Path,
+    lists: &mut Vec<(Path, serde_json::Value)>,
+) {
+    use serde_json::Value;
+
+    let names: Vec<String> = members.keys().cloned().collect();
+    for name in names {
+        match members.get_mut(&name) {
+            Some(Value::Array(entries))
+                if !entries.is_empty() && entries.iter().all(Value::is_object) =>
+            {
+                if let Some(list) = members.remove(&name) {
+                    lists.push((child(path, &name), list));
+                }
+            }
+            Some(Value::Object(nested)) => extract_lists(nested, &child(path, &name), lists),
+            _ => {}
+        }
+    }
+}
+
+fn is_empty_json(value: &TypedValue) -> bool {
+    match value {
+        TypedValue::Json(json) | TypedValue::JsonIetf(json) => json.as_slice() == b"{}",
+        _ => false,
+    }
+}
+
+fn json_ietf_value(value: &serde_json::Value) -> Result<TypedValue, GinmiError> {
+    serde_json::to_vec(value)
+        .map(TypedValue::JsonIetf)
+        .map_err(|e| GinmiError::DecodeError(e.to_string()))
+}
+
+/// The operations of a set request, independent of the [`Client`] that sends them.
+///
+/// Created with [`SetRequestBuilder::to_request`] and replayed with
+/// [`SetRequestBuilder::extend`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SetRequest {
+    /// Prefix of all paths of the request.
+    pub prefix: Option<Path>,
+    /// Data elements that are deleted, including their children.
+    pub deletes: Vec<Path>,
+    /// Data elements that are replaced.
+    pub replaces: Vec<Update>,
+    /// Data elements that are updated.
+    pub updates: Vec<Update>,
+    /// Data elements that are replaced with the union of all union replaces.
+    pub union_replaces: Vec<Update>,
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl SetRequest {
+    /// Serialize the request, e.g. to store it for review or auditing and replay it later.
+    ///
+    /// The schema is independent of the generated protobuf types. Paths are serialized as
+    /// their string representation and values with their type, JSON encoded values are
+    /// embedded as JSON and bytes are encoded as base64 strings:
+    /// ```json
+    /// {
+    ///   "prefix": "/interfaces/interface[name=eth0]",
+    ///   "delete": ["/config/description"],
+    ///   "replace": [],
+    ///   "update": [{ "path": "/config/mtu", "type": "uint", "value": 9000 }],
+    ///   "union_replace": []
+    /// }
+    /// ```
+    /// The result can be written as JSON with [`serde_json`], or in any other format
+    /// supported by serde, e.g. YAML.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::DecodeError`] if a JSON value is not valid JSON.
+    pub fn to_json(&self) -> Result<serde_json::Value, GinmiError> {
+        let updates = |updates: &[Update]| {
+            updates
+                .iter()
+                .map(|update| {
+                    let (kind, value) = typed_json(&update.value)?;
+                    Ok(serde_json::json!({
+                        "path": update.path.to_string(),
+                        "type": kind,
+                        "value": value,
+                    }))
+                })
+                .collect::<Result<Vec<_>, GinmiError>>()
+        };
+
+        Ok(serde_json::json!({
+            "prefix": self.prefix.as_ref().map(ToString::to_string),
+            "delete": self.deletes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            "replace": updates(&self.replaces)?,
+            "update": updates(&self.updates)?,
+            "union_replace": updates(&self.union_replaces)?,
+        }))
+    }
+
+    /// Deserialize a request that was serialized with [`SetRequest::to_json`]. Members that
+    /// are missing are treated as empty.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::DecodeError`] if `json` does not match the schema of
+    ///   [`SetRequest::to_json`].
+    /// - Returns [`GinmiError::PathParseError`] if a path can not be parsed.
+    pub fn from_json(json: &serde_json::Value) -> Result<Self, GinmiError> {
+        let members = json
+            .as_object()
+            .ok_or_else(|| invalid_json("set request is not an object"))?;
+        let list = |name: &str| match members.get(name) {
+            Some(serde_json::Value::Array(items)) => Ok(items.as_slice()),
+            None | Some(serde_json::Value::Null) => Ok(&[][..]),
+            Some(_) => Err(invalid_json(&format!("{} is not an array", name))),
+        };
+        let path = |json: &serde_json::Value| -> Result<Path, GinmiError> {
+            json.as_str()
+                .ok_or_else(|| invalid_json("path is not a string"))?
+                .parse()
+        };
+        let updates = |name: &str| -> Result<Vec<Update>, GinmiError> {
+            list(name)?
+                .iter()
+                .map(|json| {
+                    let target = json
+                        .get("path")
+                        .ok_or_else(|| invalid_json("update has no path"))?;
+                    Ok(self::update(path(target)?, value_from_json(json)?))
+                })
+                .collect()
+        };
+
+        Ok(SetRequest {
+            prefix: match members.get("prefix") {
+                None | Some(serde_json::Value::Null) => None,
+                Some(prefix) => Some(path(prefix)?),
+            },
+            deletes: list("delete")?.iter().map(path).collect::<Result<_, _>>()?,
+            replaces: updates("replace")?,
+            updates: updates("update")?,
+            union_replaces: updates("union_replace")?,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+fn invalid_json(message: &str) -> GinmiError {
+    GinmiError::DecodeError(format!("invalid set request: {}", message))
+}
+
+/// Serialize `value` as an object with its `type` and its decoded `value`.
+#[cfg(feature = "serde")]
+fn value_to_json(value: &TypedValue) -> Result<serde_json::Value, GinmiError> {
+    let (kind, value) = typed_json(value)?;
+    Ok(serde_json::json!({ "type": kind, "value": value }))
+}
+
+/// Returns the name of the type of `value` and its decoded form.
+#[cfg(feature = "serde")]
+fn typed_json(value: &TypedValue) -> Result<(&'static str, serde_json::Value), GinmiError> {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde_json::json;
+
+    let json = |json: &[u8]| {
+        serde_json::from_slice::<serde_json::Value>(json)
+            .map_err(|e| GinmiError::DecodeError(e.to_string()))
+    };
+    let (kind, value) = match value {
+        TypedValue::String(v) => ("string", json!(v)),
+        TypedValue::Int(v) => ("int", json!(v)),
+        TypedValue::Uint(v) => ("uint", json!(v)),
+        TypedValue::Bool(v) => ("bool", json!(v)),
+        TypedValue::Bytes(v) => ("bytes", json!(STANDARD.encode(v))),
+        TypedValue::Float(v) => ("float", json!(v)),
+        TypedValue::Double(v) => ("double", json!(v)),
+        TypedValue::Decimal { digits, precision } => (
+            "decimal",
+            json!({ "digits": digits, "precision": precision }),
+        ),
+        TypedValue::LeafList(v) => (
+            "leaf_list",
+            serde_json::Value::Array(v.iter().map(value_to_json).collect::<Result<_, _>>()?),
+        ),
+        TypedValue::Any { type_url, value } => (
+            "any",
+            json!({ "type_url": type_url, "value": STANDARD.encode(value) }),
+        ),
+        TypedValue::Json(v) => ("json", json(v)?),
+        TypedValue::JsonIetf(v) => ("json_ietf", json(v)?),
+        TypedValue::Ascii(v) => ("ascii", json!(v)),
+        TypedValue::ProtoBytes(v) => ("proto_bytes", json!(STANDARD.encode(v))),
+    };
+
+    Ok((kind, value))
+}
+
+/// Deserialize a value that was serialized with [`value_to_json`].
+#[cfg(feature = "serde")]
+fn value_from_json(json: &serde_json::Value) -> Result<TypedValue, GinmiError> {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    let kind = json
+        .get("type")
+        .and_then(serde_json::Value::as_str)
+        .ok_or_else(|| invalid_json("value has no type"))?;
+    let value = json
+        .get("value")
+        .ok_or_else(|| invalid_json("value is missing"))?;
+    let mismatch = || invalid_json(&format!("value does not match its type {}", kind));
+    let string = || value.as_str().map(str::to_string).ok_or_else(mismatch);
+    let base64 = |value: &serde_json::Value| {
+        let encoded = value.as_str().ok_or_else(mismatch)?;
+        STANDARD
+            .decode(encoded)
+            .map_err(|e| GinmiError::DecodeError(e.to_string()))
+    };
+    let json = || serde_json::to_vec(value).map_err(|e| GinmiError::DecodeError(e.to_string()));
+
+    Ok(match kind {
+        "string" => TypedValue::String(string()?),
+        "int" => TypedValue::Int(value.as_i64().ok_or_else(mismatch)?),
+        "uint" => TypedValue::Uint(value.as_u64().ok_or_else(mismatch)?),
+        "bool" => TypedValue::Bool(value.as_bool().ok_or_else(mismatch)?),
+        "bytes" => TypedValue::Bytes(base64(value)?),
+        "float" => TypedValue::Float(value.as_f64().ok_or_else(mismatch)? as f32),
+        "double" => TypedValue::Double(value.as_f64().ok_or_else(mismatch)?),
+        "decimal" => TypedValue::Decimal {
+            digits: value
+                .get("digits")
+                .and_then(serde_json::Value::as_i64)
+                .ok_or_else(mismatch)?,
+            precision: value
+                .get("precision")
+                .and_then(serde_json::Value::as_u64)
+                .and_then(|precision| u32::try_from(precision).ok())
+                .ok_or_else(mismatch)?,
+        },
+        "leaf_list" => TypedValue::LeafList(
+            value
+                .as_array()
+                .ok_or_else(mismatch)?
+                .iter()
+                .map(value_from_json)
+                .collect::<Result<_, _>>()?,
+        ),
+        "any" => TypedValue::Any {
+            type_url: value
+                .get("type_url")
+                .and_then(serde_json::Value::as_str)
+                .ok_or_else(mismatch)?
+                .to_string(),
+            value: base64(value.get("value").ok_or_else(mismatch)?)?,
+        },
+        "json" => TypedValue::Json(json()?),
+        "json_ietf" => TypedValue::JsonIetf(json()?),
+        "ascii" => TypedValue::Ascii(string()?),
+        "proto_bytes" => TypedValue::ProtoBytes(base64(value)?),
+        other => return Err(invalid_json(&format!("unknown value type {}", other))),
+    })
+}
+
+/// The response of a target device to a set request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetResponse {
+    /// Time the changes were applied in nanoseconds since the Unix epoch.
+    pub timestamp: i64,
+    /// Prefix of all paths contained in the response.
+    pub prefix: Option<Path>,
+    /// The results of the individual operations of the request.
+    pub results: Vec<UpdateResult>,
+    /// Extensions the target device attached to the response.
+    pub extensions: Vec<Extension>,
+}
+
+/// The result of a single operation of a set request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateResult {
+    /// Path of the modified data element.
+    pub path: Path,
+    /// The operation that was applied to the data element.
+    pub operation: Operation,
+}
+
+impl TryFrom<GnmiSetResponse> for SetResponse {
+    type Error = GinmiError;
+
+    #[allow(deprecated)]
+    fn try_from(response: GnmiSetResponse) -> Result<Self, Self::Error> {
+        error::check_embedded(response.message)?;
+        for result in &response.response {
+            error::check_embedded(result.message.clone())?;
+        }
+
+        Ok(SetResponse {
+            timestamp: response.timestamp,
+            prefix: response.prefix.map(Path::from),
+            results: response
+                .response
+                .into_iter()
+                .map(UpdateResult::from)
+                .collect(),
+            extensions: extension::decode(response.extension)?,
+        })
+    }
+}
+
+impl From<GnmiUpdateResult> for UpdateResult {
+    fn from(result: GnmiUpdateResult) -> Self {
+        UpdateResult {
+            path: result.path.map(Path::from).unwrap_or_default(),
+            operation: Operation::try_from(result.op).unwrap_or(Operation::Invalid),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::lazy_client;
+    use crate::gen::gnmi::typed_value::Value as GnmiValueKind;
+    use crate::gen::gnmi::Update as GnmiUpdate;
+    use serde_json::json;
+
+    #[test]
+    fn json_encoded_as_json_ietf() {
+        let config = serde_json::json!({
+            "openconfig-interfaces:config": {
+                "name": "eth0",
+                "enabled": true,
+                "mtu": 9000
+            }
+        });
+
+        let value = json_ietf_value(&config).unwrap();
+        let update = GnmiUpdate::from(update(
+            "/interfaces/interface[name=eth0]".parse().unwrap(),
+            value,
+        ));
+
+        match update.val.and_then(|v| v.value) {
+            Some(GnmiValueKind::JsonIetfVal(json)) => {
+                assert_eq!(
+                    serde_json::from_slice::<serde_json::Value>(&json).unwrap(),
+                    config
+                );
+            }
+            other => panic!("expected json_ietf_val, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn replay_serialized_request() {
+        let mut client = lazy_client();
+
+        let builder = client
+            .set()
+            .prefix("/interfaces/interface[name=eth0]".parse().unwrap())
+            .delete("config/description".parse().unwrap())
+            .update("config/mtu".parse().unwrap(), TypedValue::Uint(9000))
+            .replace(
+                "config/counters".parse().unwrap(),
+                TypedValue::LeafList(vec![
+                    TypedValue::Bytes(b"ginmi".to_vec()),
+                    TypedValue::Decimal {
+                        digits: 1250,
+                        precision: 2,
+                    },
+                ]),
+            )
+            .union_replace(
+                "config".parse().unwrap(),
+                TypedValue::JsonIetf(br#"{"enabled":true}"#.to_vec()),
+            );
+        let json = builder.to_json().unwrap();
+        let request = builder.to_request();
+
+        assert_eq!(json["prefix"], "/interfaces/interface[name=eth0]");
+        assert_eq!(json["delete"], json!(["/config/description"]));
+        assert_eq!(
+            json["update"],
+            json!([{ "path": "/config/mtu", "type": "uint", "value": 9000 }])
+        );
+        assert_eq!(
+            json["union_replace"][0]["value"],
+            json!({ "enabled": true })
+        );
+
+        let text = serde_json::to_string(&json).unwrap();
+        let loaded = SetRequest::from_json(&serde_json::from_str(&text).unwrap()).unwrap();
+        assert_eq!(loaded, request);
+
+        let mut other = lazy_client();
+        let replayed = other.set().extend(loaded);
+        assert_eq!(replayed.request(), client.set().extend(request).request());
+
+        assert!(matches!(
+            SetRequest::from_json(
+                &json!({ "update": [{ "path": "/a", "type": "uint", "value": -1 }] })
+            ),
+            Err(GinmiError::DecodeError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn origins_of_all_operations_move_to_prefix() {
+        let mut client = lazy_client();
+
+        let mut req = client
+            .set()
+            .prefix("/interfaces".parse().unwrap())
+            .delete("openconfig:/interface[name=eth1]".parse().unwrap())
+            .update(
+                "openconfig:/interface[name=eth0]/config/mtu"
+                    .parse()
+                    .unwrap(),
+                TypedValue::Uint(9000),
+            )
+            .request();
+        set_origins_on_prefix(&mut req).unwrap();
+
+        assert_eq!(req.prefix.unwrap().origin, "openconfig");
+        assert!(req.delete[0].origin.is_empty());
+        assert!(req.update[0].path.as_ref().unwrap().origin.is_empty());
+    }
+
+    #[tokio::test]
+    async fn chunk_json_at_lists() {
+        let mut client = lazy_client();
+
+        let interfaces = json!({
+            "interface": [
+                { "name": "eth0", "config": { "mtu": 9000 } },
+                { "name": "eth1", "config": { "mtu": 1500 } }
+            ]
+        });
+        let system = json!({
+            "config": { "hostname": "router1", "domain-names": ["example.com"] },
+            "aaa": { "server-group": [{ "name": "radius" }] }
+        });
+        let requests = client
+            .set()
+            .replace_json("/interfaces".parse().unwrap(), interfaces.clone())
+            .unwrap()
+            .update_json("/system".parse().unwrap(), system.clone())
+            .unwrap()
+            .delete("/acl".parse().unwrap())
+            .chunk_json_at_lists(true)
+            .chunked_requests()
+            .unwrap();
+        let value = |update: &crate::gen::gnmi::Update| {
+            let path = Path::from(update.path.clone().unwrap()).to_string();
+            match update.val.clone().and_then(|v| v.value) {
+                Some(GnmiValueKind::JsonIetfVal(json)) => (
+                    path,
+                    serde_json::from_slice::<serde_json::Value>(&json).unwrap(),
+                ),
+                other => panic!("expected json_ietf_val, got {:?}", other),
+            }
+        };
+
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].delete.len(), 1);
+        assert_eq!(
+            value(&requests[0].replace[0]),
+            ("/interfaces".to_string(), json!({}))
+        );
+        assert_eq!(
+            value(&requests[0].update[0]),
+            (
+                "/system".to_string(),
+                json!({
+                    "config": { "hostname": "router1", "domain-names": ["example.com"] },
+                    "aaa": {}
+                })
+            )
+        );
+        assert_eq!(
+            value(&requests[1].update[0]),
+            (
+                "/interfaces/interface".to_string(),
+                interfaces["interface"].clone()
+            )
+        );
+        assert_eq!(
+            value(&requests[2].update[0]),
+            (
+                "/system/aaa/server-group".to_string(),
+                system["aaa"]["server-group"].clone()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn diff_json_documents() {
+        let mut client = lazy_client();
+
+        let current = json!({
+            "name": "eth0",
+            "mtu": 1500,
+            "description": "uplink",
+            "addresses": ["10.0.0.1"],
+            "counters": { "enabled": true }
+        });
+        let desired = json!({
+            "name": "eth0",
+            "mtu": 9000,
+            "addresses": ["10.0.0.1", "10.0.0.2"],
+            "counters": { "enabled": true },
+            "vlan": { "id": 10 }
+        });
+
+        let base: Path = "/interfaces/interface[name=eth0]/config".parse().unwrap();
+        let set = client.set().from_diff(&current, &desired, base).unwrap();
+
+        let paths = |updates: &[Update]| {
+            updates
+                .iter()
+                .map(|u| (u.path.to_string(), u.value.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            set.deletes,
+            vec!["/interfaces/interface[name=eth0]/config/description"
+                .parse::<Path>()
+                .unwrap()]
+        );
+        assert_eq!(
+            paths(&set.updates),
+            vec![
+                (
+                    "/interfaces/interface[name=eth0]/config/mtu".to_string(),
+                    TypedValue::JsonIetf(b"9000".to_vec())
+                ),
+                (
+                    "/interfaces/interface[name=eth0]/config/vlan".to_string(),
+                    TypedValue::JsonIetf(br#"{"id":10}"#.to_vec())
+                ),
+            ]
+        );
+        assert_eq!(
+            paths(&set.replaces),
+            vec![(
+                "/interfaces/interface[name=eth0]/config/addresses".to_string(),
+                TypedValue::JsonIetf(br#"["10.0.0.1","10.0.0.2"]"#.to_vec())
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn diff_equal_documents() {
+        let mut client = lazy_client();
+
+        let config = json!({ "mtu": 9000 });
+        let set = client
+            .set()
+            .from_diff(&config, &config, Path::new())
+            .unwrap();
+        assert!(set.deletes.is_empty() && set.updates.is_empty() && set.replaces.is_empty());
+    }
+
+    #[tokio::test]
+    async fn operations_in_spec_order() {
+        let mut client = lazy_client();
+
+        let request = client
+            .set()
+            .union_replace("/system".parse().unwrap(), TypedValue::Bool(true))
+            .update("/interfaces".parse().unwrap(), TypedValue::Int(1))
+            .replace("/network-instances".parse().unwrap(), TypedValue::Int(2))
+            .delete("/acl".parse().unwrap())
+            .update("/qos".parse().unwrap(), TypedValue::Int(3))
+            .request();
+
+        // field numbers of delete, replace, update and union_replace in the order of the
+        // encoded request
+        let encoded = request.encode_to_vec();
+        let mut fields = Vec::new();
+        let mut rest = encoded.as_slice();
+        while let [key, length, ..] = rest {
+            fields.push(key >> 3);
+            rest = &rest[2 + *length as usize..];
+        }
+        assert_eq!(fields, [2, 3, 4, 4, 6]);
+        assert_eq!(
+            request.update[1].path,
+            Some("/qos".parse::<Path>().unwrap().into())
+        );
+    }
+
+    #[test]
+    fn union_replace_versions() {
+        assert!(supports_union_replace("0.10.0"));
+        assert!(supports_union_replace("1.0"));
+        assert!(!supports_union_replace("0.7.0"));
+        assert!(supports_union_replace("unknown"));
+    }
+}
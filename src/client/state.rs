@@ -0,0 +1,142 @@
+//! Live view of the values of subscribed data elements.
+//!
+//! A [`SubscriptionState`] is created with
+//! [`Client::subscribe_state`](super::Client::subscribe_state). It applies the updates and
+//! deletes of a subscription as they arrive, so the current values can be read at any time
+//! without consuming the [`SubscribeStream`] yourself.
+use super::subscribe::{SubscribeEvent, SubscribeStream};
+use crate::notification::Notification;
+use crate::path::Path;
+use crate::value::TypedValue;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio::sync::watch;
+use tokio_stream::StreamExt;
+
+/// The current values of the data elements of a subscription, keyed by their absolute path.
+///
+/// Clones share the same state. The subscription is cancelled once all clones have been
+/// dropped, or ends when the target device closes it or reports an error.
+#[derive(Debug, Clone)]
+pub struct SubscriptionState {
+    values: Arc<RwLock<HashMap<Path, TypedValue>>>,
+    changes: watch::Receiver<()>,
+}
+
+impl SubscriptionState {
+    pub(crate) fn new(mut stream: SubscribeStream) -> Self {
+        let values = Arc::new(RwLock::new(HashMap::new()));
+        let (sender, changes) = watch::channel(());
+
+        let state = Arc::clone(&values);
+        tokio::spawn(async move {
+            loop {
+                let response = tokio::select! {
+                    // all handles have been dropped, so cancel the subscription
+                    _ = sender.closed() => break,
+                    response = stream.next() => response,
+                };
+
+                match response {
+                    Some(Ok(response)) => {
+                        if let SubscribeEvent::Update(notification)
+                        | SubscribeEvent::OutOfOrder { notification, .. } = response.event
+                        {
+                            apply(&mut state.write().unwrap(), notification);
+                            sender.send_replace(());
+                        }
+                    }
+                    Some(Err(_)) | None => break,
+                }
+            }
+        });
+
+        Self { values, changes }
+    }
+
+    /// Returns the current value of the data element at `path`.
+    pub fn get(&self, path: &Path) -> Option<TypedValue> {
+        self.values.read().unwrap().get(path).cloned()
+    }
+
+    /// Returns a copy of the current values of all data elements.
+    pub fn snapshot(&self) -> HashMap<Path, TypedValue> {
+        self.values.read().unwrap().clone()
+    }
+
+    /// Wait until the values changed since the last call.
+    ///
+    /// Returns `false` if the subscription has ended, so no further changes will follow.
+    pub async fn changed(&mut self) -> bool {
+        self.changes.changed().await.is_ok()
+    }
+}
+
+/// Apply the deletes and then the updates of `notification`. Deleting a path also deletes
+/// all of its children.
+fn apply(values: &mut HashMap<Path, TypedValue>, notification: Notification) {
+    let absolute = |path: Path| match &notification.prefix {
+        Some(prefix) => path.with_prefix(prefix),
+        None => path,
+    };
+
+    for delete in notification.deletes.iter().cloned().map(absolute) {
+        values.retain(|path, _| path.strip_prefix(&delete).is_none());
+    }
+
+    for update in notification.updates.iter().cloned() {
+        values.insert(absolute(update.path), update.value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notification::Update;
+
+    fn notification(updates: &[(&str, i64)], deletes: &[&str]) -> Notification {
+        Notification {
+            timestamp: 0,
+            prefix: Some("/interfaces".parse().unwrap()),
+            updates: updates
+                .iter()
+                .map(|(path, value)| Update {
+                    path: path.parse().unwrap(),
+                    value: TypedValue::Int(*value),
+                    duplicates: 0,
+                })
+                .collect(),
+            deletes: deletes.iter().map(|path| path.parse().unwrap()).collect(),
+            atomic: false,
+        }
+    }
+
+    #[test]
+    fn apply_updates_and_deletes() {
+        let mut values = HashMap::new();
+        apply(
+            &mut values,
+            notification(
+                &[
+                    ("interface[name=eth0]/mtu", 1500),
+                    ("interface[name=eth0]/counters/in-octets", 10),
+                    ("interface[name=eth1]/mtu", 9000),
+                ],
+                &[],
+            ),
+        );
+        assert_eq!(values.len(), 3);
+
+        apply(
+            &mut values,
+            notification(
+                &[("interface[name=eth1]/mtu", 1500)],
+                &["interface[name=eth0]"],
+            ),
+        );
+
+        let eth1: Path = "/interfaces/interface[name=eth1]/mtu".parse().unwrap();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values.get(&eth1), Some(&TypedValue::Int(1500)));
+    }
+}
@@ -0,0 +1,43 @@
+//! Statistics about the RPCs of a [`Client`](super::Client), see the
+//! [module documentation](super#metrics) for the recorded metrics.
+use std::future::Future;
+use tonic::{Response, Status};
+
+/// Send a request by awaiting `call` and record its outcome.
+pub(crate) async fn observe<T>(
+    method: &'static str,
+    call: impl Future<Output = Result<Response<T>, Status>>,
+) -> Result<Response<T>, Status> {
+    #[cfg(feature = "metrics")]
+    let start = std::time::Instant::now();
+
+    let result = call.await;
+
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!("gnmi_client_requests_total", "method" => method).increment(1);
+        metrics::histogram!("gnmi_client_request_duration_seconds", "method" => method)
+            .record(start.elapsed().as_secs_f64());
+        if let Err(status) = &result {
+            metrics::counter!(
+                "gnmi_client_errors_total",
+                "method" => method,
+                "code" => format!("{:?}", status.code())
+            )
+            .increment(1);
+        }
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = method;
+
+    result
+}
+
+/// Record that a message of `bytes` encoded bytes was received.
+pub(crate) fn received(method: &'static str, bytes: usize) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("gnmi_client_received_bytes_total", "method" => method)
+        .increment(bytes as u64);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (method, bytes);
+}
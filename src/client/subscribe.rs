@@ -0,0 +1,1268 @@
+//! Subscriptions to data elements of a target device.
+//!
+//! A subscription is created by building a [`SubscriptionList`] and passing it to
+//! [`Client::subscribe`](super::Client::subscribe), as described in the
+//! [gNMI Specification Section 3.5](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-specification.md#35-subscribing-to-telemetry-updates).
+//!
+//! # Examples
+//! Subscribing to changes of the operational state of an interface:
+//! ```rust
+//! # use ginmi::client::{Client, Subscription, SubscriptionList, SubscriptionMode};
+//! # use tokio_stream::StreamExt;
+//! # tokio_test::block_on(async {
+//! # const CERT: &str = "CA Certificate";
+//! let mut client = Client::builder("https://clab-srl01-srl:57400")
+//!     .tls(CERT, "clab-srl01-srl")
+//!     .credentials("admin", "admin")
+//!     .build()
+//!     .await
+//!     .unwrap();
+//!
+//! let subscriptions = SubscriptionList::builder()
+//!     .subscription(
+//!         Subscription::new("/interface[name=ethernet-1/1]/oper-state".parse().unwrap())
+//!             .mode(SubscriptionMode::OnChange)
+//!     )
+//!     .build()
+//!     .unwrap();
+//!
+//! let mut stream = client.subscribe(subscriptions).await.unwrap();
+//! while let Some(response) = stream.next().await {
+//!     println!("{:?}", response.unwrap());
+//! }
+//! # });
+//! ```
+use super::stats;
+use super::transport::BoxStream;
+use super::Capabilities;
+use crate::error::GinmiError;
+use crate::extension::{self, Extension};
+use crate::gen::gnmi::subscribe_request::Request;
+use crate::gen::gnmi::subscribe_response::Response;
+use crate::gen::gnmi::{
+    Encoding, Poll as GnmiPoll, SubscribeRequest, SubscribeResponse as GnmiSubscribeResponse,
+    Subscription as GnmiSubscription, SubscriptionList as GnmiSubscriptionList,
+};
+use crate::notification::Notification;
+use crate::path::{origins_on_prefix, Path, PathElement};
+use crate::value::TypedValue;
+use prost::Message;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::time::{Interval, MissedTickBehavior};
+use tokio_stream::{Stream, StreamExt};
+use tonic::metadata::MetadataMap;
+use tonic::{Code, Status};
+
+pub use crate::gen::gnmi::subscription_list::Mode as SubscriptionListMode;
+pub use crate::gen::gnmi::SubscriptionMode;
+
+/// A subscription to a single path of a target device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subscription {
+    path: Path,
+    mode: SubscriptionMode,
+    sample_interval: u64,
+    suppress_redundant: bool,
+    heartbeat_interval: u64,
+}
+
+impl Subscription {
+    /// Create a new [`Subscription`] to the given path, letting the target device select
+    /// the [`SubscriptionMode`].
+    pub fn new(path: Path) -> Self {
+        Self {
+            path,
+            mode: SubscriptionMode::TargetDefined,
+            sample_interval: 0,
+            suppress_redundant: false,
+            heartbeat_interval: 0,
+        }
+    }
+
+    #[cfg(feature = "raw")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "raw")))]
+    /// Create a new [`Subscription`] to a generated protobuf path, see [`crate::raw`].
+    pub fn new_raw(path: crate::raw::Path) -> Self {
+        Self::new(path.into())
+    }
+
+    /// Set the [`SubscriptionMode`] of the subscription.
+    pub fn mode(mut self, mode: SubscriptionMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the interval between samples of a [`SubscriptionMode::Sample`] subscription. An
+    /// interval of zero lets the target device choose the lowest interval it supports.
+    ///
+    /// The interval is sent in nanoseconds, intervals that exceed the range of gNMI are
+    /// clamped to the longest interval it can express.
+    pub fn sample_interval(mut self, interval: Duration) -> Self {
+        self.sample_interval = nanos(interval);
+        self
+    }
+
+    /// Only send samples of a [`SubscriptionMode::Sample`] subscription if the value changed
+    /// since the last sample.
+    pub fn suppress_redundant(mut self, suppress_redundant: bool) -> Self {
+        self.suppress_redundant = suppress_redundant;
+        self
+    }
+
+    /// Set the maximum interval after which the value is sent even if it did not change.
+    /// It is clamped like the [sample interval](Self::sample_interval).
+    ///
+    /// The heartbeat interval applies to [`SubscriptionMode::OnChange`] subscriptions and
+    /// to [`SubscriptionMode::Sample`] subscriptions that [suppress redundant](Self::suppress_redundant)
+    /// samples. Setting it for any other sample subscription is rejected when
+    /// building the [`SubscriptionList`].
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = nanos(interval);
+        self
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.mode == SubscriptionMode::Sample
+            && self.heartbeat_interval != 0
+            && !self.suppress_redundant
+        {
+            return Err(format!(
+                "heartbeat_interval of sample subscription to '{}' requires suppress_redundant",
+                self.path
+            ));
+        }
+
+        if self.mode == SubscriptionMode::OnChange && self.suppress_redundant {
+            return Err(format!(
+                "suppress_redundant only applies to sample subscriptions, but '{}' is on change",
+                self.path
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl From<Subscription> for GnmiSubscription {
+    fn from(subscription: Subscription) -> Self {
+        GnmiSubscription {
+            path: Some(subscription.path.into()),
+            mode: subscription.mode as i32,
+            sample_interval: subscription.sample_interval,
+            suppress_redundant: subscription.suppress_redundant,
+            heartbeat_interval: subscription.heartbeat_interval,
+        }
+    }
+}
+
+/// A validated set of [`Subscription`]s that can be passed to
+/// [`Client::subscribe`](super::Client::subscribe).
+///
+/// Created with a [`SubscriptionBuilder`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionList {
+    pub(crate) list: GnmiSubscriptionList,
+    pub(crate) expand_wildcards: bool,
+    pub(crate) verify_encoding: bool,
+    pub(crate) explicit_encoding: bool,
+    pub(crate) detect_out_of_order: bool,
+}
+
+impl SubscriptionList {
+    /// Create a [`SubscriptionBuilder`] that can create [`SubscriptionList`]s.
+    pub fn builder() -> SubscriptionBuilder {
+        SubscriptionBuilder::new()
+    }
+
+    /// Create a [`SubscriptionList`] in [`SubscriptionListMode::Stream`] from `(path, mode,
+    /// interval)` specifications, e.g. for many similar sample subscriptions. Use the
+    /// [`SubscriptionBuilder`] for all other settings.
+    ///
+    /// The interval is the [sample interval](Subscription::sample_interval) of
+    /// [`SubscriptionMode::Sample`] and [`SubscriptionMode::TargetDefined`] subscriptions and
+    /// the [heartbeat interval](Subscription::heartbeat_interval) of
+    /// [`SubscriptionMode::OnChange`] subscriptions. Zero leaves it to the target device.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::client::{SubscriptionList, SubscriptionMode};
+    /// # use std::time::Duration;
+    /// let subscriptions = SubscriptionList::from_specs([
+    ///     ("/interfaces/interface[name=*]/state/counters", SubscriptionMode::Sample, Duration::from_secs(10)),
+    ///     ("/system/state/hostname", SubscriptionMode::OnChange, Duration::ZERO),
+    /// ])
+    /// .unwrap();
+    /// ```
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::PathParseError`] if a path is not valid.
+    /// - Returns [`GinmiError::InvalidSubscriptionError`] if an interval exceeds the range of
+    ///   gNMI.
+    pub fn from_specs<'s>(
+        specs: impl IntoIterator<Item = (&'s str, SubscriptionMode, Duration)>,
+    ) -> Result<Self, GinmiError> {
+        let mut builder = SubscriptionList::builder();
+
+        for (path, mode, interval) in specs {
+            let path = Path::try_parse(path)?;
+            if u64::try_from(interval.as_nanos()).is_err() {
+                return Err(GinmiError::InvalidSubscriptionError(format!(
+                    "interval of subscription to '{}' exceeds the range of gNMI",
+                    path
+                )));
+            }
+
+            let subscription = Subscription::new(path).mode(mode);
+            let subscription = if mode == SubscriptionMode::OnChange {
+                subscription.heartbeat_interval(interval)
+            } else {
+                subscription.sample_interval(interval)
+            };
+            builder = builder.subscription(subscription);
+        }
+
+        builder.build()
+    }
+
+    /// Target of the prefix of the list, which identifies the device in deployments where
+    /// one gNMI server serves many devices.
+    pub fn target(&self) -> Option<&str> {
+        self.list
+            .prefix
+            .as_ref()
+            .map(|prefix| prefix.target.as_str())
+            .filter(|target| !target.is_empty())
+    }
+
+    /// The [`Encoding`] the target device is asked to use for values.
+    ///
+    /// Unless it was set with [`SubscriptionBuilder::encoding`], this is
+    /// [`DEFAULT_ENCODING`](super::DEFAULT_ENCODING) and replaced with the
+    /// [default encoding](super::ClientBuilder::default_encoding) of the client when
+    /// subscribing.
+    pub fn encoding(&self) -> Encoding {
+        Encoding::try_from(self.list.encoding).unwrap_or_default()
+    }
+
+    /// Check that the target device advertises the encoding of the list.
+    pub(crate) fn check_encoding(&self, capabilities: &Capabilities) -> Result<(), GinmiError> {
+        let encoding = self.encoding();
+        if capabilities.supports_encoding(encoding) {
+            return Ok(());
+        }
+
+        Err(GinmiError::InvalidEncodingError(format!(
+            "target device does not support the {} encoding",
+            encoding
+        )))
+    }
+
+    /// Full paths of all subscriptions, with the prefix of the list applied.
+    pub(crate) fn paths(&self) -> Vec<Path> {
+        let prefix = self.list.prefix.clone().map(Path::from);
+        self.list
+            .subscription
+            .iter()
+            .map(|subscription| {
+                let path = subscription
+                    .path
+                    .clone()
+                    .map(Path::from)
+                    .unwrap_or_default();
+                match &prefix {
+                    Some(prefix) => path.with_prefix(prefix),
+                    None => path,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Builder for [`SubscriptionList`]s.
+///
+/// Client-defined aliases are not supported, because the `aliases` field of the
+/// `SubscribeRequest` is reserved since gNMI 0.10.0. Use [`SubscriptionBuilder::prefix`] to
+/// avoid repeating a long prefix that is shared by many subscriptions.
+#[derive(Debug, Clone)]
+pub struct SubscriptionBuilder {
+    prefix: Option<Path>,
+    target: Option<String>,
+    subscriptions: Vec<Subscription>,
+    mode: SubscriptionListMode,
+    encoding: Option<Encoding>,
+    updates_only: bool,
+    expand_wildcards: bool,
+    verify_encoding: bool,
+    detect_out_of_order: bool,
+}
+
+impl SubscriptionBuilder {
+    pub fn new() -> Self {
+        Self {
+            prefix: None,
+            target: None,
+            subscriptions: Vec::new(),
+            mode: SubscriptionListMode::Stream,
+            encoding: None,
+            updates_only: false,
+            expand_wildcards: false,
+            verify_encoding: false,
+            detect_out_of_order: false,
+        }
+    }
+
+    /// Set a prefix that is prepended to the paths of all subscriptions.
+    pub fn prefix(mut self, prefix: Path) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    /// Set the target of the prefix, i.e. the device the subscription refers to if the
+    /// gNMI server serves many devices, e.g. a gateway or dial-out collector.
+    ///
+    /// The target device includes it in the prefix of all notifications, so responses can be
+    /// attributed to the device they belong to. Replaces the target of
+    /// [`SubscriptionBuilder::prefix`], if any.
+    pub fn target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// Add a [`Subscription`] to the list.
+    pub fn subscription(mut self, subscription: Subscription) -> Self {
+        self.subscriptions.push(subscription);
+        self
+    }
+
+    /// Set whether values are streamed, sent once or polled. Defaults to
+    /// [`SubscriptionListMode::Stream`].
+    pub fn mode(mut self, mode: SubscriptionListMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the [`Encoding`] the target device should use for values. Defaults to the
+    /// encoding configured with
+    /// [`ClientBuilder::default_encoding`](super::ClientBuilder::default_encoding).
+    ///
+    /// The encoding only applies to the subscription, so it can differ from the encoding of
+    /// get requests, e.g. for devices that support [`Encoding::Proto`] only for subscriptions.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Check the encoding against the capabilities of the target device before subscribing.
+    ///
+    /// [`Client::subscribe`](super::Client::subscribe) then rejects the subscription if the
+    /// target device does not advertise its encoding. The capabilities are queried only once,
+    /// see [`Client::capabilities_cached`](super::Client::capabilities_cached).
+    pub fn verify_encoding(mut self, verify_encoding: bool) -> Self {
+        self.verify_encoding = verify_encoding;
+        self
+    }
+
+    /// Only send updates to the current state, skipping the initial values.
+    pub fn updates_only(mut self, updates_only: bool) -> Self {
+        self.updates_only = updates_only;
+        self
+    }
+
+    /// Resolve wildcards in the paths of the subscriptions before subscribing, for target
+    /// devices that do not support wildcards in subscriptions.
+    ///
+    /// [`Client::subscribe`](super::Client::subscribe) then first queries the keys of the
+    /// list entries that every path with wildcards matches and subscribes to each of them
+    /// with a concrete path. This costs an extra get request per path with wildcards before
+    /// the subscription is created, and list entries that are created later are not
+    /// subscribed. Paths containing `...` are subscribed to as they are.
+    pub fn expand_wildcards(mut self, expand_wildcards: bool) -> Self {
+        self.expand_wildcards = expand_wildcards;
+        self
+    }
+
+    /// Flag notifications whose timestamp is older than that of an earlier notification of
+    /// the subscription, e.g. because the clock of the target device was reset.
+    ///
+    /// Such notifications are yielded as [`SubscribeEvent::OutOfOrder`] instead of
+    /// [`SubscribeEvent::Update`], so they can be dropped or stored separately from the
+    /// time series. The timestamps of later notifications are compared to the newest
+    /// timestamp received so far.
+    pub fn detect_out_of_order(mut self, detect_out_of_order: bool) -> Self {
+        self.detect_out_of_order = detect_out_of_order;
+        self
+    }
+
+    /// Consume the [`SubscriptionBuilder`] and return a [`SubscriptionList`].
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::InvalidSubscriptionError`] if no subscription was added.
+    /// - Returns [`GinmiError::InvalidSubscriptionError`] if the target is set, but empty.
+    /// - Returns [`GinmiError::InvalidSubscriptionError`] if a subscription combines options
+    ///   that do not apply to its [`SubscriptionMode`].
+    /// - Returns [`GinmiError::PathParseError`] if the path of a subscription has a different
+    ///   origin than the prefix, as only the prefix may have an origin.
+    pub fn build(self) -> Result<SubscriptionList, GinmiError> {
+        if self.subscriptions.is_empty() {
+            return Err(GinmiError::InvalidSubscriptionError(
+                "at least one subscription is required".to_string(),
+            ));
+        }
+
+        for subscription in &self.subscriptions {
+            subscription
+                .validate()
+                .map_err(GinmiError::InvalidSubscriptionError)?;
+        }
+
+        let prefix = match self.target {
+            Some(target) if target.trim().is_empty() => {
+                return Err(GinmiError::InvalidSubscriptionError(
+                    "target of the subscription list must not be empty".to_string(),
+                ))
+            }
+            Some(target) => Some(self.prefix.unwrap_or_default().with_target(target)),
+            None => self.prefix,
+        };
+
+        let mut list = GnmiSubscriptionList {
+            prefix: prefix.map(Into::into),
+            subscription: self.subscriptions.into_iter().map(Into::into).collect(),
+            mode: self.mode as i32,
+            encoding: self.encoding.unwrap_or(super::DEFAULT_ENCODING) as i32,
+            updates_only: self.updates_only,
+            ..Default::default()
+        };
+        let paths = list
+            .subscription
+            .iter_mut()
+            .filter_map(|subscription| subscription.path.as_mut())
+            .collect();
+        origins_on_prefix(list.prefix.as_mut(), paths)?;
+
+        Ok(SubscriptionList {
+            list,
+            expand_wildcards: self.expand_wildcards,
+            verify_encoding: self.verify_encoding,
+            explicit_encoding: self.encoding.is_some(),
+            detect_out_of_order: self.detect_out_of_order,
+        })
+    }
+}
+
+impl Default for SubscriptionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A response of the target device to a subscription.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscribeResponse {
+    /// What the target device reported.
+    pub event: SubscribeEvent,
+    /// Extensions the target device attached to the response.
+    pub extensions: Vec<Extension>,
+}
+
+/// The content of a [`SubscribeResponse`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscribeEvent {
+    /// Values of subscribed data elements have changed or were sampled.
+    Update(Notification),
+    /// All initial values of the subscribed data elements have been sent.
+    SyncResponse,
+    /// Values of subscribed data elements, with a timestamp older than `latest`, the newest
+    /// timestamp of an earlier notification. Only reported if
+    /// [`SubscriptionBuilder::detect_out_of_order`] is set, otherwise such notifications are
+    /// reported as [`SubscribeEvent::Update`].
+    OutOfOrder {
+        notification: Notification,
+        latest: i64,
+    },
+    /// The target device reported an error for one of the subscriptions, but keeps
+    /// serving the others.
+    Error(SubscriptionError),
+}
+
+/// An error the target device reported within a subscription.
+///
+/// Errors that end the whole subscription are returned as [`GinmiError::GrpcError`] instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionError {
+    /// The gRPC status code of the error.
+    pub code: Code,
+    /// Description of the error.
+    pub message: String,
+    /// The subscribed path the error refers to, if it could be determined. This is the case
+    /// if the subscription list contains a single subscription or the message mentions
+    /// exactly one of the subscribed paths.
+    pub path: Option<Path>,
+}
+
+/// Stream of [`SubscribeResponse`]s of an active subscription.
+///
+/// Returned by [`Client::subscribe`](super::Client::subscribe). Dropping the stream cancels
+/// the subscription: the underlying HTTP/2 stream is reset, so the target device stops sending
+/// and releases the subscription instead of keeping it until the connection is closed.
+///
+/// Path aliases are not supported, because they have been removed from gNMI 0.10.0, the
+/// version implemented by this crate. The `alias` field of notifications is reserved in this
+/// version, so [`Notification`]s always carry full paths.
+#[derive(Debug)]
+pub struct SubscribeStream {
+    requests: mpsc::Sender<SubscribeRequest>,
+    responses: mpsc::Receiver<Result<GnmiSubscribeResponse, Status>>,
+    metadata: MetadataMap,
+    paths: Vec<Path>,
+    /// Newest timestamp received so far, if out of order notifications are detected.
+    latest_timestamp: Option<Option<i64>>,
+}
+
+impl SubscribeStream {
+    /// Read up to `buffer` responses ahead of the consumer of the stream.
+    pub(crate) fn new(
+        requests: mpsc::Sender<SubscribeRequest>,
+        metadata: MetadataMap,
+        mut responses: BoxStream<Result<GnmiSubscribeResponse, Status>>,
+        buffer: usize,
+        paths: Vec<Path>,
+        detect_out_of_order: bool,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(buffer);
+
+        tokio::spawn(async move {
+            loop {
+                let response = tokio::select! {
+                    // the stream has been dropped, so stop reading and reset the HTTP/2 stream
+                    _ = sender.closed() => break,
+                    response = responses.next() => response,
+                };
+
+                match response {
+                    Some(response) => {
+                        if let Ok(response) = &response {
+                            stats::received("subscribe", response.encoded_len());
+                        }
+                        if sender.send(response).await.is_err() {
+                            break;
+                        }
+                    }
+                    None => break,
+                }
+            }
+        });
+
+        Self {
+            requests,
+            responses: receiver,
+            metadata,
+            paths,
+            latest_timestamp: detect_out_of_order.then_some(None),
+        }
+    }
+
+    /// Returns the metadata of the response headers the target device sent when it accepted
+    /// the subscription, including the `x-request-id` of the subscription if
+    /// [`ClientBuilder::correlation_ids`](super::ClientBuilder::correlation_ids) is enabled.
+    pub fn metadata(&self) -> &MetadataMap {
+        &self.metadata
+    }
+
+    /// Request the current values of a [`SubscriptionListMode::Poll`] subscription.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::GrpcError`] if the subscription has already been closed.
+    pub async fn poll(&self) -> Result<(), GinmiError> {
+        let request = SubscribeRequest {
+            request: Some(Request::Poll(GnmiPoll {})),
+            ..Default::default()
+        };
+
+        self.requests
+            .send(request)
+            .await
+            .map_err(|_| Status::cancelled("subscription has been closed").into())
+    }
+
+    /// Close the subscription.
+    ///
+    /// Ends the stream of requests to signal the target device that no further polls
+    /// will follow, then cancels the stream of responses. Responses that have not been
+    /// received yet are discarded.
+    pub async fn close(self) {
+        let Self {
+            requests,
+            responses,
+            ..
+        } = self;
+
+        drop(requests);
+        drop(responses);
+    }
+
+    /// Turn the stream into a stream of the individual updates and deletes of all
+    /// notifications, with absolute paths.
+    ///
+    /// Sync responses and errors of single subscriptions are skipped, errors that end the
+    /// subscription are passed on. [Out of order](SubscribeEvent::OutOfOrder) notifications
+    /// are flattened like any other.
+    pub fn flatten_updates(self) -> FlatUpdates {
+        FlatUpdates {
+            stream: self,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Turn the stream into a stream of [`FlatUpdate`]s, like
+    /// [`SubscribeStream::flatten_updates`], that yields at most one change per path and
+    /// `interval`, e.g. to cap the rate at which a user interface is refreshed.
+    ///
+    /// Changes received within an interval are held back until it ends, and only the latest
+    /// change of each path is passed on, in the order they were last changed. Errors that end
+    /// the subscription are passed on immediately. Once the subscription ends, the held back
+    /// changes are yielded right away.
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero or if it is not called within a Tokio runtime.
+    pub fn throttle(self, interval: Duration) -> ThrottledUpdates {
+        let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        ThrottledUpdates {
+            updates: self.flatten_updates(),
+            ticks,
+            window: BTreeMap::new(),
+            positions: HashMap::new(),
+            next_position: 0,
+            ready: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+impl Stream for SubscribeStream {
+    type Item = Result<SubscribeResponse, GinmiError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let response = match ready!(self.responses.poll_recv(cx)) {
+                Some(Ok(response)) => response,
+                Some(Err(status)) => return Poll::Ready(Some(Err(status.into()))),
+                None => return Poll::Ready(None),
+            };
+
+            // skip responses that do not contain anything
+            if let Some(item) = decode_response(response, &self.paths) {
+                let item = match self.latest_timestamp.as_mut() {
+                    Some(latest) => item.map(|response| SubscribeResponse {
+                        event: check_order(response.event, latest),
+                        ..response
+                    }),
+                    None => item,
+                };
+                return Poll::Ready(Some(item));
+            }
+        }
+    }
+}
+
+/// Decode a response to a subscription to `paths`, or return `None` if it is empty.
+pub(crate) fn decode_response(
+    response: GnmiSubscribeResponse,
+    paths: &[Path],
+) -> Option<Result<SubscribeResponse, GinmiError>> {
+    let event = match response.response? {
+        Response::Update(notification) => {
+            Notification::try_from(notification).map(SubscribeEvent::Update)
+        }
+        Response::SyncResponse(_) => Ok(SubscribeEvent::SyncResponse),
+        Response::Error(error) => Ok(SubscribeEvent::Error(SubscriptionError {
+            code: Code::from(error.code as i32),
+            path: failed_path(paths, &error.message),
+            message: error.message,
+        })),
+    };
+
+    Some(event.and_then(|event| {
+        Ok(SubscribeResponse {
+            event,
+            extensions: extension::decode(response.extension)?,
+        })
+    }))
+}
+
+/// Turn `event` into [`SubscribeEvent::OutOfOrder`] if it is older than `latest`, or
+/// advance `latest` otherwise.
+fn check_order(event: SubscribeEvent, latest: &mut Option<i64>) -> SubscribeEvent {
+    match event {
+        SubscribeEvent::Update(notification) => match *latest {
+            Some(newest) if notification.timestamp < newest => SubscribeEvent::OutOfOrder {
+                notification,
+                latest: newest,
+            },
+            _ => {
+                *latest = Some(notification.timestamp);
+                SubscribeEvent::Update(notification)
+            }
+        },
+        event => event,
+    }
+}
+
+/// A single change of a data element, as yielded by [`SubscribeStream::flatten_updates`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlatUpdate {
+    /// The data element at `path` has the given value.
+    Update {
+        path: Path,
+        value: TypedValue,
+        timestamp: SystemTime,
+    },
+    /// The data element at `path` and all of its children have been deleted.
+    Delete { path: Path, timestamp: SystemTime },
+}
+
+/// Stream of the [`FlatUpdate`]s of an active subscription.
+///
+/// Returned by [`SubscribeStream::flatten_updates`]. Dropping the stream cancels the
+/// subscription.
+#[derive(Debug)]
+pub struct FlatUpdates {
+    stream: SubscribeStream,
+    pending: VecDeque<FlatUpdate>,
+}
+
+impl Stream for FlatUpdates {
+    type Item = Result<FlatUpdate, GinmiError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(update) = self.pending.pop_front() {
+                return Poll::Ready(Some(Ok(update)));
+            }
+
+            match ready!(Pin::new(&mut self.stream).poll_next(cx)) {
+                Some(Ok(SubscribeResponse {
+                    event:
+                        SubscribeEvent::Update(notification)
+                        | SubscribeEvent::OutOfOrder { notification, .. },
+                    ..
+                })) => self.pending.extend(flatten(notification)),
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+/// Stream of the [`FlatUpdate`]s of an active subscription, limited to one per path and
+/// interval.
+///
+/// Returned by [`SubscribeStream::throttle`]. Dropping the stream cancels the subscription.
+#[derive(Debug)]
+pub struct ThrottledUpdates {
+    updates: FlatUpdates,
+    ticks: Interval,
+    /// Latest changes of the current interval, ordered by when they were received.
+    window: BTreeMap<u64, FlatUpdate>,
+    /// Position in `window` of the latest change of each path.
+    positions: HashMap<Path, u64>,
+    next_position: u64,
+    ready: VecDeque<FlatUpdate>,
+    done: bool,
+}
+
+impl ThrottledUpdates {
+    /// Hold back `update`, replacing a previous change of its path.
+    fn hold(&mut self, update: FlatUpdate) {
+        let path = match &update {
+            FlatUpdate::Update { path, .. } | FlatUpdate::Delete { path, .. } => path.clone(),
+        };
+
+        let position = self.next_position;
+        self.next_position += 1;
+        if let Some(previous) = self.positions.insert(path, position) {
+            self.window.remove(&previous);
+        }
+        self.window.insert(position, update);
+    }
+
+    /// Pass on all changes held back in the current interval.
+    fn release(&mut self) {
+        self.positions.clear();
+        let window = std::mem::take(&mut self.window);
+        self.ready.extend(window.into_values());
+    }
+}
+
+impl Stream for ThrottledUpdates {
+    type Item = Result<FlatUpdate, GinmiError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(update) = self.ready.pop_front() {
+                return Poll::Ready(Some(Ok(update)));
+            }
+            if self.done {
+                return Poll::Ready(None);
+            }
+
+            while let Poll::Ready(update) = Pin::new(&mut self.updates).poll_next(cx) {
+                match update {
+                    Some(Ok(update)) => self.hold(update),
+                    Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                    None => {
+                        self.done = true;
+                        break;
+                    }
+                }
+            }
+
+            if self.done {
+                self.release();
+                continue;
+            }
+
+            ready!(self.ticks.poll_tick(cx));
+            self.release();
+        }
+    }
+}
+
+/// Split a notification into its deletes, followed by its updates.
+fn flatten(notification: Notification) -> Vec<FlatUpdate> {
+    let timestamp = system_time(notification.timestamp);
+    let absolute = |path: Path| match &notification.prefix {
+        Some(prefix) => path.with_prefix(prefix),
+        None => path,
+    };
+
+    let deletes = notification.deletes.iter().map(|path| FlatUpdate::Delete {
+        path: absolute(path.clone()),
+        timestamp,
+    });
+    let updates = notification
+        .updates
+        .iter()
+        .map(|update| FlatUpdate::Update {
+            path: absolute(update.path.clone()),
+            value: update.value.clone(),
+            timestamp,
+        });
+
+    deletes.chain(updates).collect()
+}
+
+/// Convert an interval into nanoseconds, clamped to the range of gNMI.
+fn nanos(interval: Duration) -> u64 {
+    u64::try_from(interval.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// Convert nanoseconds since the Unix epoch into a [`SystemTime`].
+fn system_time(nanos: i64) -> SystemTime {
+    let offset = Duration::from_nanos(nanos.unsigned_abs());
+    if nanos < 0 {
+        UNIX_EPOCH - offset
+    } else {
+        UNIX_EPOCH + offset
+    }
+}
+
+/// The list that the last wildcard of `path` selects entries of, together with the path that
+/// queries the keys of its entries, or `None` if the wildcards can not be expanded.
+///
+/// The keys are queried by the first key of the list that is a wildcard. If only the name of
+/// the element is a wildcard, the whole list is queried instead.
+pub(crate) fn wildcard_list(path: &Path) -> Option<(Path, Path)> {
+    if path.elements().iter().any(|e| e.name() == "...") {
+        return None;
+    }
+    let last = path.elements().iter().rposition(PathElement::is_wildcard)?;
+
+    let mut list = Path::new();
+    if let Some(origin) = path.origin() {
+        list = list.with_origin(origin);
+    }
+    for element in &path.elements()[..=last] {
+        list.push(element.clone());
+    }
+
+    let mut keys = list.clone();
+    let element = &path.elements()[last];
+    if let Some((key, _)) = element.keys().iter().find(|(_, value)| *value == "*") {
+        keys.push(PathElement::new(key.clone()));
+    }
+    Some((list, keys))
+}
+
+/// Find the subscribed path an error message refers to.
+fn failed_path(paths: &[Path], message: &str) -> Option<Path> {
+    if let [path] = paths {
+        return Some(path.clone());
+    }
+
+    let mut mentioned = paths
+        .iter()
+        .filter(|path| message.contains(&path.to_string()));
+    match (mentioned.next(), mentioned.next()) {
+        (Some(path), None) => Some(path.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path() -> Path {
+        "/interfaces/interface[name=ethernet-1/1]/state"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn out_of_order_notifications() {
+        let update = |timestamp| {
+            SubscribeEvent::Update(Notification {
+                timestamp,
+                prefix: None,
+                updates: Vec::new(),
+                deletes: Vec::new(),
+                atomic: false,
+            })
+        };
+        let mut latest = None;
+
+        assert_eq!(check_order(update(10), &mut latest), update(10));
+        assert_eq!(check_order(update(20), &mut latest), update(20));
+        assert!(matches!(
+            check_order(update(15), &mut latest),
+            SubscribeEvent::OutOfOrder {
+                notification: Notification { timestamp: 15, .. },
+                latest: 20,
+            }
+        ));
+        assert_eq!(latest, Some(20));
+        assert_eq!(
+            check_order(SubscribeEvent::SyncResponse, &mut latest),
+            SubscribeEvent::SyncResponse
+        );
+        assert_eq!(check_order(update(20), &mut latest), update(20));
+    }
+
+    #[test]
+    fn error_refers_to_path() {
+        let list = SubscriptionList::builder()
+            .prefix("/interfaces".parse().unwrap())
+            .subscription(Subscription::new(
+                "interface[name=eth0]/state".parse().unwrap(),
+            ))
+            .subscription(Subscription::new(
+                "interface[name=eht1]/state".parse().unwrap(),
+            ))
+            .build()
+            .unwrap();
+        let paths = list.paths();
+
+        assert_eq!(
+            failed_path(
+                &paths,
+                "unknown path /interfaces/interface[name=eht1]/state"
+            ),
+            Some("/interfaces/interface[name=eht1]/state".parse().unwrap())
+        );
+        assert_eq!(failed_path(&paths, "internal error"), None);
+        assert_eq!(
+            failed_path(&paths[..1], "internal error"),
+            Some(paths[0].clone())
+        );
+    }
+
+    #[test]
+    fn flatten_notification() {
+        use crate::notification::Update;
+
+        let notification = Notification {
+            timestamp: 1_700_000_000_000_000_000,
+            prefix: Some("/interfaces/interface[name=eth0]".parse().unwrap()),
+            updates: vec![Update {
+                path: "state/mtu".parse().unwrap(),
+                value: TypedValue::Uint(9000),
+                duplicates: 0,
+            }],
+            deletes: vec!["state/description".parse().unwrap()],
+            atomic: false,
+        };
+        let timestamp = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        assert_eq!(
+            flatten(notification),
+            vec![
+                FlatUpdate::Delete {
+                    path: "/interfaces/interface[name=eth0]/state/description"
+                        .parse()
+                        .unwrap(),
+                    timestamp,
+                },
+                FlatUpdate::Update {
+                    path: "/interfaces/interface[name=eth0]/state/mtu"
+                        .parse()
+                        .unwrap(),
+                    value: TypedValue::Uint(9000),
+                    timestamp,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn throttle_updates() {
+        use crate::gen::gnmi::Notification as GnmiNotification;
+
+        let update = |path: &str, value: u64| GnmiSubscribeResponse {
+            response: Some(Response::Update(GnmiNotification {
+                update: vec![crate::notification::Update {
+                    path: path.parse().unwrap(),
+                    value: TypedValue::Uint(value),
+                    duplicates: 0,
+                }
+                .into()],
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        let value = |update: Option<Result<FlatUpdate, GinmiError>>| match update {
+            Some(Ok(FlatUpdate::Update { path, value, .. })) => (path.to_string(), value),
+            update => panic!("unexpected {:?}", update),
+        };
+
+        let (requests, _) = mpsc::channel(1);
+        let (sender, responses) = mpsc::channel(8);
+        for response in [update("/a", 1), update("/b", 1), update("/a", 2)] {
+            sender.send(Ok(response)).await.unwrap();
+        }
+        let stream = SubscribeStream {
+            requests,
+            responses,
+            metadata: MetadataMap::new(),
+            paths: Vec::new(),
+            latest_timestamp: None,
+        };
+
+        let start = tokio::time::Instant::now();
+        let interval = Duration::from_millis(50);
+        let mut updates = stream.throttle(interval);
+        assert_eq!(
+            value(updates.next().await),
+            ("/b".to_string(), TypedValue::Uint(1))
+        );
+        assert!(start.elapsed() >= interval);
+        assert_eq!(
+            value(updates.next().await),
+            ("/a".to_string(), TypedValue::Uint(2))
+        );
+
+        sender.send(Ok(update("/a", 3))).await.unwrap();
+        drop(sender);
+        assert_eq!(
+            value(updates.next().await),
+            ("/a".to_string(), TypedValue::Uint(3))
+        );
+        assert!(updates.next().await.is_none());
+    }
+
+    #[test]
+    fn wildcard_lists() {
+        let path = |s: &str| s.parse::<Path>().unwrap();
+        let list = |s: &str| wildcard_list(&path(s)).map(|(l, k)| (l.to_string(), k.to_string()));
+
+        assert_eq!(
+            list("/interfaces/interface[name=*]/state/counters"),
+            Some((
+                "/interfaces/interface[name=*]".to_string(),
+                "/interfaces/interface[name=*]/name".to_string()
+            ))
+        );
+        assert_eq!(
+            list("/network-instances/network-instance[name=*]/protocols/protocol[identifier=BGP][name=*]/bgp"),
+            Some((
+                "/network-instances/network-instance[name=*]/protocols/protocol[identifier=BGP][name=*]".to_string(),
+                "/network-instances/network-instance[name=*]/protocols/protocol[identifier=BGP][name=*]/name".to_string()
+            ))
+        );
+        assert_eq!(
+            list("/interfaces/*/state"),
+            Some(("/interfaces/*".to_string(), "/interfaces/*".to_string()))
+        );
+        assert_eq!(list("/interfaces/.../state"), None);
+        assert_eq!(list("/system/name"), None);
+    }
+
+    #[test]
+    fn encoding_in_capabilities() {
+        use crate::gen::gnmi::CapabilityResponse;
+
+        let capabilities = Capabilities(CapabilityResponse {
+            supported_encodings: vec![Encoding::JsonIetf as i32, Encoding::Proto as i32],
+            ..Default::default()
+        });
+        let list = |encoding| {
+            SubscriptionList::builder()
+                .subscription(Subscription::new(path()))
+                .encoding(encoding)
+                .build()
+                .unwrap()
+        };
+
+        assert_eq!(list(Encoding::Proto).encoding(), Encoding::Proto);
+        assert!(list(Encoding::Proto).check_encoding(&capabilities).is_ok());
+        assert!(matches!(
+            list(Encoding::Json).check_encoding(&capabilities),
+            Err(GinmiError::InvalidEncodingError(_))
+        ));
+    }
+
+    #[test]
+    fn target_in_prefix() {
+        let list = SubscriptionList::builder()
+            .prefix("/interfaces".parse().unwrap())
+            .target("router1")
+            .subscription(Subscription::new(path()))
+            .build()
+            .unwrap();
+
+        assert_eq!(list.target(), Some("router1"));
+        assert_eq!(list.paths()[0].target(), Some("router1"));
+
+        let list = SubscriptionList::builder()
+            .target(" ")
+            .subscription(Subscription::new(path()))
+            .build();
+        assert!(matches!(list, Err(GinmiError::InvalidSubscriptionError(_))));
+    }
+
+    #[test]
+    fn empty_subscription_list() {
+        assert!(SubscriptionList::builder().build().is_err());
+    }
+
+    #[test]
+    fn heartbeat_interval_with_suppress_redundant() {
+        let subscription = Subscription::new(path())
+            .mode(SubscriptionMode::Sample)
+            .sample_interval(Duration::from_secs(10))
+            .suppress_redundant(true)
+            .heartbeat_interval(Duration::from_secs(60));
+
+        let list = SubscriptionList::builder()
+            .subscription(subscription)
+            .build()
+            .unwrap();
+
+        assert!(list.list.subscription[0].suppress_redundant);
+        assert_eq!(list.list.subscription[0].heartbeat_interval, 60_000_000_000);
+    }
+
+    #[test]
+    fn intervals_in_nanoseconds() {
+        let subscription = Subscription::new(path())
+            .mode(SubscriptionMode::Sample)
+            .sample_interval(Duration::from_millis(500))
+            .suppress_redundant(true)
+            .heartbeat_interval(Duration::MAX);
+        let subscription = GnmiSubscription::from(subscription);
+
+        assert_eq!(subscription.sample_interval, 500_000_000);
+        assert_eq!(subscription.heartbeat_interval, u64::MAX);
+    }
+
+    #[test]
+    fn origins_only_on_prefix() {
+        let list = SubscriptionList::builder()
+            .target("router1")
+            .subscription(Subscription::new("openconfig:/system".parse().unwrap()))
+            .subscription(Subscription::new("openconfig:/interfaces".parse().unwrap()))
+            .build()
+            .unwrap();
+
+        let prefix = list.list.prefix.clone().map(Path::from).unwrap();
+        assert_eq!(prefix.origin(), Some("openconfig"));
+        assert_eq!(prefix.target(), Some("router1"));
+        assert!(list
+            .list
+            .subscription
+            .iter()
+            .all(|subscription| subscription.path.as_ref().unwrap().origin.is_empty()));
+
+        let list = SubscriptionList::builder()
+            .prefix("openconfig:/".parse().unwrap())
+            .subscription(Subscription::new("cli:/show".parse().unwrap()))
+            .build();
+        assert!(matches!(list, Err(GinmiError::PathParseError(_))));
+    }
+
+    #[test]
+    fn list_from_specs() {
+        let list = SubscriptionList::from_specs([
+            (
+                "/interfaces",
+                SubscriptionMode::Sample,
+                Duration::from_secs(10),
+            ),
+            (
+                "/system/name",
+                SubscriptionMode::OnChange,
+                Duration::from_secs(60),
+            ),
+        ])
+        .unwrap();
+
+        let subscriptions = &list.list.subscription;
+        assert_eq!(subscriptions.len(), 2);
+        assert_eq!(subscriptions[0].mode, SubscriptionMode::Sample as i32);
+        assert_eq!(subscriptions[0].sample_interval, 10_000_000_000);
+        assert_eq!(subscriptions[1].heartbeat_interval, 60_000_000_000);
+        assert_eq!(subscriptions[1].sample_interval, 0);
+
+        assert!(matches!(
+            SubscriptionList::from_specs([("/a[b", SubscriptionMode::Sample, Duration::ZERO)]),
+            Err(GinmiError::PathParseError(_))
+        ));
+        assert!(matches!(
+            SubscriptionList::from_specs([("/a", SubscriptionMode::Sample, Duration::MAX)]),
+            Err(GinmiError::InvalidSubscriptionError(_))
+        ));
+    }
+
+    #[test]
+    fn heartbeat_interval_without_suppress_redundant() {
+        let subscription = Subscription::new(path())
+            .mode(SubscriptionMode::Sample)
+            .heartbeat_interval(Duration::from_secs(60));
+
+        let list = SubscriptionList::builder()
+            .subscription(subscription)
+            .build();
+
+        assert!(list.is_err());
+    }
+
+    #[test]
+    fn suppress_redundant_on_change() {
+        let subscription = Subscription::new(path())
+            .mode(SubscriptionMode::OnChange)
+            .suppress_redundant(true);
+
+        let list = SubscriptionList::builder()
+            .subscription(subscription)
+            .build();
+
+        assert!(list.is_err());
+    }
+}
@@ -0,0 +1,312 @@
+//! Restrict the TLS versions and cipher suites that are negotiated with the target device.
+//!
+//! The TLS-Settings of tonic always use the defaults of rustls. If the TLS versions or cipher
+//! suites are restricted with [`ClientBuilder::tls_min_version`] or
+//! [`ClientBuilder::tls_cipher_suites`], the client establishes the TLS session itself with a
+//! rustls [`ClientConfig`] instead, still verifying the certificate of the target device
+//! against the configured CA and the trust store of the operating system.
+//!
+//! Because the TLS session is established by ginmi then, its [`TlsDetails`] are available
+//! with [`Client::tls_details`].
+//!
+//! [`ClientBuilder::tls_min_version`]: super::ClientBuilder::tls_min_version
+//! [`ClientBuilder::tls_cipher_suites`]: super::ClientBuilder::tls_cipher_suites
+//! [`Client::tls_details`]: super::Client::tls_details
+use crate::error::GinmiError;
+use std::fmt;
+use std::future::Future;
+use std::io::Cursor;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::{
+    Certificate, ClientConfig, ClientConnection, RootCertStore, ServerName, ALL_VERSIONS,
+    DEFAULT_CIPHER_SUITES,
+};
+use tonic::transport::Uri;
+use tower_service::Service;
+
+pub use tokio_rustls::rustls::{
+    cipher_suite, version, ProtocolVersion, SupportedCipherSuite, SupportedProtocolVersion,
+};
+
+/// ALPN protocol of HTTP/2, which gRPC requires.
+const ALPN_H2: &[u8] = b"h2";
+
+/// ALPN protocol of HTTP/1.1, which gRPC-Web uses.
+const ALPN_HTTP1: &[u8] = b"http/1.1";
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Details of the TLS session negotiated with the target device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsDetails {
+    /// Protocol negotiated with ALPN, `h2` for HTTP/2.
+    pub alpn_protocol: Option<Vec<u8>>,
+    /// Version of TLS that is used.
+    pub protocol_version: Option<ProtocolVersion>,
+    /// Cipher suite that is used.
+    pub cipher_suite: Option<SupportedCipherSuite>,
+}
+
+impl TlsDetails {
+    /// Whether HTTP/2 was negotiated with ALPN.
+    pub fn is_http2(&self) -> bool {
+        self.alpn_protocol.as_deref() == Some(ALPN_H2)
+    }
+
+    pub(crate) fn of(session: &ClientConnection) -> Self {
+        TlsDetails {
+            alpn_protocol: session.alpn_protocol().map(<[u8]>::to_vec),
+            protocol_version: session.protocol_version(),
+            cipher_suite: session.negotiated_cipher_suite(),
+        }
+    }
+}
+
+/// The [`TlsDetails`] of the most recent connection, shared between a connector and the
+/// [`Client`](super::Client) that uses it.
+pub(crate) type SharedTlsDetails = Arc<Mutex<Option<TlsDetails>>>;
+
+/// TLS versions and cipher suites that may be negotiated with the target device.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TlsOptions {
+    pub(crate) min_version: Option<&'static SupportedProtocolVersion>,
+    pub(crate) cipher_suites: Option<Vec<SupportedCipherSuite>>,
+}
+
+impl TlsOptions {
+    /// Whether the defaults of rustls are restricted at all.
+    pub(crate) fn is_restricted(&self) -> bool {
+        self.min_version.is_some() || self.cipher_suites.is_some()
+    }
+
+    /// Create a rustls [`ClientConfig`] that verifies certificates against `roots`.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::TlsConfigError`] if the TLS versions and cipher suites have
+    ///   no match.
+    pub(crate) fn client_config(&self, roots: RootCertStore) -> Result<ClientConfig, GinmiError> {
+        let min_version = self.min_version.map_or(0, |v| v.version.get_u16());
+        let versions: Vec<&'static SupportedProtocolVersion> = ALL_VERSIONS
+            .iter()
+            .copied()
+            .filter(|v| v.version.get_u16() >= min_version)
+            .collect();
+        let suites = self
+            .cipher_suites
+            .as_deref()
+            .unwrap_or(DEFAULT_CIPHER_SUITES);
+
+        Ok(ClientConfig::builder()
+            .with_cipher_suites(suites)
+            .with_safe_default_kx_groups()
+            .with_protocol_versions(&versions)
+            .map_err(|e| GinmiError::TlsConfigError(e.to_string()))?
+            .with_root_certificates(roots)
+            .with_no_client_auth())
+    }
+}
+
+/// Create a store of the certificates that are trusted to sign the certificate of the target
+/// device, i.e. the trust store of the operating system and the PEM encoded `ca_certificate`,
+/// like tonic does for its TLS-Settings.
+///
+/// # Errors
+/// - Returns [`GinmiError::TlsConfigError`] if the trust store of the operating system can
+///   not be loaded or `ca_certificate` does not contain a valid certificate.
+pub(crate) fn root_store(ca_certificate: Option<&[u8]>) -> Result<RootCertStore, GinmiError> {
+    let mut roots = RootCertStore::empty();
+    let native = rustls_native_certs::load_native_certs().map_err(|e| {
+        GinmiError::TlsConfigError(format!("can not load the native trust store: {}", e))
+    })?;
+    roots.add_parsable_certificates(&native);
+
+    if let Some(ca_certificate) = ca_certificate {
+        let invalid = |e: &dyn fmt::Display| {
+            GinmiError::TlsConfigError(format!("invalid CA certificate: {}", e))
+        };
+        let certificates =
+            rustls_pemfile::certs(&mut Cursor::new(ca_certificate)).map_err(|e| invalid(&e))?;
+        if certificates.is_empty() {
+            return Err(invalid(&"no PEM encoded certificate found"));
+        }
+        for certificate in certificates {
+            roots
+                .add(&Certificate(certificate))
+                .map_err(|e| invalid(&e))?;
+        }
+    }
+
+    Ok(roots)
+}
+
+/// rustls [`ClientConfig`] and name of the target device to establish TLS sessions with.
+#[derive(Debug, Clone)]
+pub(crate) struct TlsSession {
+    config: Arc<ClientConfig>,
+    server_name: ServerName,
+    protocol: &'static [u8],
+    details: SharedTlsDetails,
+}
+
+impl TlsSession {
+    /// Establish sessions with `config`, verifying the certificate of the target device
+    /// against `domain`.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::TlsConfigError`] if `domain` is neither a valid DNS name nor
+    ///   an IP address.
+    pub(crate) fn new(mut config: ClientConfig, domain: &str) -> Result<Self, GinmiError> {
+        let server_name = ServerName::try_from(domain).map_err(|_| {
+            GinmiError::TlsConfigError(format!("{} is not a valid TLS server name", domain))
+        })?;
+        config.alpn_protocols = vec![ALPN_H2.to_vec()];
+
+        Ok(Self {
+            config: Arc::new(config),
+            server_name,
+            protocol: ALPN_H2,
+            details: Default::default(),
+        })
+    }
+
+    /// Negotiate HTTP/1.1 instead of HTTP/2, e.g. for gRPC-Web. Target devices that do not
+    /// support ALPN are accepted as well, as they speak HTTP/1.1 by default.
+    pub(crate) fn http1(mut self) -> Self {
+        Arc::make_mut(&mut self.config).alpn_protocols = vec![ALPN_HTTP1.to_vec()];
+        self.protocol = ALPN_HTTP1;
+        self
+    }
+
+    /// The [`TlsDetails`] of the most recent session established by the connectors.
+    pub(crate) fn details(&self) -> SharedTlsDetails {
+        Arc::clone(&self.details)
+    }
+
+    /// Wrap the connector `inner`, so a TLS session is established on top of its connections.
+    pub(crate) fn connector<C>(&self, inner: C) -> TlsConnector<C> {
+        TlsConnector {
+            inner,
+            session: self.clone(),
+        }
+    }
+}
+
+/// Connector that establishes a TLS session on top of the connections of its inner
+/// connector, which are expected to use plaintext.
+#[derive(Debug, Clone)]
+pub(crate) struct TlsConnector<C> {
+    inner: C,
+    session: TlsSession,
+}
+
+impl<C> Service<Uri> for TlsConnector<C>
+where
+    C: Service<Uri>,
+    C::Future: Send + 'static,
+    C::Response: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    C::Error: Into<BoxError>,
+{
+    type Response = TlsStream<C::Response>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connecting = self.inner.call(uri);
+        let connector = tokio_rustls::TlsConnector::from(Arc::clone(&self.session.config));
+        let server_name = self.session.server_name.clone();
+        let details = self.session.details();
+        let protocol = self.session.protocol;
+
+        Box::pin(async move {
+            let io = connecting.await.map_err(Into::<BoxError>::into)?;
+            let stream = connector.connect(server_name, io).await?;
+
+            let (_, session) = stream.get_ref();
+            // recorded before checking ALPN, to tell what was negotiated instead
+            *details
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(TlsDetails::of(session));
+            match session.alpn_protocol() {
+                Some(negotiated) if negotiated == protocol => {}
+                None if protocol == ALPN_HTTP1 => {}
+                _ => {
+                    return Err(BoxError::from(format!(
+                        "{} was not negotiated with the target device",
+                        String::from_utf8_lossy(protocol)
+                    )))
+                }
+            }
+            Ok::<_, BoxError>(stream)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restricted_versions() {
+        let options = TlsOptions {
+            min_version: Some(&version::TLS13),
+            cipher_suites: Some(vec![cipher_suite::TLS13_AES_256_GCM_SHA384]),
+        };
+        assert!(options.is_restricted());
+        assert!(options.client_config(RootCertStore::empty()).is_ok());
+        assert!(!TlsOptions::default().is_restricted());
+
+        let options = TlsOptions {
+            min_version: Some(&version::TLS13),
+            cipher_suites: Some(vec![cipher_suite::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256]),
+        };
+        assert!(matches!(
+            options.client_config(RootCertStore::empty()),
+            Err(GinmiError::TlsConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_ca_certificate() {
+        assert!(matches!(
+            root_store(Some(b"CA Certificate")),
+            Err(GinmiError::TlsConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn server_names() {
+        let config = || {
+            TlsOptions::default()
+                .client_config(RootCertStore::empty())
+                .unwrap()
+        };
+        let session = TlsSession::new(config(), "router1.example.com").unwrap();
+        assert_eq!(*session.details().lock().unwrap(), None);
+        assert_eq!(session.config.alpn_protocols, vec![b"h2".to_vec()]);
+        let session = session.http1();
+        assert_eq!(session.config.alpn_protocols, vec![b"http/1.1".to_vec()]);
+        assert!(TlsSession::new(config(), "2001:db8::1").is_ok());
+        assert!(matches!(
+            TlsSession::new(config(), "invalid domain"),
+            Err(GinmiError::TlsConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn negotiated_http2() {
+        let details = TlsDetails {
+            alpn_protocol: Some(b"h2".to_vec()),
+            protocol_version: Some(ProtocolVersion::TLSv1_3),
+            cipher_suite: None,
+        };
+        assert!(details.is_http2());
+    }
+}
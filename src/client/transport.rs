@@ -0,0 +1,148 @@
+//! Transport that carries the RPCs of a [`Client`] to the target device.
+//!
+//! All requests of a [`Client`] are sent through a [`GnmiTransport`], whose methods take and
+//! return the protobuf messages of gNMI. [`TonicTransport`] sends them to the target device
+//! with tonic and is used by every [`Client`] created with the [`ClientBuilder`]. Code that is
+//! built on top of [`Client`] can be unit tested with a fake transport instead, passed to
+//! [`Client::from_transport`], without a target device or a gRPC server.
+//!
+//! # Examples
+//! ```rust
+//! use ginmi::client::transport::{
+//!     BoxStream, CapabilityRequest, CapabilityResponse, GetRequest, GetResponse, GnmiTransport,
+//!     SetRequest, SetResponse, SubscribeRequest, SubscribeResponse,
+//! };
+//! use ginmi::client::Client;
+//! use tonic::{Request, Response, Status};
+//!
+//! /// Target device that supports gNMI 0.10.0, but no other RPC.
+//! #[derive(Clone)]
+//! struct Fake;
+//!
+//! #[tonic::async_trait]
+//! impl GnmiTransport for Fake {
+//!     async fn capabilities(
+//!         &mut self,
+//!         _: Request<CapabilityRequest>,
+//!     ) -> Result<Response<CapabilityResponse>, Status> {
+//!         Ok(Response::new(CapabilityResponse {
+//!             g_nmi_version: "0.10.0".to_string(),
+//!             ..Default::default()
+//!         }))
+//!     }
+//!
+//!     async fn get(&mut self, _: Request<GetRequest>) -> Result<Response<GetResponse>, Status> {
+//!         Err(Status::unimplemented("get"))
+//!     }
+//!
+//!     async fn set(&mut self, _: Request<SetRequest>) -> Result<Response<SetResponse>, Status> {
+//!         Err(Status::unimplemented("set"))
+//!     }
+//!
+//!     async fn subscribe(
+//!         &mut self,
+//!         _: Request<BoxStream<SubscribeRequest>>,
+//!     ) -> Result<Response<BoxStream<Result<SubscribeResponse, Status>>>, Status> {
+//!         Err(Status::unimplemented("subscribe"))
+//!     }
+//! }
+//!
+//! # tokio_test::block_on(async {
+//! let mut client = Client::from_transport(Fake);
+//! assert_eq!(client.capabilities().await.unwrap().gnmi_version(), "0.10.0");
+//! # });
+//! ```
+//!
+//! [`Client`]: super::Client
+//! [`Client::from_transport`]: super::Client::from_transport
+//! [`ClientBuilder`]: super::ClientBuilder
+use crate::gen::gnmi::g_nmi_client::GNmiClient;
+use hyper::body::Bytes;
+use std::pin::Pin;
+use tokio_stream::Stream;
+use tonic::body::BoxBody;
+use tonic::client::GrpcService;
+use tonic::codegen::{Body, StdError};
+use tonic::{Request, Response, Status};
+
+pub use crate::gen::gnmi::{
+    CapabilityRequest, CapabilityResponse, GetRequest, GetResponse, SetRequest, SetResponse,
+    SubscribeRequest, SubscribeResponse,
+};
+
+/// Stream of the messages of a streaming RPC.
+pub type BoxStream<T> = Pin<Box<dyn Stream<Item = T> + Send + 'static>>;
+
+/// The RPCs of the gNMI service, as sent by a [`Client`](super::Client).
+///
+/// The requests carry the metadata the client adds itself, e.g. the correlation id of the
+/// request, while credentials and static headers are added by [`TonicTransport`]. The
+/// metadata of responses is passed on to the caller, e.g. by
+/// [`Client::capabilities_with_metadata`](super::Client::capabilities_with_metadata).
+#[tonic::async_trait]
+pub trait GnmiTransport: Clone + Send + Sync + 'static {
+    /// Query the capabilities of the target device.
+    async fn capabilities(
+        &mut self,
+        request: Request<CapabilityRequest>,
+    ) -> Result<Response<CapabilityResponse>, Status>;
+
+    /// Retrieve data from the target device.
+    async fn get(&mut self, request: Request<GetRequest>) -> Result<Response<GetResponse>, Status>;
+
+    /// Modify the state of the target device.
+    async fn set(&mut self, request: Request<SetRequest>) -> Result<Response<SetResponse>, Status>;
+
+    /// Subscribe to data of the target device. The requests after the first one poll the
+    /// subscription, the subscription ends once the stream of responses is dropped.
+    async fn subscribe(
+        &mut self,
+        request: Request<BoxStream<SubscribeRequest>>,
+    ) -> Result<Response<BoxStream<Result<SubscribeResponse, Status>>>, Status>;
+}
+
+/// [`GnmiTransport`] that sends the RPCs to the target device with tonic, over the gRPC
+/// service `S`, e.g. a [`Channel`](tonic::transport::Channel).
+#[derive(Debug, Clone)]
+pub struct TonicTransport<S> {
+    inner: GNmiClient<S>,
+}
+
+impl<S> TonicTransport<S> {
+    pub(crate) fn new(inner: GNmiClient<S>) -> Self {
+        Self { inner }
+    }
+}
+
+#[tonic::async_trait]
+impl<S> GnmiTransport for TonicTransport<S>
+where
+    S: GrpcService<BoxBody> + Clone + Send + Sync + 'static,
+    S::Future: Send,
+    S::Error: Into<StdError>,
+    S::ResponseBody: Body<Data = Bytes> + Send + 'static,
+    <S::ResponseBody as Body>::Error: Into<StdError> + Send,
+{
+    async fn capabilities(
+        &mut self,
+        request: Request<CapabilityRequest>,
+    ) -> Result<Response<CapabilityResponse>, Status> {
+        self.inner.capabilities(request).await
+    }
+
+    async fn get(&mut self, request: Request<GetRequest>) -> Result<Response<GetResponse>, Status> {
+        self.inner.get(request).await
+    }
+
+    async fn set(&mut self, request: Request<SetRequest>) -> Result<Response<SetResponse>, Status> {
+        self.inner.set(request).await
+    }
+
+    async fn subscribe(
+        &mut self,
+        request: Request<BoxStream<SubscribeRequest>>,
+    ) -> Result<Response<BoxStream<Result<SubscribeResponse, Status>>>, Status> {
+        let response = self.inner.subscribe(request).await?;
+        Ok(response.map(|responses| Box::pin(responses) as BoxStream<_>))
+    }
+}
@@ -0,0 +1,255 @@
+//! Receive telemetry from target devices that dial out to a collector.
+//!
+//! # Non-standard service
+//! gNMI does not specify a dial-out service: the `gnmi.proto` and `collector.proto` of the
+//! gNMI specification only define the `Subscribe` RPC of targets and the `Reconnect` RPC of
+//! the gNMI collector. The `gNMIDialOut.Publish` RPC served by this module is defined by
+//! ginmi itself in `proto/dialout/dialout.proto`, so target devices can only dial out to it
+//! if they, or an agent running on them, implement that service. Vendor specific dial-out
+//! protocols, e.g. the ones of Cisco or Huawei, are not supported.
+//!
+//! With dial-out, the subscriptions are configured on the target device, which connects to
+//! the collector and publishes the responses to them, instead of the collector connecting to
+//! the target device and subscribing.
+//!
+//! [`listen`] serves the `Publish` RPC of the `gnmi_dialout.gNMIDialOut` service and returns
+//! a [`PublishStream`] of the responses of all connected target devices.
+//!
+//! # Examples
+//! ```rust
+//! # use tokio_stream::StreamExt;
+//! # tokio_test::block_on(async {
+//! let mut published = ginmi::dialout::listen("0.0.0.0:57500".parse().unwrap())
+//!     .await
+//!     .unwrap();
+//!
+//! while let Some(published) = published.next().await {
+//!     match published.response {
+//!         Ok(response) => println!("{:?}: {:?}", published.target, response.event),
+//!         Err(e) => println!("{:?} disconnected: {}", published.peer, e),
+//!     }
+//! }
+//! # });
+//! ```
+use crate::client::{decode_response, SubscribeEvent, SubscribeResponse, DEFAULT_SUBSCRIBE_BUFFER};
+use crate::error::GinmiError;
+use crate::gen::gnmi::SubscribeResponse as GnmiSubscribeResponse;
+use crate::gen::gnmi_dialout::g_nmi_dial_out_server::{GNmiDialOut, GNmiDialOutServer};
+use crate::gen::gnmi_dialout::PublishResponse;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status, Streaming};
+
+/// A response published by a target device.
+#[derive(Debug)]
+pub struct Published {
+    /// Target of the prefix of the most recent notification of the connection, which
+    /// identifies the device if it sets one.
+    pub target: Option<String>,
+    /// Address the target device connected from.
+    pub peer: Option<SocketAddr>,
+    /// The published response, or the error that ended the connection of the device.
+    pub response: Result<SubscribeResponse, GinmiError>,
+}
+
+/// Stream of the responses published by all target devices that connect to a collector.
+///
+/// Returned by [`listen`]. Dropping the stream stops accepting target devices and ends the
+/// connections to all of them.
+#[derive(Debug)]
+pub struct PublishStream {
+    local_addr: SocketAddr,
+    published: mpsc::Receiver<Published>,
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl PublishStream {
+    /// Address the collector listens on, e.g. to find the port when listening on port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Stream for PublishStream {
+    type Item = Published;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.published.poll_recv(cx)
+    }
+}
+
+/// Listen for target devices on `addr` and return the stream of the responses they publish.
+///
+/// The connections use plaintext HTTP/2. Up to
+/// [`DEFAULT_SUBSCRIBE_BUFFER`] responses are buffered if the stream is not consumed as fast
+/// as the target devices publish, after which the target devices are slowed down.
+///
+/// If the collector stops serving, a last [`Published`] without `target` and `peer` is
+/// yielded, whose `response` is a [`GinmiError::ListenError`] with the cause.
+///
+/// # Errors
+/// - Returns [`GinmiError::ListenError`] if the address can not be bound.
+pub async fn listen(addr: SocketAddr) -> Result<PublishStream, GinmiError> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| GinmiError::ListenError(e.to_string()))?;
+    let local_addr = listener
+        .local_addr()
+        .map_err(|e| GinmiError::ListenError(e.to_string()))?;
+    let incoming = TcpIncoming::from_listener(listener, true, None)
+        .map_err(|e| GinmiError::ListenError(e.to_string()))?;
+
+    let (sender, published) = mpsc::channel(DEFAULT_SUBSCRIBE_BUFFER);
+    let (shutdown, stopped) = oneshot::channel::<()>();
+    let failed = sender.clone();
+    let server = Server::builder()
+        .add_service(GNmiDialOutServer::new(Collector { published: sender }))
+        .serve_with_incoming_shutdown(incoming, async {
+            let _ = stopped.await;
+        });
+
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            tracing::error!("collector at {} stopped: {}", local_addr, e);
+            let item = Published {
+                target: None,
+                peer: None,
+                response: Err(GinmiError::ListenError(e.to_string())),
+            };
+            let _ = failed.send(item).await;
+        }
+    });
+
+    Ok(PublishStream {
+        local_addr,
+        published,
+        _shutdown: shutdown,
+    })
+}
+
+/// Server of the `gNMIDialOut` service that forwards all published responses.
+struct Collector {
+    published: mpsc::Sender<Published>,
+}
+
+#[tonic::async_trait]
+impl GNmiDialOut for Collector {
+    type PublishStream = ReceiverStream<Result<PublishResponse, Status>>;
+
+    async fn publish(
+        &self,
+        request: Request<Streaming<GnmiSubscribeResponse>>,
+    ) -> Result<Response<Self::PublishStream>, Status> {
+        let peer = request.remote_addr();
+        let mut responses = request.into_inner();
+        let published = self.published.clone();
+        // the RPC ends once the task drops the sender of the acknowledgements
+        let (acknowledgements, stream) = mpsc::channel(1);
+
+        tokio::spawn(async move {
+            let _acknowledgements = acknowledgements;
+            let mut target = None;
+
+            loop {
+                let response = tokio::select! {
+                    _ = published.closed() => break,
+                    response = responses.next() => response,
+                };
+
+                let (response, end) = match response {
+                    Some(Ok(response)) => match decode_response(response, &[]) {
+                        Some(response) => (response, false),
+                        None => continue,
+                    },
+                    Some(Err(status)) => (Err(status.into()), true),
+                    None => break,
+                };
+
+                if let Ok(SubscribeResponse {
+                    event: SubscribeEvent::Update(notification),
+                    ..
+                }) = &response
+                {
+                    if let Some(prefix_target) = notification
+                        .prefix
+                        .as_ref()
+                        .and_then(|prefix| prefix.target())
+                    {
+                        target = Some(prefix_target.to_string());
+                    }
+                }
+
+                let item = Published {
+                    target: target.clone(),
+                    peer,
+                    response,
+                };
+                if published.send(item).await.is_err() || end {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(stream)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::gnmi::subscribe_response::Response as GnmiResponse;
+    use crate::gen::gnmi::Notification as GnmiNotification;
+    use crate::gen::gnmi_dialout::g_nmi_dial_out_client::GNmiDialOutClient;
+    use crate::path::Path;
+    use tonic::transport::Channel;
+
+    #[tokio::test]
+    async fn receive_published_responses() {
+        let mut published = listen("127.0.0.1:0".parse().unwrap()).await.unwrap();
+        let target = format!("http://{}", published.local_addr());
+        let channel = Channel::from_shared(target)
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let mut device = GNmiDialOutClient::new(channel);
+
+        let update = GnmiSubscribeResponse {
+            response: Some(GnmiResponse::Update(GnmiNotification {
+                prefix: Some(Path::new().with_target("router1").into()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        let sync = GnmiSubscribeResponse {
+            response: Some(GnmiResponse::SyncResponse(true)),
+            ..Default::default()
+        };
+        let _acknowledgements = device
+            .publish(tokio_stream::iter([update, sync]))
+            .await
+            .unwrap();
+
+        let first = published.next().await.unwrap();
+        assert_eq!(first.target.as_deref(), Some("router1"));
+        assert!(first.peer.is_some());
+        assert!(matches!(
+            first.response.unwrap().event,
+            SubscribeEvent::Update(_)
+        ));
+
+        let second = published.next().await.unwrap();
+        assert_eq!(second.target.as_deref(), Some("router1"));
+        assert!(matches!(
+            second.response.unwrap().event,
+            SubscribeEvent::SyncResponse
+        ));
+    }
+}
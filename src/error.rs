@@ -1,3 +1,13 @@
+use crate::gen::gnmi::Error as GnmiError;
+
+/// Errors returned by this crate.
+///
+/// Failures reported by the target device, e.g. a rejected request, are returned as
+/// [`GinmiError::NotFound`], [`GinmiError::PermissionDenied`], [`GinmiError::Unavailable`] or
+/// [`GinmiError::Unauthenticated`] for the common status codes and as
+/// [`GinmiError::GrpcError`] for all others. Responses that were received, but could not be interpreted,
+/// e.g. values without content or invalid extensions, are returned as
+/// [`GinmiError::DecodeError`] instead, and invalid paths as [`GinmiError::PathParseError`].
 #[derive(thiserror::Error, Debug)]
 pub enum GinmiError {
     #[error("error connecting to endpoint: {}", .0)]
@@ -6,6 +16,143 @@ pub enum GinmiError {
     InvalidUriError(String),
     #[error("invalid header in grpc request: {}", .0)]
     InvalidHeaderValue(#[from] tonic::metadata::errors::InvalidMetadataValue),
+    #[error("invalid header name in grpc request: {}", .0)]
+    InvalidHeaderName(#[from] tonic::metadata::errors::InvalidMetadataKey),
+    #[error("invalid tls configuration: {}", .0)]
+    TlsConfigError(String),
+    #[error("invalid credentials: {}", .0)]
+    InvalidCredentialsError(String),
+    #[error("error reconnecting to target device: {}", .0)]
+    ReconnectError(String),
     #[error("error communicating with target device: {}", .0)]
-    GrpcError(#[from] tonic::Status),
+    GrpcError(tonic::Status),
+    #[error("data element not found on target device: {}", .0.message())]
+    NotFound(tonic::Status),
+    #[error("permission denied by target device: {}", .0.message())]
+    PermissionDenied(tonic::Status),
+    #[error("target device is unavailable: {}", .0.message())]
+    Unavailable(tonic::Status),
+    #[error("not authenticated by target device: {}", .0.message())]
+    Unauthenticated(tonic::Status),
+    #[error("invalid path: {}", .0)]
+    PathParseError(String),
+    #[error("invalid subscription: {}", .0)]
+    InvalidSubscriptionError(String),
+    #[error("operation not supported by target device: {}", .0)]
+    UnsupportedOperationError(String),
+    #[error("model not supported by target device: {}", .0)]
+    UnsupportedModelError(String),
+    #[error("invalid encoding: {}", .0)]
+    InvalidEncodingError(String),
+    #[error("error decoding response of target device: {}", .0)]
+    DecodeError(String),
+    #[error("invalid configuration in environment: {}", .0)]
+    EnvironmentError(String),
+    #[error("error listening for target devices: {}", .0)]
+    ListenError(String),
+}
+
+impl GinmiError {
+    /// Returns `true` if the target device does not implement the requested RPC, e.g. a
+    /// read-only device that only supports Get and Subscribe and rejects Set requests with
+    /// the gRPC status `UNIMPLEMENTED`, or if the request uses an operation that the target
+    /// device does not support.
+    pub fn is_unsupported(&self) -> bool {
+        match self {
+            GinmiError::GrpcError(status) => status.code() == tonic::Code::Unimplemented,
+            GinmiError::UnsupportedOperationError(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the gRPC status the target device responded with, if the error was reported
+    /// by the target device.
+    pub fn status(&self) -> Option<&tonic::Status> {
+        match self {
+            GinmiError::GrpcError(status)
+            | GinmiError::NotFound(status)
+            | GinmiError::PermissionDenied(status)
+            | GinmiError::Unavailable(status)
+            | GinmiError::Unauthenticated(status) => Some(status),
+            _ => None,
+        }
+    }
+}
+
+impl From<tonic::Status> for GinmiError {
+    /// Categorize the status by its code, keeping the status with its message and details.
+    fn from(status: tonic::Status) -> Self {
+        match status.code() {
+            tonic::Code::NotFound => GinmiError::NotFound(status),
+            tonic::Code::PermissionDenied => GinmiError::PermissionDenied(status),
+            tonic::Code::Unavailable => GinmiError::Unavailable(status),
+            tonic::Code::Unauthenticated => GinmiError::Unauthenticated(status),
+            _ => GinmiError::GrpcError(status),
+        }
+    }
+}
+
+/// Convert an error that a target device embedded in a response into a [`GinmiError`].
+///
+/// The `Error` message is deprecated in favor of the gRPC status, but older targets still
+/// use it to report failures in otherwise successful responses.
+#[allow(deprecated)]
+pub(crate) fn check_embedded(error: Option<GnmiError>) -> Result<(), GinmiError> {
+    match error {
+        Some(error) if error.code != 0 => {
+            Err(tonic::Status::new(tonic::Code::from(error.code as i32), error.message).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unimplemented_is_unsupported() {
+        let error = GinmiError::from(tonic::Status::unimplemented("set is not supported"));
+        assert!(error.is_unsupported());
+
+        let error = GinmiError::from(tonic::Status::permission_denied("read-only user"));
+        assert!(!error.is_unsupported());
+    }
+
+    #[test]
+    fn categorize_status() {
+        let error = GinmiError::from(tonic::Status::not_found("no such interface"));
+        assert!(matches!(error, GinmiError::NotFound(_)));
+        assert_eq!(error.status().unwrap().message(), "no such interface");
+        assert_eq!(
+            error.to_string(),
+            "data element not found on target device: no such interface"
+        );
+
+        let error = GinmiError::from(tonic::Status::permission_denied("read-only user"));
+        assert!(matches!(error, GinmiError::PermissionDenied(_)));
+        let error = GinmiError::from(tonic::Status::unavailable("connection refused"));
+        assert!(matches!(error, GinmiError::Unavailable(_)));
+        let error = GinmiError::from(tonic::Status::unauthenticated("invalid password"));
+        assert!(matches!(error, GinmiError::Unauthenticated(_)));
+        let error = GinmiError::from(tonic::Status::internal("internal error"));
+        assert!(matches!(error, GinmiError::GrpcError(_)));
+        assert!(GinmiError::PathParseError(String::new()).status().is_none());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn embedded_errors() {
+        let error = |code| {
+            Some(GnmiError {
+                code,
+                message: "set is not supported".to_string(),
+                data: None,
+            })
+        };
+
+        assert!(check_embedded(None).is_ok());
+        assert!(check_embedded(error(0)).is_ok());
+        assert!(check_embedded(error(12)).unwrap_err().is_unsupported());
+    }
 }
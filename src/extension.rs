@@ -0,0 +1,211 @@
+//! Extensions that can be attached to gNMI requests and responses.
+//!
+//! The available extensions are described in the
+//! [gNMI Extensions](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-extensions.md)
+//! documentation. Extensions that are not known to this crate are represented as
+//! [`Extension::Registered`] with their raw payload.
+use crate::error::GinmiError;
+use crate::gen::gnmi_ext::commit::Action as GnmiCommitAction;
+use crate::gen::gnmi_ext::extension::Ext;
+use crate::gen::gnmi_ext::history::Request as GnmiHistoryRequest;
+use crate::gen::gnmi_ext::{
+    Commit as GnmiCommit, CommitCancel, CommitConfirm, CommitRequest, Extension as GnmiExtension,
+    History as GnmiHistory, MasterArbitration as GnmiMasterArbitration, RegisteredExtension, Role,
+    TimeRange, Uint128,
+};
+use crate::gen::google::protobuf::Duration as GnmiDuration;
+use std::time::Duration;
+
+/// An extension of a gNMI request or response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Extension {
+    /// An extension identified by its registered id, carrying the binary encoded protobuf
+    /// message of the extension.
+    Registered { id: i32, msg: Vec<u8> },
+    /// Election of the client that is allowed to modify the target device.
+    MasterArbitration {
+        /// Role of the client, or `None` for the default role.
+        role: Option<String>,
+        /// Election id of the client. The client with the highest id is the master.
+        election_id: Option<u128>,
+    },
+    /// Retrieval of historical data.
+    History(History),
+    /// Confirmed commit of a set request.
+    Commit {
+        /// Identifier of the commit.
+        id: String,
+        /// Action to take on the commit.
+        action: Option<CommitAction>,
+    },
+}
+
+/// Point in time or time range for which historical data is requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum History {
+    /// Values at the given time in nanoseconds since the Unix epoch.
+    SnapshotTime(i64),
+    /// All values between the given times in nanoseconds since the Unix epoch.
+    Range { start: i64, end: i64 },
+}
+
+/// Action of a confirmed commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitAction {
+    /// Apply the set request, rolling it back if it is not confirmed within the given
+    /// duration.
+    Commit { rollback_duration: Option<Duration> },
+    /// Confirm a pending commit.
+    Confirm,
+    /// Cancel a pending commit and roll it back.
+    Cancel,
+}
+
+impl From<Extension> for GnmiExtension {
+    fn from(extension: Extension) -> Self {
+        let ext = match extension {
+            Extension::Registered { id, msg } => {
+                Ext::RegisteredExt(RegisteredExtension { id, msg })
+            }
+            Extension::MasterArbitration { role, election_id } => {
+                Ext::MasterArbitration(GnmiMasterArbitration {
+                    role: role.map(|id| Role { id }),
+                    election_id: election_id.map(|id| Uint128 {
+                        high: (id >> 64) as u64,
+                        low: id as u64,
+                    }),
+                })
+            }
+            Extension::History(history) => Ext::History(GnmiHistory {
+                request: Some(match history {
+                    History::SnapshotTime(time) => GnmiHistoryRequest::SnapshotTime(time),
+                    History::Range { start, end } => {
+                        GnmiHistoryRequest::Range(TimeRange { start, end })
+                    }
+                }),
+            }),
+            Extension::Commit { id, action } => Ext::Commit(GnmiCommit {
+                id,
+                action: action.map(|action| match action {
+                    CommitAction::Commit { rollback_duration } => {
+                        GnmiCommitAction::Commit(CommitRequest {
+                            rollback_duration: rollback_duration.map(|d| GnmiDuration {
+                                seconds: d.as_secs() as i64,
+                                nanos: d.subsec_nanos() as i32,
+                            }),
+                        })
+                    }
+                    CommitAction::Confirm => GnmiCommitAction::Confirm(CommitConfirm {}),
+                    CommitAction::Cancel => GnmiCommitAction::Cancel(CommitCancel {}),
+                }),
+            }),
+        };
+
+        GnmiExtension { ext: Some(ext) }
+    }
+}
+
+impl TryFrom<GnmiExtension> for Extension {
+    type Error = GinmiError;
+
+    fn try_from(extension: GnmiExtension) -> Result<Self, Self::Error> {
+        let ext = match extension.ext {
+            Some(ext) => ext,
+            None => {
+                return Err(GinmiError::DecodeError(
+                    "extension does not contain a value".to_string(),
+                ))
+            }
+        };
+
+        Ok(match ext {
+            Ext::RegisteredExt(ext) => Extension::Registered {
+                id: ext.id,
+                msg: ext.msg,
+            },
+            Ext::MasterArbitration(ext) => Extension::MasterArbitration {
+                role: ext.role.map(|role| role.id),
+                election_id: ext
+                    .election_id
+                    .map(|id| (id.high as u128) << 64 | id.low as u128),
+            },
+            Ext::History(ext) => Extension::History(match ext.request {
+                Some(GnmiHistoryRequest::SnapshotTime(time)) => History::SnapshotTime(time),
+                Some(GnmiHistoryRequest::Range(range)) => History::Range {
+                    start: range.start,
+                    end: range.end,
+                },
+                None => {
+                    return Err(GinmiError::DecodeError(
+                        "history extension does not contain a time".to_string(),
+                    ))
+                }
+            }),
+            Ext::Commit(ext) => Extension::Commit {
+                id: ext.id,
+                action: match ext.action {
+                    Some(GnmiCommitAction::Commit(commit)) => Some(CommitAction::Commit {
+                        rollback_duration: commit.rollback_duration.map(duration).transpose()?,
+                    }),
+                    Some(GnmiCommitAction::Confirm(_)) => Some(CommitAction::Confirm),
+                    Some(GnmiCommitAction::Cancel(_)) => Some(CommitAction::Cancel),
+                    None => None,
+                },
+            },
+        })
+    }
+}
+
+fn duration(duration: GnmiDuration) -> Result<Duration, GinmiError> {
+    match (
+        u64::try_from(duration.seconds),
+        u32::try_from(duration.nanos),
+    ) {
+        (Ok(seconds), Ok(nanos)) => Ok(Duration::new(seconds, nanos)),
+        _ => Err(GinmiError::DecodeError(format!(
+            "negative duration of {}s {}ns",
+            duration.seconds, duration.nanos
+        ))),
+    }
+}
+
+/// Decode all extensions of a response.
+pub(crate) fn decode(extensions: Vec<GnmiExtension>) -> Result<Vec<Extension>, GinmiError> {
+    extensions.into_iter().map(Extension::try_from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions_round_trip() {
+        let extensions = vec![
+            Extension::History(History::Range { start: 1, end: 2 }),
+            Extension::MasterArbitration {
+                role: None,
+                election_id: Some(u128::MAX - 1),
+            },
+            Extension::Commit {
+                id: "change-42".to_string(),
+                action: Some(CommitAction::Commit {
+                    rollback_duration: Some(Duration::from_millis(1500)),
+                }),
+            },
+            Extension::Registered {
+                id: 999,
+                msg: vec![1, 2, 3],
+            },
+        ];
+
+        for extension in extensions {
+            let encoded = GnmiExtension::from(extension.clone());
+            assert_eq!(Extension::try_from(encoded).unwrap(), extension);
+        }
+    }
+
+    #[test]
+    fn empty_extension() {
+        assert!(Extension::try_from(GnmiExtension { ext: None }).is_err());
+    }
+}
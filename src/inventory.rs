@@ -0,0 +1,306 @@
+//! Operations on many target devices at once.
+//!
+//! The functions of this module connect to every target with the same settings and run
+//! the operations concurrently. A failure of one target does not affect the others, so
+//! the result of every target is returned individually.
+//!
+//! A [`ClientPool`] keeps the clients of many targets around instead, e.g. for collectors
+//! that talk to the same fleet of devices over and over.
+use crate::auth::AuthInterceptor;
+use crate::client::transport::TonicTransport;
+use crate::client::{Capabilities, Client, ClientBuilder};
+use crate::error::GinmiError;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OnceCell, Semaphore};
+use tokio::task::JoinSet;
+use tonic::codegen::InterceptedService;
+use tonic::transport::Channel;
+
+/// [`Client`] created by a [`ClientPool`].
+pub type PooledClient = Client<TonicTransport<InterceptedService<Channel, AuthInterceptor>>>;
+
+/// Clients of many targets that are created on first use and shared afterwards.
+///
+/// Every client is created with the settings of the [`ClientBuilder`] the pool was created
+/// with, except for the target URI. [`ClientPool::get`] returns a clone of the client of a
+/// target, which shares its connection, so it is cheap to call for every request. Clients
+/// that have not been requested for the [idle timeout](ClientPool::idle_timeout) are
+/// evicted from the pool, their connection is closed once all clones have been dropped.
+///
+/// # Examples
+/// ```rust
+/// # use ginmi::client::Client;
+/// # use ginmi::inventory::ClientPool;
+/// # use std::time::Duration;
+/// # tokio_test::block_on(async {
+/// # const CERT: &str = "CA Certificate";
+/// let builder = Client::builder("")
+///     .tls_ca_only(CERT)
+///     .credentials("admin", "admin");
+/// let pool = ClientPool::new(builder).idle_timeout(Duration::from_secs(300));
+///
+/// let mut client = pool.get("https://clab-srl01-srl:57400").await.unwrap();
+/// let capabilities = client.capabilities().await.unwrap();
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct ClientPool<'a> {
+    builder: ClientBuilder<'a>,
+    idle_timeout: Option<Duration>,
+    clients: Mutex<HashMap<String, Arc<PoolEntry>>>,
+}
+
+#[derive(Debug)]
+struct PoolEntry {
+    client: OnceCell<PooledClient>,
+    last_used: Mutex<Instant>,
+}
+
+impl<'a> ClientPool<'a> {
+    /// Create an empty pool whose clients are created with the settings of `builder`.
+    pub fn new(builder: ClientBuilder<'a>) -> Self {
+        Self {
+            builder,
+            idle_timeout: None,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Evict clients that have not been requested for `timeout`. By default, clients are
+    /// kept until they are [removed](ClientPool::remove).
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the client of `target`, connecting to it if the pool does not contain a
+    /// client for it yet.
+    ///
+    /// Concurrent calls for the same target share one connection attempt, while targets
+    /// are connected to independently. A failed attempt is not cached, so the next call
+    /// tries to connect again.
+    ///
+    /// # Errors
+    /// - Returns the errors of [`ClientBuilder::build`] if the client can not be created.
+    pub async fn get(&self, target: &str) -> Result<PooledClient, GinmiError> {
+        let entry = {
+            let mut clients = self.lock();
+            self.evict_idle_from(&mut clients);
+            let entry = clients.entry(target.to_string()).or_insert_with(|| {
+                Arc::new(PoolEntry {
+                    client: OnceCell::new(),
+                    last_used: Mutex::new(Instant::now()),
+                })
+            });
+            Arc::clone(entry)
+        };
+        *entry
+            .last_used
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Instant::now();
+
+        let client = entry
+            .client
+            .get_or_try_init(|| {
+                let mut builder = self.builder.clone();
+                builder.target = target.to_string().into();
+                builder.build()
+            })
+            .await;
+
+        match client {
+            Ok(client) => Ok(client.clone()),
+            Err(e) => {
+                // do not keep entries of targets that can not be connected to
+                let mut clients = self.lock();
+                let failed = clients.get(target).is_some_and(|current| {
+                    Arc::ptr_eq(current, &entry) && !entry.client.initialized()
+                });
+                if failed {
+                    clients.remove(target);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Remove the client of `target` from the pool. Returns whether the pool contained it.
+    pub fn remove(&self, target: &str) -> bool {
+        self.lock().remove(target).is_some()
+    }
+
+    /// Evict all clients that exceeded the idle timeout and return how many were evicted.
+    ///
+    /// Idle clients are also evicted by [`ClientPool::get`], call this periodically to
+    /// release them if the pool is not used for a while.
+    pub fn evict_idle(&self) -> usize {
+        self.evict_idle_from(&mut self.lock())
+    }
+
+    /// Number of targets the pool holds a client for.
+    pub fn len(&self) -> usize {
+        self.lock()
+            .values()
+            .filter(|entry| entry.client.initialized())
+            .count()
+    }
+
+    /// Returns `true` if the pool does not hold any client.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn evict_idle_from(&self, clients: &mut HashMap<String, Arc<PoolEntry>>) -> usize {
+        let timeout = match self.idle_timeout {
+            Some(timeout) => timeout,
+            None => return 0,
+        };
+
+        let before = clients.len();
+        clients.retain(|_, entry| {
+            let last_used = *entry
+                .last_used
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            last_used.elapsed() < timeout
+        });
+        before - clients.len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<PoolEntry>>> {
+        self.clients
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Query the capabilities of all `targets`, connecting to at most `concurrency` targets at
+/// the same time.
+///
+/// Every target is connected to with the settings of `builder`, except for the target URI.
+/// The results are returned in the order of `targets`.
+///
+/// # Examples
+/// ```rust
+/// # use ginmi::client::Client;
+/// # tokio_test::block_on(async {
+/// # const CERT: &str = "CA Certificate";
+/// let builder = Client::builder("")
+///     .tls(CERT, "clab-srl01-srl")
+///     .credentials("admin", "admin");
+///
+/// let targets = ["https://clab-srl01-srl:57400", "https://clab-srl02-srl:57400"];
+/// for (target, capabilities) in ginmi::inventory::capabilities(builder, &targets, 8).await {
+///     match capabilities {
+///         Ok(capabilities) => println!("{}: gNMI {}", target, capabilities.gnmi_version()),
+///         Err(e) => println!("{}: {}", target, e),
+///     }
+/// }
+/// # });
+/// ```
+pub async fn capabilities<'a>(
+    builder: ClientBuilder<'a>,
+    targets: &[&'a str],
+    concurrency: usize,
+) -> Vec<(&'a str, Result<Capabilities, GinmiError>)> {
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut queries = JoinSet::new();
+    for (index, target) in targets.iter().enumerate() {
+        let mut builder = builder.clone();
+        builder.target = (*target).into();
+        let connecting = builder.connecting();
+        let permits = Arc::clone(&permits);
+        queries.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let capabilities = async { connecting.await?.capabilities().await }.await;
+            (index, capabilities)
+        });
+    }
+
+    let mut results = Vec::new();
+    while let Some(result) = queries.join_next().await {
+        match result {
+            Ok(result) => results.push(result),
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+    results.sort_by_key(|(index, _)| *index);
+
+    results
+        .into_iter()
+        .map(|(index, capabilities)| (targets[index], capabilities))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn failures_are_reported_per_target() {
+        let builder = Client::builder("").credentials("admin", "admin");
+        let targets = ["http://[::1", "$ invalid"];
+
+        let results = capabilities(builder, &targets, 4).await;
+        assert_eq!(results.len(), 2);
+        for ((target, result), expected) in results.into_iter().zip(targets) {
+            assert_eq!(target, expected);
+            assert!(matches!(result, Err(GinmiError::InvalidUriError(_))));
+        }
+    }
+
+    /// Accept connections on a local port until the test ends, returning its URI.
+    async fn listen() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((connection, _)) = listener.accept().await {
+                connections.push(connection);
+            }
+        });
+        target
+    }
+
+    #[tokio::test]
+    async fn pool_reuses_clients() {
+        let (first, second) = (listen().await, listen().await);
+        let pool = ClientPool::new(Client::builder(""));
+        assert!(pool.is_empty());
+
+        pool.get(&first).await.unwrap();
+        pool.get(&first).await.unwrap();
+        assert_eq!(pool.len(), 1);
+        pool.get(&second).await.unwrap();
+        assert_eq!(pool.len(), 2);
+
+        assert!(pool.remove(&first));
+        assert!(!pool.remove(&first));
+        assert_eq!(pool.len(), 1);
+
+        assert!(matches!(
+            pool.get("$ invalid").await,
+            Err(GinmiError::InvalidUriError(_))
+        ));
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn pool_evicts_idle_clients() {
+        let (first, second) = (listen().await, listen().await);
+        let pool = ClientPool::new(Client::builder("")).idle_timeout(Duration::from_millis(50));
+
+        pool.get(&first).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        pool.get(&second).await.unwrap();
+        assert_eq!(pool.len(), 1);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(pool.evict_idle(), 1);
+        assert!(pool.is_empty());
+    }
+}
@@ -4,10 +4,27 @@
 //! as well as various telemetry data.
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
+// GinmiError wraps tonic::Status, which is large, but boxing it would make matching on errors clumsy.
+#![allow(clippy::result_large_err)]
 mod auth;
 pub mod client;
+#[cfg(feature = "dialout")]
+#[cfg_attr(docsrs, doc(cfg(feature = "dialout")))]
+pub mod dialout;
 pub mod error;
+pub mod extension;
+pub mod inventory;
+pub mod notification;
+pub mod path;
+#[cfg(feature = "raw")]
+#[cfg_attr(docsrs, doc(cfg(feature = "raw")))]
+pub mod raw;
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+mod serialize;
+pub mod value;
 
+#[allow(dead_code, clippy::enum_variant_names)]
 pub(crate) mod gen {
     pub mod gnmi {
         tonic::include_proto!("gnmi");
@@ -17,6 +34,11 @@ pub(crate) mod gen {
         tonic::include_proto!("gnmi_ext");
     }
 
+    #[cfg(feature = "dialout")]
+    pub mod gnmi_dialout {
+        tonic::include_proto!("gnmi_dialout");
+    }
+
     pub mod target {
         tonic::include_proto!("target");
     }
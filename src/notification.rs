@@ -0,0 +1,647 @@
+//! Notifications about the state of data elements on a target device.
+//!
+//! Notifications are returned by subscriptions and get requests and contain the updated and deleted
+//! data elements of a target device at a given point in time, as described in the
+//! [gNMI Specification Section 2.1](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-specification.md#21-reusable-notification-message-format).
+use crate::error::{self, GinmiError};
+use crate::extension::{self, Extension};
+use crate::gen::gnmi::GetResponse as GnmiGetResponse;
+use crate::gen::gnmi::Notification as GnmiNotification;
+use crate::gen::gnmi::Update as GnmiUpdate;
+use crate::path::Path;
+use crate::value::TypedValue;
+use prost::Message;
+use std::collections::{BTreeMap, HashMap};
+
+/// A set of updates and deletes of data elements that share a timestamp.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// Time of the notification in nanoseconds since the Unix epoch.
+    pub timestamp: i64,
+    /// Prefix of all paths contained in the notification.
+    pub prefix: Option<Path>,
+    /// Data elements that have changed their value.
+    pub updates: Vec<Update>,
+    /// Data elements that have been deleted.
+    pub deletes: Vec<Path>,
+    /// Whether all updates and deletes of the notification belong to one transaction.
+    pub atomic: bool,
+}
+
+/// The value of a single data element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Update {
+    /// Path of the data element, relative to the prefix of the [`Notification`]. See
+    /// [`Notification::absolute_updates`] for the absolute paths.
+    pub path: Path,
+    /// Value of the data element.
+    pub value: TypedValue,
+    /// Number of updates that were coalesced into this one by the target device.
+    pub duplicates: u32,
+}
+
+/// The [`Notification`]s returned by a get request.
+///
+/// Obtained via [`GetRequestBuilder::send`](crate::client::GetRequestBuilder::send).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Notifications {
+    notifications: Vec<Notification>,
+    extensions: Vec<Extension>,
+}
+
+impl Notification {
+    /// The updates of the notification with their absolute path, i.e. with the prefix of the
+    /// notification prepended to the path of each update.
+    pub fn absolute_updates(&self) -> Vec<(Path, TypedValue)> {
+        self.updates
+            .iter()
+            .map(|update| (self.absolute(&update.path), update.value.clone()))
+            .collect()
+    }
+
+    /// The effective origin of `path`, one of the paths of the notification: the origin of
+    /// the prefix, or the origin of `path` itself if the prefix has none, for target devices
+    /// that set it on the paths instead. The paths returned by
+    /// [`Notification::absolute_updates`] carry the same origin.
+    pub fn origin_of<'a>(&'a self, path: &'a Path) -> Option<&'a str> {
+        self.prefix
+            .as_ref()
+            .and_then(Path::origin)
+            .or_else(|| path.origin())
+    }
+
+    /// Prepend the prefix of the notification to `path`.
+    fn absolute(&self, path: &Path) -> Path {
+        match &self.prefix {
+            Some(prefix) => path.clone().with_prefix(prefix),
+            None => path.clone(),
+        }
+    }
+}
+
+impl Notifications {
+    /// Iterate over the contained [`Notification`]s.
+    pub fn iter(&self) -> std::slice::Iter<'_, Notification> {
+        self.notifications.iter()
+    }
+
+    /// Extensions the target device attached to the response.
+    pub fn extensions(&self) -> &[Extension] {
+        &self.extensions
+    }
+
+    /// Iterate over all updates as pairs of their absolute path and their value, both
+    /// formatted as human-readable strings.
+    pub fn rows(&self) -> impl Iterator<Item = (String, String)> + '_ {
+        self.updates()
+            .map(|(path, update)| (path.to_string(), update.value.to_string()))
+    }
+
+    /// Returns the absolute paths of all updates, i.e. every data element contained in the
+    /// response, in the order they were returned.
+    pub fn paths(&self) -> Vec<Path> {
+        self.updates().map(|(path, _)| path).collect()
+    }
+
+    /// Returns the number of updates contained in all notifications.
+    pub fn update_count(&self) -> usize {
+        self.notifications.iter().map(|n| n.updates.len()).sum()
+    }
+
+    /// Returns the approximate size of the response in bytes, as encoded by the target
+    /// device.
+    ///
+    /// The size is calculated by encoding the contained notifications again, so it differs
+    /// from the received size if the target device used deprecated fields.
+    pub fn encoded_len(&self) -> usize {
+        GnmiGetResponse {
+            notification: self.notifications.iter().cloned().map(Into::into).collect(),
+            extension: self.extensions.iter().cloned().map(Into::into).collect(),
+            ..Default::default()
+        }
+        .encoded_len()
+    }
+
+    /// Returns only the updates of configuration data, dropping operational state.
+    ///
+    /// Configuration and state are told apart by the OpenConfig convention of placing them in
+    /// `config` and `state` containers, so all updates below a `state` container are dropped.
+    /// If the response contains neither, e.g. because the models of the target device do not
+    /// follow this convention, all updates are returned unchanged and a warning is logged.
+    /// Request [`DataType::Config`](crate::client::DataType::Config) to have the target device
+    /// do the filtering instead.
+    pub fn config_only(&self) -> Notifications {
+        let tagged = self.updates().any(|(path, _)| {
+            path.elements()
+                .iter()
+                .any(|e| matches!(e.local_name(), "config" | "state"))
+        });
+        if !tagged {
+            tracing::warn!(
+                "response does not tell configuration and state apart, returning all updates"
+            );
+            return self.clone();
+        }
+
+        let is_state = |notification: &Notification, update: &Update| {
+            let prefix = notification.prefix.iter().flat_map(|p| p.elements());
+            prefix
+                .chain(update.path.elements())
+                .any(|e| e.local_name() == "state")
+        };
+
+        Notifications {
+            notifications: self
+                .notifications
+                .iter()
+                .map(|notification| Notification {
+                    updates: notification
+                        .updates
+                        .iter()
+                        .filter(|update| !is_state(notification, update))
+                        .cloned()
+                        .collect(),
+                    ..notification.clone()
+                })
+                .collect(),
+            extensions: self.extensions.clone(),
+        }
+    }
+
+    /// Merge these notifications with `others` into a single snapshot, e.g. to assemble the
+    /// full configuration from several partial get requests.
+    ///
+    /// Every data element is contained only once, with the value of its newest update. If
+    /// updates of the same data element share the timestamp, the last one wins. Deletes
+    /// remove all older updates of the deleted data elements and their children and are not
+    /// part of the snapshot.
+    ///
+    /// The merged notifications carry absolute paths and are ordered by timestamp, so
+    /// there is one notification per distinct timestamp. The extensions of all responses are
+    /// kept.
+    pub fn merge(self, others: impl IntoIterator<Item = Notifications>) -> Notifications {
+        let mut all = self;
+        for other in others {
+            all.append(other);
+        }
+
+        let mut latest: Vec<(i64, Update)> = Vec::new();
+        let mut positions: HashMap<Path, usize> = HashMap::new();
+        let mut deletes: Vec<(i64, Path)> = Vec::new();
+        for notification in &all.notifications {
+            let timestamp = notification.timestamp;
+            for delete in &notification.deletes {
+                deletes.push((timestamp, notification.absolute(delete)));
+            }
+
+            for update in &notification.updates {
+                let update = Update {
+                    path: notification.absolute(&update.path),
+                    ..update.clone()
+                };
+                match positions.get(&update.path) {
+                    Some(&idx) if latest[idx].0 > timestamp => {}
+                    Some(&idx) => latest[idx] = (timestamp, update),
+                    None => {
+                        positions.insert(update.path.clone(), latest.len());
+                        latest.push((timestamp, update));
+                    }
+                }
+            }
+        }
+
+        let mut snapshot: BTreeMap<i64, Vec<Update>> = BTreeMap::new();
+        for (timestamp, update) in latest {
+            let deleted = deletes.iter().any(|(deleted_at, path)| {
+                *deleted_at > timestamp && update.path.strip_prefix(path).is_some()
+            });
+            if !deleted {
+                snapshot.entry(timestamp).or_default().push(update);
+            }
+        }
+
+        Notifications {
+            notifications: snapshot
+                .into_iter()
+                .map(|(timestamp, updates)| Notification {
+                    timestamp,
+                    prefix: None,
+                    updates,
+                    deletes: Vec::new(),
+                    atomic: false,
+                })
+                .collect(),
+            extensions: all.extensions,
+        }
+    }
+
+    /// Move all notifications and extensions of `other` to the end of these.
+    pub(crate) fn append(&mut self, mut other: Notifications) {
+        self.notifications.append(&mut other.notifications);
+        self.extensions.append(&mut other.extensions);
+    }
+
+    /// Iterate over all updates together with their absolute path.
+    fn updates(&self) -> impl Iterator<Item = (Path, &Update)> + '_ {
+        self.notifications.iter().flat_map(|notification| {
+            notification
+                .updates
+                .iter()
+                .map(move |update| (notification.absolute(&update.path), update))
+        })
+    }
+
+    /// Format all updates as a table with one row per update, listing the absolute path and
+    /// the value of the data element.
+    ///
+    /// # Examples
+    /// ```text
+    /// /interfaces/interface[name=eth0]/state/oper-status  UP
+    /// /interfaces/interface[name=eth0]/state/mtu          1500
+    /// ```
+    pub fn to_table(&self) -> String {
+        let rows: Vec<_> = self.rows().collect();
+        let width = rows.iter().map(|(path, _)| path.len()).max().unwrap_or(0);
+
+        rows.iter()
+            .map(|(path, value)| format!("{:<width$}  {}\n", path, value, width = width))
+            .collect()
+    }
+}
+
+impl From<Vec<Notification>> for Notifications {
+    fn from(notifications: Vec<Notification>) -> Self {
+        Notifications {
+            notifications,
+            extensions: Vec::new(),
+        }
+    }
+}
+
+impl IntoIterator for Notifications {
+    type Item = Notification;
+    type IntoIter = std::vec::IntoIter<Notification>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.notifications.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Notifications {
+    type Item = &'a Notification;
+    type IntoIter = std::slice::Iter<'a, Notification>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.notifications.iter()
+    }
+}
+
+impl TryFrom<GnmiGetResponse> for Notifications {
+    type Error = GinmiError;
+
+    #[allow(deprecated)]
+    fn try_from(response: GnmiGetResponse) -> Result<Self, Self::Error> {
+        error::check_embedded(response.error)?;
+
+        Ok(Notifications {
+            notifications: response
+                .notification
+                .into_iter()
+                .map(Notification::try_from)
+                .collect::<Result<_, _>>()?,
+            extensions: extension::decode(response.extension)?,
+        })
+    }
+}
+
+impl TryFrom<GnmiNotification> for Notification {
+    type Error = GinmiError;
+
+    fn try_from(notification: GnmiNotification) -> Result<Self, Self::Error> {
+        Ok(Notification {
+            timestamp: notification.timestamp,
+            prefix: notification.prefix.map(Path::from),
+            updates: notification
+                .update
+                .into_iter()
+                .map(Update::try_from)
+                .collect::<Result<_, _>>()?,
+            deletes: notification.delete.into_iter().map(Path::from).collect(),
+            atomic: notification.atomic,
+        })
+    }
+}
+
+impl From<Notification> for GnmiNotification {
+    fn from(notification: Notification) -> Self {
+        GnmiNotification {
+            timestamp: notification.timestamp,
+            prefix: notification.prefix.map(Into::into),
+            update: notification.updates.into_iter().map(Into::into).collect(),
+            delete: notification.deletes.into_iter().map(Into::into).collect(),
+            atomic: notification.atomic,
+        }
+    }
+}
+
+impl From<Update> for GnmiUpdate {
+    fn from(update: Update) -> Self {
+        GnmiUpdate {
+            path: Some(update.path.into()),
+            val: Some(update.value.into()),
+            duplicates: update.duplicates,
+            ..Default::default()
+        }
+    }
+}
+
+impl TryFrom<GnmiUpdate> for Update {
+    type Error = GinmiError;
+
+    #[allow(deprecated)]
+    fn try_from(update: GnmiUpdate) -> Result<Self, Self::Error> {
+        // older targets may still send the deprecated value field instead of val
+        let value = match (update.val, update.value) {
+            (Some(val), _) => TypedValue::try_from(val)?,
+            (None, Some(value)) => TypedValue::try_from(value)?,
+            (None, None) => {
+                return Err(GinmiError::DecodeError(
+                    "update does not contain a value".to_string(),
+                ))
+            }
+        };
+
+        Ok(Update {
+            path: update.path.map(Path::from).unwrap_or_default(),
+            value,
+            duplicates: update.duplicates,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::gnmi::typed_value::Value as GnmiValueKind;
+    use crate::gen::gnmi::{
+        Encoding, Notification as GnmiNotification, TypedValue as GnmiTypedValue,
+        Value as GnmiValue,
+    };
+
+    #[test]
+    #[allow(deprecated)]
+    fn decode_returned_encoding() {
+        // the target answers a request for JSON_IETF with other encodings
+        let update = |val, value| GnmiUpdate {
+            path: Some(Path::try_parse("/system/name").unwrap().into()),
+            val,
+            value,
+            ..Default::default()
+        };
+        let response = GnmiGetResponse {
+            notification: vec![GnmiNotification {
+                update: vec![
+                    update(
+                        Some(GnmiTypedValue {
+                            value: Some(GnmiValueKind::ProtoBytes(vec![0x08, 0x01])),
+                        }),
+                        None,
+                    ),
+                    update(
+                        Some(GnmiTypedValue {
+                            value: Some(GnmiValueKind::JsonVal(b"\"srl\"".to_vec())),
+                        }),
+                        None,
+                    ),
+                    update(
+                        None,
+                        Some(GnmiValue {
+                            value: b"srl".to_vec(),
+                            r#type: Encoding::Ascii as i32,
+                        }),
+                    ),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let notifications = Notifications::try_from(response).unwrap();
+        let values: Vec<_> = notifications
+            .iter()
+            .flat_map(|n| &n.updates)
+            .map(|u| &u.value)
+            .collect();
+        assert_eq!(
+            values,
+            [
+                &TypedValue::ProtoBytes(vec![0x08, 0x01]),
+                &TypedValue::Json(b"\"srl\"".to_vec()),
+                &TypedValue::Ascii("srl".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn absolute_update_paths() {
+        let update = |path: &str| Update {
+            path: Path::try_parse(path).unwrap(),
+            value: TypedValue::Uint(1500),
+            duplicates: 0,
+        };
+        let mut notification = Notification {
+            timestamp: 0,
+            prefix: Some(Path::try_parse("/interfaces/interface[name=eth0]").unwrap()),
+            updates: vec![update("state/mtu")],
+            deletes: Vec::new(),
+            atomic: false,
+        };
+
+        assert_eq!(
+            notification.absolute_updates(),
+            [(
+                Path::try_parse("/interfaces/interface[name=eth0]/state/mtu").unwrap(),
+                TypedValue::Uint(1500)
+            )]
+        );
+
+        notification.prefix = None;
+        assert_eq!(
+            notification.absolute_updates(),
+            [(
+                Path::try_parse("state/mtu").unwrap(),
+                TypedValue::Uint(1500)
+            )]
+        );
+    }
+
+    #[test]
+    fn effective_origin() {
+        let path = Path::try_parse("cli:/show").unwrap();
+        let mut notification = Notification {
+            timestamp: 0,
+            prefix: Some(Path::new().with_origin("openconfig")),
+            updates: Vec::new(),
+            deletes: Vec::new(),
+            atomic: false,
+        };
+
+        // the origin of the prefix takes precedence over that of the path
+        assert_eq!(notification.origin_of(&path), Some("openconfig"));
+        notification.prefix = Some(Path::new().with_target("router1"));
+        assert_eq!(notification.origin_of(&path), Some("cli"));
+        assert_eq!(notification.origin_of(&Path::new()), None);
+    }
+
+    #[test]
+    fn notifications_as_table() {
+        let update = |path: &str, value| Update {
+            path: Path::try_parse(path).unwrap(),
+            value,
+            duplicates: 0,
+        };
+        let notifications = Notifications::from(vec![Notification {
+            timestamp: 0,
+            prefix: Some(Path::try_parse("/interfaces/interface[name=eth0]/state").unwrap()),
+            updates: vec![
+                update("oper-status", TypedValue::String("UP".to_string())),
+                update("counters/in-octets", TypedValue::Uint(1500)),
+            ],
+            deletes: Vec::new(),
+            atomic: false,
+        }]);
+
+        assert_eq!(
+            notifications.paths()[1],
+            Path::try_parse("/interfaces/interface[name=eth0]/state/counters/in-octets").unwrap()
+        );
+        assert_eq!(
+            notifications.to_table(),
+            "/interfaces/interface[name=eth0]/state/oper-status         UP\n\
+             /interfaces/interface[name=eth0]/state/counters/in-octets  1500\n"
+        );
+    }
+
+    #[test]
+    fn merge_keeps_newest_values() {
+        let notification = |timestamp, updates: &[(&str, u64)], deletes: &[&str]| Notification {
+            timestamp,
+            prefix: Some(Path::try_parse("/interfaces").unwrap()),
+            updates: updates
+                .iter()
+                .map(|(path, value)| Update {
+                    path: Path::try_parse(path).unwrap(),
+                    value: TypedValue::Uint(*value),
+                    duplicates: 0,
+                })
+                .collect(),
+            deletes: deletes
+                .iter()
+                .map(|p| Path::try_parse(p).unwrap())
+                .collect(),
+            atomic: false,
+        };
+
+        let first = Notifications::from(vec![notification(
+            20,
+            &[
+                ("interface[name=eth0]/mtu", 1500),
+                ("interface[name=eth1]/mtu", 1500),
+                ("interface[name=eth2]/mtu", 1500),
+            ],
+            &[],
+        )]);
+        let second = Notifications::from(vec![
+            notification(10, &[("interface[name=eth0]/mtu", 9000)], &[]),
+            notification(30, &[("interface[name=eth1]/mtu", 9000)], &[]),
+            notification(30, &[], &["interface[name=eth2]"]),
+        ]);
+
+        let merged = first.merge([second]);
+        let values: Vec<_> = merged
+            .iter()
+            .flat_map(|n| n.updates.iter().map(move |u| (n.timestamp, u)))
+            .map(|(timestamp, u)| (timestamp, u.path.to_string(), u.value.clone()))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                (
+                    20,
+                    "/interfaces/interface[name=eth0]/mtu".to_string(),
+                    TypedValue::Uint(1500)
+                ),
+                (
+                    30,
+                    "/interfaces/interface[name=eth1]/mtu".to_string(),
+                    TypedValue::Uint(9000)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn count_and_size() {
+        let notification = Notification {
+            timestamp: 1_700_000_000_000_000_000,
+            prefix: Some(Path::try_parse("/system").unwrap()),
+            updates: vec![
+                Update {
+                    path: Path::try_parse("name").unwrap(),
+                    value: TypedValue::String("router1".to_string()),
+                    duplicates: 0,
+                },
+                Update {
+                    path: Path::try_parse("uptime").unwrap(),
+                    value: TypedValue::Uint(3600),
+                    duplicates: 0,
+                },
+            ],
+            deletes: vec![Path::try_parse("motd").unwrap()],
+            atomic: false,
+        };
+        let response = GnmiGetResponse {
+            notification: vec![notification.clone().into(), notification.into()],
+            ..Default::default()
+        };
+        let size = response.encoded_len();
+
+        let notifications = Notifications::try_from(response).unwrap();
+        assert_eq!(notifications.update_count(), 4);
+        assert_eq!(notifications.encoded_len(), size);
+    }
+
+    #[test]
+    fn config_only() {
+        let update = |path: &str| Update {
+            path: Path::try_parse(path).unwrap(),
+            value: TypedValue::Uint(1500),
+            duplicates: 0,
+        };
+        let notification = |prefix: &str, paths: &[&str]| Notification {
+            timestamp: 0,
+            prefix: Some(Path::try_parse(prefix).unwrap()),
+            updates: paths.iter().map(|p| update(p)).collect(),
+            deletes: Vec::new(),
+            atomic: false,
+        };
+
+        let notifications = Notifications::from(vec![
+            notification(
+                "/interfaces/interface[name=eth0]",
+                &["config/mtu", "state/mtu", "state/counters/in-octets"],
+            ),
+            notification("/interfaces/interface[name=eth0]/state", &["oper-status"]),
+        ]);
+        let paths: Vec<String> = notifications
+            .config_only()
+            .paths()
+            .iter()
+            .map(Path::to_string)
+            .collect();
+        assert_eq!(paths, ["/interfaces/interface[name=eth0]/config/mtu"]);
+
+        let untagged = Notifications::from(vec![notification("/system", &["hostname", "uptime"])]);
+        assert_eq!(untagged.config_only(), untagged);
+    }
+}
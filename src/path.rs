@@ -0,0 +1,711 @@
+//! Paths addressing data elements on a target device.
+//!
+//! A [`Path`] consists of an optional origin, an optional target and an ordered list of
+//! [`PathElement`]s as described in the [gNMI Path Conventions](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-path-conventions.md).
+//!
+//! # Examples
+//! Parsing a path from its string representation:
+//! ```rust
+//! # use ginmi::path::Path;
+//! let path: Path = "/interfaces/interface[name=ethernet-1/1]/state".parse().unwrap();
+//! assert_eq!(path.elements().len(), 3);
+//! assert_eq!(path.elements()[1].key("name"), Some("ethernet-1/1"));
+//! ```
+//!
+//! # Escaping
+//! Names, keys and values are stored unescaped. When parsing and displaying paths, a `\`
+//! escapes the following character as described in the path conventions: `\]` and `\\`
+//! within values, additionally `\=` within keys, and `\/` and `\[` within names.
+//! ```rust
+//! # use ginmi::path::Path;
+//! let path: Path = r"/acl/entry[description=deny 10.0.0.0/8 \[legacy\]]".parse().unwrap();
+//! assert_eq!(path.elements()[1].key("description"), Some("deny 10.0.0.0/8 [legacy]"));
+//! assert_eq!(path.to_string(), r"/acl/entry[description=deny 10.0.0.0/8 [legacy\]]");
+//! ```
+use crate::error::GinmiError;
+use crate::gen::gnmi::Path as GnmiPath;
+use crate::gen::gnmi::PathElem;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A single element of a [`Path`], consisting of a name and optional keys.
+///
+/// The name may be qualified with the YANG module that defines it, e.g.
+/// `openconfig-interfaces:interfaces`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PathElement {
+    name: String,
+    keys: BTreeMap<String, String>,
+}
+
+impl PathElement {
+    /// Create a new [`PathElement`] without any keys.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            keys: BTreeMap::new(),
+        }
+    }
+
+    /// Add a key to the [`PathElement`], replacing any previous value of that key.
+    pub fn with_key(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.keys.insert(key.into(), value.into());
+        self
+    }
+
+    /// Name of the element, including the module qualifier if present.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Module qualifier of the element name, if present.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::path::PathElement;
+    /// let element = PathElement::new("openconfig-interfaces:interfaces");
+    /// assert_eq!(element.module(), Some("openconfig-interfaces"));
+    /// assert_eq!(element.local_name(), "interfaces");
+    /// ```
+    pub fn module(&self) -> Option<&str> {
+        self.name.split_once(':').map(|(module, _)| module)
+    }
+
+    /// Name of the element without the module qualifier.
+    pub fn local_name(&self) -> &str {
+        match self.name.split_once(':') {
+            Some((_, name)) => name,
+            None => &self.name,
+        }
+    }
+
+    /// All keys of the element, ordered by key name.
+    pub fn keys(&self) -> &BTreeMap<String, String> {
+        &self.keys
+    }
+
+    /// Value of the given key, if present.
+    pub fn key(&self, key: &str) -> Option<&str> {
+        self.keys.get(key).map(String::as_str)
+    }
+
+    /// Returns `true` if the name is `*` or `...`, or any key has the value `*`.
+    pub fn is_wildcard(&self) -> bool {
+        matches!(self.name.as_str(), "*" | "...") || self.keys.values().any(|v| v == "*")
+    }
+}
+
+impl fmt::Display for PathElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", escape(&self.name, &['/', '['][..]))?;
+        for (key, value) in &self.keys {
+            write!(
+                f,
+                "[{}={}]",
+                escape(key, &['=', ']'][..]),
+                escape(value, &[']'][..])
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Path to a data element on a target device.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Path {
+    origin: String,
+    target: String,
+    elements: Vec<PathElement>,
+}
+
+impl Path {
+    /// Create an empty [`Path`] that refers to the root of the data tree.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a [`Path`] from a string as typically passed on a command line.
+    ///
+    /// The leading `/` is optional, so `system/name` and `/system/name` both result in a path
+    /// with the two elements `system` and `name`. See the [`FromStr`] implementation for how
+    /// origins and module qualifiers are recognized.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::PathParseError`] if the string is not a valid path.
+    pub fn try_parse(s: &str) -> Result<Self, GinmiError> {
+        s.parse()
+    }
+
+    /// Set the origin of the [`Path`], e.g. `openconfig` or `cli`.
+    pub fn with_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origin = origin.into();
+        self
+    }
+
+    /// Set the target of the [`Path`].
+    pub fn with_target(mut self, target: impl Into<String>) -> Self {
+        self.target = target.into();
+        self
+    }
+
+    /// Origin of the [`Path`], if set.
+    pub fn origin(&self) -> Option<&str> {
+        (!self.origin.is_empty()).then_some(self.origin.as_str())
+    }
+
+    /// Target of the [`Path`], if set.
+    pub fn target(&self) -> Option<&str> {
+        (!self.target.is_empty()).then_some(self.target.as_str())
+    }
+
+    /// Elements of the [`Path`], starting at the root.
+    pub fn elements(&self) -> &[PathElement] {
+        &self.elements
+    }
+
+    /// Returns `true` if any element of the [`Path`] is a wildcard, so the path may match
+    /// multiple data elements. Wildcards are not permitted in set requests.
+    pub fn is_wildcard(&self) -> bool {
+        self.elements.iter().any(PathElement::is_wildcard)
+    }
+
+    /// Guess whether the [`Path`] refers to a single leaf rather than a container or list.
+    ///
+    /// Without the schema this can only be a guess: the path is assumed to refer to a leaf
+    /// if it is not empty, contains no wildcards and its last element has no keys. A path to
+    /// a container therefore also counts as a leaf.
+    pub fn is_leaf_guess(&self) -> bool {
+        !self.is_wildcard() && self.elements.last().is_some_and(|e| e.keys.is_empty())
+    }
+
+    /// Append an element to the end of the [`Path`].
+    pub fn push(&mut self, element: PathElement) {
+        self.elements.push(element);
+    }
+
+    /// Returns a new [`Path`] with the elements of `relative` appended to the elements of
+    /// this path.
+    ///
+    /// The origin and target are taken from this path, unless they are set on `relative`.
+    pub fn join(&self, relative: &Path) -> Path {
+        let mut elements = self.elements.clone();
+        elements.extend(relative.elements.iter().cloned());
+
+        Path {
+            origin: relative.origin().unwrap_or(&self.origin).to_string(),
+            target: relative.target().unwrap_or(&self.target).to_string(),
+            elements,
+        }
+    }
+
+    /// Returns the elements of the [`Path`] that follow the elements of `prefix`, or `None` if
+    /// the path does not start with `prefix`.
+    ///
+    /// The returned path is relative to `prefix`, so it has neither an origin nor a target.
+    pub fn strip_prefix(&self, prefix: &Path) -> Option<Path> {
+        let elements = self.elements.strip_prefix(prefix.elements.as_slice())?;
+        Some(Path {
+            elements: elements.to_vec(),
+            ..Default::default()
+        })
+    }
+
+    /// Prepend the elements of `prefix`, taking the origin and target from `prefix` if set.
+    pub(crate) fn with_prefix(self, prefix: &Path) -> Path {
+        let mut elements = prefix.elements.clone();
+        elements.extend(self.elements);
+
+        Path {
+            origin: prefix.origin().map(String::from).unwrap_or(self.origin),
+            target: prefix.target().map(String::from).unwrap_or(self.target),
+            elements,
+        }
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.origin.is_empty() {
+            write!(f, "{}:", self.origin)?;
+        }
+        if self.elements.is_empty() {
+            return write!(f, "/");
+        }
+        for element in &self.elements {
+            write!(f, "/{}", element)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Path {
+    type Err = GinmiError;
+
+    /// Parse a [`Path`] from its string representation.
+    ///
+    /// Colons are interpreted according to the following precedence rules:
+    /// 1. A colon that is directly followed by a `/` (or ends the string) and is not preceded
+    ///    by any `/` or `[` separates the origin from the path, e.g. `openconfig:/interfaces`.
+    /// 2. Any other colon within an element name qualifies the element with its YANG module,
+    ///    e.g. `openconfig-interfaces:interfaces/interface`. The qualifier is kept as part
+    ///    of the name and can be inspected with [`PathElement::module`].
+    /// 3. Colons within key predicates are part of the key or value, e.g. `[name=1:1]`.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::PathParseError`] if an element has no name, a key predicate is not
+    ///   terminated or a key predicate does not have the form `[key=value]`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (origin, rest) = split_origin(s.trim());
+
+        let rest = rest.strip_prefix('/').unwrap_or(rest);
+        let rest = rest.strip_suffix('/').unwrap_or(rest);
+
+        let mut path = Path::new().with_origin(origin);
+        if rest.is_empty() {
+            return Ok(path);
+        }
+
+        for segment in split_elements(rest).map_err(GinmiError::PathParseError)? {
+            path.push(parse_element(segment).map_err(GinmiError::PathParseError)?);
+        }
+
+        Ok(path)
+    }
+}
+
+impl From<PathElement> for PathElem {
+    fn from(element: PathElement) -> Self {
+        PathElem {
+            name: element.name,
+            key: element.keys.into_iter().collect(),
+        }
+    }
+}
+
+impl From<PathElem> for PathElement {
+    fn from(element: PathElem) -> Self {
+        PathElement {
+            name: element.name,
+            keys: element.key.into_iter().collect(),
+        }
+    }
+}
+
+impl From<Path> for GnmiPath {
+    fn from(path: Path) -> Self {
+        GnmiPath {
+            origin: path.origin,
+            target: path.target,
+            elem: path.elements.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<GnmiPath> for Path {
+    #[allow(deprecated)]
+    fn from(path: GnmiPath) -> Self {
+        // older targets may still use the deprecated element field instead of elem
+        let elements = if path.elem.is_empty() {
+            path.element.into_iter().map(PathElement::new).collect()
+        } else {
+            path.elem.into_iter().map(Into::into).collect()
+        };
+
+        Path {
+            origin: path.origin,
+            target: path.target,
+            elements,
+        }
+    }
+}
+
+/// Move the origins of the `paths` of a request to its `prefix`.
+///
+/// The [gNMI Specification Section 2.2.2.1](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-specification.md#2221-path-prefixes)
+/// only permits an origin on the prefix if a request has one. Origins of paths that match the
+/// origin of the prefix are removed. If the prefix has no origin, it takes the origin that all
+/// of the paths share. Requests without a prefix are left unchanged, as each path may then
+/// carry its own origin.
+///
+/// # Errors
+/// - Returns [`GinmiError::PathParseError`] if the origin of a path differs from the origin
+///   of the prefix, or if the prefix has no origin and the paths do not share one.
+pub(crate) fn origins_on_prefix(
+    prefix: Option<&mut GnmiPath>,
+    mut paths: Vec<&mut GnmiPath>,
+) -> Result<(), GinmiError> {
+    let prefix = match prefix {
+        Some(prefix) => prefix,
+        None => return Ok(()),
+    };
+
+    if prefix.origin.is_empty() {
+        if let Some(first) = paths.first() {
+            let origin = first.origin.clone();
+            if paths.iter().all(|path| path.origin == origin) {
+                prefix.origin = origin;
+            }
+        }
+    }
+
+    for path in paths.iter_mut() {
+        if path.origin == prefix.origin {
+            path.origin.clear();
+        } else if !path.origin.is_empty() {
+            return Err(GinmiError::PathParseError(format!(
+                "origin of path {} differs from the origin of prefix {}, only the prefix may \
+                 have an origin",
+                Path::from(path.clone()),
+                Path::from(prefix.clone())
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Split off the origin according to the first precedence rule of [`Path::from_str`].
+fn split_origin(s: &str) -> (&str, &str) {
+    if let Some(idx) = s.find(':') {
+        let (origin, rest) = (&s[..idx], &s[idx + 1..]);
+        let is_origin = !origin.contains(['/', '['])
+            && !origin.is_empty()
+            && (rest.is_empty() || rest.starts_with('/'));
+        if is_origin {
+            return (origin, rest);
+        }
+    }
+    ("", s)
+}
+
+/// Split a path into its elements at every `/` that is not part of a key predicate.
+fn split_elements(s: &str) -> Result<Vec<&str>, String> {
+    let mut segments = Vec::new();
+    let mut in_key = false;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (idx, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' if !in_key => in_key = true,
+            ']' if in_key => in_key = false,
+            '/' if !in_key => {
+                segments.push(&s[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+
+    if in_key {
+        return Err(format!("unterminated key predicate in path '{}'", s));
+    }
+
+    segments.push(&s[start..]);
+    Ok(segments)
+}
+
+/// Parse a single element of the form `name[key=value][key=value]`.
+fn parse_element(s: &str) -> Result<PathElement, String> {
+    let (name, mut predicates) = match find_unescaped(s, '[') {
+        Some(idx) => (&s[..idx], &s[idx..]),
+        None => (s, ""),
+    };
+
+    if name.is_empty() {
+        return Err(format!("path element '{}' has no name", s));
+    }
+
+    let mut element = PathElement::new(unescape(name));
+    while !predicates.is_empty() {
+        let end = match (
+            predicates.strip_prefix('['),
+            find_unescaped(predicates, ']'),
+        ) {
+            (Some(_), Some(end)) => end,
+            _ => {
+                return Err(format!(
+                    "invalid key predicate '{}' in path element '{}'",
+                    predicates, s
+                ))
+            }
+        };
+
+        let predicate = &predicates[1..end];
+        match find_unescaped(predicate, '=') {
+            Some(idx) if idx > 0 => {
+                element =
+                    element.with_key(unescape(&predicate[..idx]), unescape(&predicate[idx + 1..]));
+            }
+            _ => {
+                return Err(format!(
+                    "key predicate '{}' in path element '{}' is not of the form [key=value]",
+                    &predicates[..=end],
+                    s
+                ))
+            }
+        }
+        predicates = &predicates[end + 1..];
+    }
+
+    Ok(element)
+}
+
+/// Byte index of the first occurrence of `c` in `s` that is not escaped with a `\`.
+fn find_unescaped(s: &str, c: char) -> Option<usize> {
+    let mut escaped = false;
+    for (idx, current) in s.char_indices() {
+        match current {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if current == c => return Some(idx),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Escape `\` and all `special` characters of `s` with a `\`.
+fn escape<'a>(s: &'a str, special: &[char]) -> Cow<'a, str> {
+    if !s.contains(|c| c == '\\' || special.contains(&c)) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut escaped = String::with_capacity(s.len() + 2);
+    for c in s.chars() {
+        if c == '\\' || special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    Cow::Owned(escaped)
+}
+
+/// Remove the `\` of all escaped characters of `s`.
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_simple_path() {
+        let path: Path = "/interfaces/interface[name=ethernet-1/1]/state"
+            .parse()
+            .unwrap();
+
+        assert_eq!(path.origin(), None);
+        assert_eq!(path.elements().len(), 3);
+        assert_eq!(path.elements()[1].name(), "interface");
+        assert_eq!(path.elements()[1].key("name"), Some("ethernet-1/1"));
+        assert_eq!(
+            path.to_string(),
+            "/interfaces/interface[name=ethernet-1/1]/state"
+        );
+    }
+
+    #[test]
+    fn parse_origin() {
+        let path: Path = "openconfig:/interfaces".parse().unwrap();
+        assert_eq!(path.origin(), Some("openconfig"));
+        assert_eq!(path.elements()[0].name(), "interfaces");
+
+        let path: Path = "cli:".parse().unwrap();
+        assert_eq!(path.origin(), Some("cli"));
+        assert!(path.elements().is_empty());
+    }
+
+    #[test]
+    fn parse_module_qualified_path() {
+        let path: Path = "openconfig-interfaces:interfaces/interface/state"
+            .parse()
+            .unwrap();
+
+        assert_eq!(path.origin(), None);
+        assert_eq!(path.elements().len(), 3);
+        assert_eq!(path.elements()[0].module(), Some("openconfig-interfaces"));
+        assert_eq!(path.elements()[0].local_name(), "interfaces");
+    }
+
+    #[test]
+    fn parse_module_qualified_path_with_origin() {
+        let path: Path = "openconfig:/openconfig-interfaces:interfaces/oc-if:interface[name=1:1]"
+            .parse()
+            .unwrap();
+
+        assert_eq!(path.origin(), Some("openconfig"));
+        assert_eq!(path.elements()[0].module(), Some("openconfig-interfaces"));
+        assert_eq!(path.elements()[1].module(), Some("oc-if"));
+        assert_eq!(path.elements()[1].key("name"), Some("1:1"));
+    }
+
+    #[test]
+    fn parse_relative_path() {
+        let path = Path::try_parse("interfaces/interface[name=eth0]").unwrap();
+        assert_eq!(
+            path,
+            Path::try_parse("/interfaces/interface[name=eth0]").unwrap()
+        );
+        assert_eq!(path.elements().len(), 2);
+
+        let path = Path::try_parse("system/name").unwrap();
+        assert_eq!(path.elements()[0].name(), "system");
+        assert_eq!(path.elements()[1].name(), "name");
+
+        let path = Path::try_parse("system").unwrap();
+        assert_eq!(path.elements().len(), 1);
+    }
+
+    #[test]
+    fn strip_prefix() {
+        let prefix = Path::try_parse("/interfaces").unwrap();
+        let path = Path::try_parse("/interfaces/interface[name=eth0]/state").unwrap();
+
+        let relative = path.strip_prefix(&prefix).unwrap();
+        assert_eq!(relative.to_string(), "/interface[name=eth0]/state");
+        assert_eq!(relative.with_prefix(&prefix), path);
+        assert!(prefix.strip_prefix(&path).is_none());
+    }
+
+    #[test]
+    fn join_paths() {
+        let base = Path::try_parse("openconfig:/interfaces").unwrap();
+        let relative = Path::new()
+            .with_target("router1")
+            .join(&Path::try_parse("interface[name=eth0]/state").unwrap());
+
+        let path = base.join(&relative);
+        assert_eq!(path.origin(), Some("openconfig"));
+        assert_eq!(path.target(), Some("router1"));
+        assert_eq!(
+            path.to_string(),
+            "openconfig:/interfaces/interface[name=eth0]/state"
+        );
+        assert_eq!(path.elements()[1].key("name"), Some("eth0"));
+        assert_eq!(
+            base.join(&Path::new().with_origin("cli")).origin(),
+            Some("cli")
+        );
+    }
+
+    #[test]
+    fn generated_path_round_trip() {
+        let path = Path::try_parse("openconfig:/interfaces/interface[name=eth0]/state")
+            .unwrap()
+            .with_target("router1");
+
+        let element = path.elements()[1].clone();
+        assert_eq!(PathElement::from(PathElem::from(element.clone())), element);
+        assert_eq!(Path::from(GnmiPath::from(path.clone())), path);
+    }
+
+    #[test]
+    fn wildcard_paths() {
+        let path = |s: &str| Path::try_parse(s).unwrap();
+
+        assert!(path("/interfaces/interface[name=*]/state").is_wildcard());
+        assert!(path("/interfaces/*/state").is_wildcard());
+        assert!(path("/interfaces/.../mtu").is_wildcard());
+        assert!(!path("/interfaces/interface[name=eth0]/state").is_wildcard());
+
+        assert!(path("/interfaces/interface[name=eth0]/state/mtu").is_leaf_guess());
+        assert!(!path("/interfaces/interface[name=eth0]").is_leaf_guess());
+        assert!(!path("/interfaces/*/mtu").is_leaf_guess());
+        assert!(!Path::new().is_leaf_guess());
+    }
+
+    #[test]
+    fn escaped_keys_and_names() {
+        let mut path = Path::new();
+        path.push(PathElement::new("a/b[c]"));
+        path.push(PathElement::new("entry").with_key("key=x", r"uplink \ to core/1 [primary]"));
+
+        assert_eq!(
+            path.to_string(),
+            r"/a\/b\[c]/entry[key\=x=uplink \\ to core/1 [primary\]]"
+        );
+        assert_eq!(path.to_string().parse::<Path>().unwrap(), path);
+
+        let path: Path = r"/interfaces/interface[name=eth\/0]/description"
+            .parse()
+            .unwrap();
+        assert_eq!(path.elements().len(), 3);
+        assert_eq!(path.elements()[1].key("name"), Some("eth/0"));
+    }
+
+    #[test]
+    fn origins_move_to_prefix() {
+        let gnmi = |s: &str| GnmiPath::from(s.parse::<Path>().unwrap());
+        let origins = |prefix: Option<&str>, paths: &[&str]| {
+            let mut prefix = prefix.map(gnmi);
+            let mut paths: Vec<GnmiPath> = paths.iter().map(|path| gnmi(path)).collect();
+            origins_on_prefix(prefix.as_mut(), paths.iter_mut().collect()).map(|_| {
+                let paths: Vec<String> = paths
+                    .into_iter()
+                    .map(|p| Path::from(p).to_string())
+                    .collect();
+                (prefix.map(|p| Path::from(p).to_string()), paths)
+            })
+        };
+
+        // redundant origins of paths are removed
+        assert_eq!(
+            origins(
+                Some("openconfig:/interfaces"),
+                &["openconfig:/interface", "/state"]
+            )
+            .unwrap(),
+            (
+                Some("openconfig:/interfaces".to_string()),
+                vec!["/interface".to_string(), "/state".to_string()]
+            )
+        );
+        // the prefix takes the origin all paths share
+        assert_eq!(
+            origins(Some("/"), &["cli:/show", "cli:/show"]).unwrap(),
+            (
+                Some("cli:/".to_string()),
+                vec!["/show".to_string(), "/show".to_string()]
+            )
+        );
+        // without a prefix, every path keeps its own origin
+        assert_eq!(
+            origins(None, &["cli:/show", "openconfig:/system"]).unwrap(),
+            (
+                None,
+                vec!["cli:/show".to_string(), "openconfig:/system".to_string()]
+            )
+        );
+
+        assert!(matches!(
+            origins(Some("openconfig:/"), &["cli:/show"]),
+            Err(GinmiError::PathParseError(_))
+        ));
+        assert!(matches!(
+            origins(Some("/"), &["cli:/show", "/system"]),
+            Err(GinmiError::PathParseError(_))
+        ));
+    }
+
+    #[test]
+    fn parse_invalid_paths() {
+        assert!("/interfaces//state".parse::<Path>().is_err());
+        assert!("/interfaces/interface[name=eth0".parse::<Path>().is_err());
+        assert!("/interfaces/interface[name]".parse::<Path>().is_err());
+        assert!("/interfaces/interface[name=eth0]x".parse::<Path>().is_err());
+    }
+}
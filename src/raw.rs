@@ -0,0 +1,25 @@
+//! Generated protobuf types of gNMI paths, for interoperating with other gNMI libraries.
+//!
+//! The public API of this crate uses its own types, e.g. [`crate::path::Path`]. If paths
+//! are already available as protobuf messages, e.g. decoded from bytes that another
+//! library encoded, they can be passed to requests without a round-trip through their
+//! string representation with the `*_raw` methods, e.g.
+//! [`GetRequestBuilder::path_raw`](crate::client::GetRequestBuilder::path_raw).
+//!
+//! The types convert into the types of this crate and back without losing information.
+//!
+//! # Examples
+//! ```rust
+//! # use ginmi::raw;
+//! use prost::Message;
+//!
+//! let encoded = raw::Path {
+//!     elem: vec![raw::PathElem { name: "system".to_string(), ..Default::default() }],
+//!     ..Default::default()
+//! }
+//! .encode_to_vec();
+//!
+//! let path = raw::Path::decode(encoded.as_slice()).unwrap();
+//! assert_eq!(ginmi::path::Path::from(path).to_string(), "/system");
+//! ```
+pub use crate::gen::gnmi::{Path, PathElem};
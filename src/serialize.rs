@@ -0,0 +1,139 @@
+//! [`serde::Serialize`] implementations for the crate-owned data types.
+//!
+//! The serialized form is independent of the generated protobuf types, so it stays stable
+//! across updates of the gNMI schema. Paths are serialized as their string representation,
+//! values in their decoded form:
+//! ```json
+//! {
+//!   "timestamp": 1700000000000000000,
+//!   "prefix": "/interfaces/interface[name=eth0]",
+//!   "updates": [{ "path": "/state/counters/in-octets", "value": 1234, "duplicates": 0 }],
+//!   "deletes": [],
+//!   "atomic": false
+//! }
+//! ```
+//! JSON encoded values are embedded as JSON, bytes are encoded as base64 strings.
+use crate::notification::{Notification, Update};
+use crate::path::Path;
+use crate::value::TypedValue;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::ser::{Error, SerializeMap, SerializeStruct};
+use serde::{Serialize, Serializer};
+
+impl Serialize for Path {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Serialize for TypedValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            TypedValue::String(v) | TypedValue::Ascii(v) => serializer.serialize_str(v),
+            TypedValue::Int(v) => serializer.serialize_i64(*v),
+            TypedValue::Uint(v) => serializer.serialize_u64(*v),
+            TypedValue::Bool(v) => serializer.serialize_bool(*v),
+            TypedValue::Float(v) => serializer.serialize_f32(*v),
+            TypedValue::Double(v) => serializer.serialize_f64(*v),
+            TypedValue::Decimal { digits, precision } => {
+                serializer.serialize_f64(*digits as f64 / 10f64.powi(*precision as i32))
+            }
+            TypedValue::LeafList(v) => serializer.collect_seq(v),
+            TypedValue::Bytes(v) | TypedValue::ProtoBytes(v) => {
+                serializer.serialize_str(&STANDARD.encode(v))
+            }
+            TypedValue::Any { type_url, value } => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("type_url", type_url)?;
+                map.serialize_entry("value", &STANDARD.encode(value))?;
+                map.end()
+            }
+            TypedValue::Json(v) | TypedValue::JsonIetf(v) => {
+                serde_json::from_slice::<serde_json::Value>(v)
+                    .map_err(S::Error::custom)?
+                    .serialize(serializer)
+            }
+        }
+    }
+}
+
+impl Serialize for Update {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut update = serializer.serialize_struct("Update", 3)?;
+        update.serialize_field("path", &self.path)?;
+        update.serialize_field("value", &self.value)?;
+        update.serialize_field("duplicates", &self.duplicates)?;
+        update.end()
+    }
+}
+
+impl Serialize for Notification {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut notification = serializer.serialize_struct("Notification", 5)?;
+        notification.serialize_field("timestamp", &self.timestamp)?;
+        notification.serialize_field("prefix", &self.prefix)?;
+        notification.serialize_field("updates", &self.updates)?;
+        notification.serialize_field("deletes", &self.deletes)?;
+        notification.serialize_field("atomic", &self.atomic)?;
+        notification.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serialize_notification() {
+        let notification = Notification {
+            timestamp: 1_700_000_000_000_000_000,
+            prefix: Some("/interfaces/interface[name=eth0]".parse().unwrap()),
+            updates: vec![
+                Update {
+                    path: "state/counters/in-octets".parse().unwrap(),
+                    value: TypedValue::Uint(1234),
+                    duplicates: 0,
+                },
+                Update {
+                    path: "config".parse().unwrap(),
+                    value: TypedValue::JsonIetf(br#"{"mtu":9000}"#.to_vec()),
+                    duplicates: 2,
+                },
+            ],
+            deletes: vec!["/state/description".parse().unwrap()],
+            atomic: false,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&notification).unwrap(),
+            json!({
+                "timestamp": 1_700_000_000_000_000_000i64,
+                "prefix": "/interfaces/interface[name=eth0]",
+                "updates": [
+                    { "path": "/state/counters/in-octets", "value": 1234, "duplicates": 0 },
+                    { "path": "/config", "value": { "mtu": 9000 }, "duplicates": 2 }
+                ],
+                "deletes": ["/state/description"],
+                "atomic": false
+            })
+        );
+    }
+
+    #[test]
+    fn serialize_values() {
+        let value = TypedValue::LeafList(vec![
+            TypedValue::Bytes(b"ginmi".to_vec()),
+            TypedValue::Decimal {
+                digits: 1250,
+                precision: 2,
+            },
+        ]);
+
+        assert_eq!(
+            serde_json::to_value(&value).unwrap(),
+            json!(["Z2lubWk=", 12.5])
+        );
+    }
+}
@@ -0,0 +1,610 @@
+//! Values of data elements as sent by a target device.
+//!
+//! The [gNMI Specification Section 2.2.3](https://github.com/openconfig/reference/blob/master/rpc/gnmi/gnmi-specification.md#223-node-values)
+//! describes how values are encoded. A [`TypedValue`] always carries its encoding, so it
+//! can be interpreted without knowing which encoding was requested.
+use crate::client::Capabilities;
+use crate::error::GinmiError;
+use crate::gen::gnmi::typed_value::Value as GnmiValueKind;
+use crate::gen::gnmi::{
+    Decimal64, Encoding, ScalarArray, TypedValue as GnmiTypedValue, Value as GnmiValue,
+};
+use crate::gen::google::protobuf::Any;
+use crate::path::{Path, PathElement};
+use std::fmt;
+
+/// Largest precision of a decimal that gNMI allows. Decimals with a larger precision are
+/// displayed in scientific notation, instead of padding them with zeros.
+const MAX_DECIMAL_PRECISION: u32 = 18;
+
+/// The value of a data element, tagged with its type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypedValue {
+    /// A string value.
+    String(String),
+    /// A signed integer value.
+    Int(i64),
+    /// An unsigned integer value.
+    Uint(u64),
+    /// A boolean value.
+    Bool(bool),
+    /// An arbitrary sequence of bytes.
+    Bytes(Vec<u8>),
+    /// A single precision floating point value. Deprecated in favor of [`TypedValue::Double`].
+    Float(f32),
+    /// A double precision floating point value.
+    Double(f64),
+    /// A decimal value with the given number of digits following the decimal point.
+    /// Deprecated in favor of [`TypedValue::Double`].
+    Decimal { digits: i64, precision: u32 },
+    /// A list of scalar values.
+    LeafList(Vec<TypedValue>),
+    /// A protobuf message, encoded as `google.protobuf.Any`.
+    Any { type_url: String, value: Vec<u8> },
+    /// JSON encoded text.
+    Json(Vec<u8>),
+    /// JSON encoded text as per [RFC 7951](https://datatracker.ietf.org/doc/html/rfc7951).
+    JsonIetf(Vec<u8>),
+    /// ASCII text of an out-of-band agreed format.
+    Ascii(String),
+    /// A protobuf message, encoded as raw bytes.
+    ProtoBytes(Vec<u8>),
+}
+
+impl TypedValue {
+    /// Encode a JSON document in the JSON encoding the target device supports.
+    ///
+    /// Returns a [`TypedValue::JsonIetf`] if the target device supports
+    /// [`Encoding::JsonIetf`] and a [`TypedValue::Json`] otherwise.
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::DecodeError`] if the document can not be serialized.
+    pub fn json_auto(
+        value: &serde_json::Value,
+        capabilities: &Capabilities,
+    ) -> Result<Self, GinmiError> {
+        let json = serde_json::to_vec(value).map_err(|e| GinmiError::DecodeError(e.to_string()))?;
+
+        if capabilities.supports_encoding(Encoding::JsonIetf) {
+            Ok(TypedValue::JsonIetf(json))
+        } else {
+            Ok(TypedValue::Json(json))
+        }
+    }
+
+    /// Returns the text of an [`Encoding::Ascii`] encoded value, or `None` for all other
+    /// values.
+    ///
+    /// ASCII values in the deprecated `Value` format are not required to be valid UTF-8, so
+    /// invalid sequences are replaced with `U+FFFD REPLACEMENT CHARACTER` when decoding them.
+    pub fn as_ascii(&self) -> Option<&str> {
+        match self {
+            TypedValue::Ascii(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as an `i64`, or `None` if it is not an integer or does not fit.
+    ///
+    /// JSON encoded values are coerced if they are a number or, as RFC 7951 encodes 64 bit
+    /// integers, a string holding a number, e.g. `"9000"`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            TypedValue::Int(i) => Some(*i),
+            TypedValue::Uint(u) => i64::try_from(*u).ok(),
+            _ => match self.json()? {
+                serde_json::Value::Number(n) => n.as_i64(),
+                serde_json::Value::String(s) => s.trim().parse().ok(),
+                _ => None,
+            },
+        }
+    }
+
+    /// Returns the value as a `u64`, or `None` if it is not an integer or does not fit.
+    ///
+    /// JSON encoded values are coerced like in [`TypedValue::as_i64`].
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            TypedValue::Uint(u) => Some(*u),
+            TypedValue::Int(i) => u64::try_from(*i).ok(),
+            _ => match self.json()? {
+                serde_json::Value::Number(n) => n.as_u64(),
+                serde_json::Value::String(s) => s.trim().parse().ok(),
+                _ => None,
+            },
+        }
+    }
+
+    /// Returns the value as a `bool`, or `None` if it is not a boolean.
+    ///
+    /// JSON encoded values are coerced if they are `true` or `false`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            TypedValue::Bool(b) => Some(*b),
+            _ => self.json()?.as_bool(),
+        }
+    }
+
+    /// Returns the value as an `f64`, or `None` if it is not a number or is not finite.
+    ///
+    /// Integers and decimals are converted, possibly losing precision. JSON encoded values
+    /// are coerced if they are a number or, as RFC 7951 encodes decimals, a string holding
+    /// a number, e.g. `"12.50"`.
+    pub fn as_f64(&self) -> Option<f64> {
+        let value = match self {
+            TypedValue::Double(d) => *d,
+            TypedValue::Float(f) => f64::from(*f),
+            TypedValue::Int(i) => *i as f64,
+            TypedValue::Uint(u) => *u as f64,
+            TypedValue::Decimal { digits, precision } => {
+                *digits as f64 / 10f64.powi(i32::try_from(*precision).ok()?)
+            }
+            _ => match self.json()? {
+                serde_json::Value::Number(n) => n.as_f64()?,
+                serde_json::Value::String(s) => s.trim().parse().ok()?,
+                _ => return None,
+            },
+        };
+        Some(value).filter(|value| value.is_finite())
+    }
+
+    /// Decode a [`TypedValue::ProtoBytes`] or [`TypedValue::Any`] value into the protobuf
+    /// message `M`, e.g. a vendor specific telemetry message requested with
+    /// [`Encoding::Proto`].
+    ///
+    /// The `type_url` of an [`TypedValue::Any`] is not checked, so match on it first if the
+    /// target device may send different messages.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::value::TypedValue;
+    /// #[derive(Clone, PartialEq, prost::Message)]
+    /// struct Counters {
+    ///     #[prost(uint64, tag = "1")]
+    ///     in_octets: u64,
+    /// }
+    ///
+    /// let value = TypedValue::ProtoBytes(vec![0x08, 0x2a]);
+    /// let counters: Counters = value.decode_proto().unwrap();
+    /// assert_eq!(counters.in_octets, 42);
+    /// ```
+    ///
+    /// # Errors
+    /// - Returns [`GinmiError::DecodeError`] if the value is not protobuf encoded or is not a
+    ///   valid `M`.
+    pub fn decode_proto<M: prost::Message + Default>(&self) -> Result<M, GinmiError> {
+        let bytes = match self {
+            TypedValue::ProtoBytes(bytes) | TypedValue::Any { value: bytes, .. } => bytes,
+            _ => {
+                return Err(GinmiError::DecodeError(
+                    "value is not protobuf encoded".to_string(),
+                ))
+            }
+        };
+        M::decode(bytes.as_slice()).map_err(|e| GinmiError::DecodeError(e.to_string()))
+    }
+
+    /// Split a [`TypedValue::Json`] or [`TypedValue::JsonIetf`] document of a whole subtree
+    /// into its leaves, each with its path relative to the data element of the document.
+    ///
+    /// The leaves keep the encoding of the document, e.g. `9000` or `"eth0"`, so they can be
+    /// read with [`TypedValue::as_u64`] and friends. Leaf-lists, i.e. arrays of scalars, are
+    /// a single leaf holding the array. Member names are kept as they are, including the
+    /// module prefixes of RFC 7951.
+    ///
+    /// Arrays of objects are lists. Their keys are not known without the YANG schema, so each
+    /// entry is addressed by all of its members that are neither containers nor lists, which
+    /// matches OpenConfig models that repeat the keys of an entry next to its `config` and
+    /// `state` containers. Any other value, including invalid JSON, is returned as it is with
+    /// an empty path.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ginmi::value::TypedValue;
+    /// let value = TypedValue::JsonIetf(
+    ///     br#"{"interface": [{"name": "eth0", "config": {"mtu": 9000}}]}"#.to_vec(),
+    /// );
+    ///
+    /// let leaves = value.flatten_json();
+    /// assert_eq!(leaves[0].0.to_string(), "/interface[name=eth0]/config/mtu");
+    /// assert_eq!(leaves[0].1.as_u64(), Some(9000));
+    /// ```
+    pub fn flatten_json(&self) -> Vec<(Path, TypedValue)> {
+        let (document, ietf) = match (self, self.json()) {
+            (TypedValue::JsonIetf(_), Some(document)) => (document, true),
+            (TypedValue::Json(_), Some(document)) => (document, false),
+            _ => return vec![(Path::new(), self.clone())],
+        };
+
+        let mut leaves = Vec::new();
+        flatten(&document, Path::new(), ietf, &mut leaves);
+        leaves
+    }
+
+    /// Decode a [`TypedValue::Json`] or [`TypedValue::JsonIetf`] value.
+    fn json(&self) -> Option<serde_json::Value> {
+        match self {
+            TypedValue::Json(json) | TypedValue::JsonIetf(json) => {
+                serde_json::from_slice(json).ok()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TypedValue {
+    /// Formats the value in a human-readable way. Text and JSON values are written as is,
+    /// bytes as hexadecimal digits.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypedValue::String(v) | TypedValue::Ascii(v) => f.write_str(v),
+            TypedValue::Int(v) => write!(f, "{}", v),
+            TypedValue::Uint(v) => write!(f, "{}", v),
+            TypedValue::Bool(v) => write!(f, "{}", v),
+            TypedValue::Float(v) => write!(f, "{}", v),
+            TypedValue::Double(v) => write!(f, "{}", v),
+            TypedValue::Decimal { digits, precision } if *precision > MAX_DECIMAL_PRECISION => {
+                write!(f, "{}e-{}", digits, precision)
+            }
+            TypedValue::Decimal { digits, precision } => {
+                let sign = if *digits < 0 { "-" } else { "" };
+                let digits = format!(
+                    "{:0>width$}",
+                    digits.unsigned_abs(),
+                    width = *precision as usize + 1
+                );
+                let (integer, fraction) = digits.split_at(digits.len() - *precision as usize);
+                if fraction.is_empty() {
+                    write!(f, "{}{}", sign, integer)
+                } else {
+                    write!(f, "{}{}.{}", sign, integer, fraction)
+                }
+            }
+            TypedValue::LeafList(v) => {
+                write!(f, "[")?;
+                for (idx, element) in v.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            }
+            TypedValue::Json(v) | TypedValue::JsonIetf(v) => {
+                f.write_str(&String::from_utf8_lossy(v))
+            }
+            TypedValue::Bytes(v) | TypedValue::ProtoBytes(v) => write_hex(f, v),
+            TypedValue::Any { type_url, value } => {
+                write!(f, "{}: ", type_url)?;
+                write_hex(f, value)
+            }
+        }
+    }
+}
+
+/// Collect the leaves of the JSON `value` at `path`, see [`TypedValue::flatten_json`].
+fn flatten(
+    value: &serde_json::Value,
+    path: Path,
+    ietf: bool,
+    leaves: &mut Vec<(Path, TypedValue)>,
+) {
+    use serde_json::Value;
+
+    let child = |name: &str| {
+        let mut path = path.clone();
+        path.push(PathElement::new(name));
+        path
+    };
+    let is_nested = |value: &Value| match value {
+        Value::Object(_) => true,
+        Value::Array(entries) => entries.iter().any(Value::is_object),
+        _ => false,
+    };
+
+    match value {
+        Value::Object(members) => {
+            for (name, member) in members {
+                match member {
+                    Value::Array(entries) if entries.iter().any(Value::is_object) => {
+                        for entry in entries {
+                            let mut element = PathElement::new(name);
+                            for (key, key_value) in entry.as_object().into_iter().flatten() {
+                                if !is_nested(key_value) {
+                                    element = element.with_key(key, key_text(key_value));
+                                }
+                            }
+                            let mut entry_path = path.clone();
+                            entry_path.push(element);
+                            flatten(entry, entry_path, ietf, leaves);
+                        }
+                    }
+                    member => flatten(member, child(name), ietf, leaves),
+                }
+            }
+        }
+        leaf => {
+            let json = leaf.to_string().into_bytes();
+            let value = if ietf {
+                TypedValue::JsonIetf(json)
+            } else {
+                TypedValue::Json(json)
+            };
+            leaves.push((path, value));
+        }
+    }
+}
+
+/// Text of a JSON scalar as used for the value of a key, i.e. strings without quotes.
+fn key_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    write!(f, "0x")?;
+    for byte in bytes {
+        write!(f, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
+impl TryFrom<GnmiTypedValue> for TypedValue {
+    type Error = GinmiError;
+
+    fn try_from(value: GnmiTypedValue) -> Result<Self, Self::Error> {
+        let value = match value.value {
+            Some(v) => v,
+            None => {
+                return Err(GinmiError::DecodeError(
+                    "typed value does not contain a value".to_string(),
+                ))
+            }
+        };
+
+        Ok(match value {
+            GnmiValueKind::StringVal(v) => TypedValue::String(v),
+            GnmiValueKind::IntVal(v) => TypedValue::Int(v),
+            GnmiValueKind::UintVal(v) => TypedValue::Uint(v),
+            GnmiValueKind::BoolVal(v) => TypedValue::Bool(v),
+            GnmiValueKind::BytesVal(v) => TypedValue::Bytes(v),
+            GnmiValueKind::FloatVal(v) => TypedValue::Float(v),
+            GnmiValueKind::DoubleVal(v) => TypedValue::Double(v),
+            GnmiValueKind::DecimalVal(v) => TypedValue::Decimal {
+                digits: v.digits,
+                precision: v.precision,
+            },
+            GnmiValueKind::LeaflistVal(v) => TypedValue::LeafList(
+                v.element
+                    .into_iter()
+                    .map(TypedValue::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+            GnmiValueKind::AnyVal(v) => TypedValue::Any {
+                type_url: v.type_url,
+                value: v.value,
+            },
+            GnmiValueKind::JsonVal(v) => TypedValue::Json(v),
+            GnmiValueKind::JsonIetfVal(v) => TypedValue::JsonIetf(v),
+            GnmiValueKind::AsciiVal(v) => TypedValue::Ascii(v),
+            GnmiValueKind::ProtoBytes(v) => TypedValue::ProtoBytes(v),
+        })
+    }
+}
+
+impl From<TypedValue> for GnmiTypedValue {
+    fn from(value: TypedValue) -> Self {
+        let value = match value {
+            TypedValue::String(v) => GnmiValueKind::StringVal(v),
+            TypedValue::Int(v) => GnmiValueKind::IntVal(v),
+            TypedValue::Uint(v) => GnmiValueKind::UintVal(v),
+            TypedValue::Bool(v) => GnmiValueKind::BoolVal(v),
+            TypedValue::Bytes(v) => GnmiValueKind::BytesVal(v),
+            TypedValue::Float(v) => GnmiValueKind::FloatVal(v),
+            TypedValue::Double(v) => GnmiValueKind::DoubleVal(v),
+            TypedValue::Decimal { digits, precision } => {
+                GnmiValueKind::DecimalVal(Decimal64 { digits, precision })
+            }
+            TypedValue::LeafList(v) => GnmiValueKind::LeaflistVal(ScalarArray {
+                element: v.into_iter().map(Into::into).collect(),
+            }),
+            TypedValue::Any { type_url, value } => GnmiValueKind::AnyVal(Any { type_url, value }),
+            TypedValue::Json(v) => GnmiValueKind::JsonVal(v),
+            TypedValue::JsonIetf(v) => GnmiValueKind::JsonIetfVal(v),
+            TypedValue::Ascii(v) => GnmiValueKind::AsciiVal(v),
+            TypedValue::ProtoBytes(v) => GnmiValueKind::ProtoBytes(v),
+        };
+
+        GnmiTypedValue { value: Some(value) }
+    }
+}
+
+impl TryFrom<GnmiValue> for TypedValue {
+    type Error = GinmiError;
+
+    /// Convert a value in the deprecated `Value` format still sent by some older targets.
+    fn try_from(value: GnmiValue) -> Result<Self, Self::Error> {
+        match Encoding::try_from(value.r#type) {
+            Ok(Encoding::Json) => Ok(TypedValue::Json(value.value)),
+            Ok(Encoding::JsonIetf) => Ok(TypedValue::JsonIetf(value.value)),
+            Ok(Encoding::Bytes) => Ok(TypedValue::Bytes(value.value)),
+            Ok(Encoding::Proto) => Ok(TypedValue::ProtoBytes(value.value)),
+            Ok(Encoding::Ascii) => Ok(TypedValue::Ascii(
+                String::from_utf8_lossy(&value.value).into_owned(),
+            )),
+            Err(_) => Err(GinmiError::DecodeError(format!(
+                "unknown encoding {} of value",
+                value.r#type
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_values() {
+        let decimal = |digits, precision| TypedValue::Decimal { digits, precision };
+
+        assert_eq!(decimal(1250, 2).to_string(), "12.50");
+        assert_eq!(decimal(-5, 3).to_string(), "-0.005");
+        assert_eq!(decimal(42, 0).to_string(), "42");
+        assert_eq!(decimal(-7, 18).to_string(), "-0.000000000000000007");
+        assert_eq!(decimal(7, u32::MAX).to_string(), "7e-4294967295");
+        assert_eq!(TypedValue::Bytes(vec![0xca, 0xfe]).to_string(), "0xcafe");
+        assert_eq!(
+            TypedValue::LeafList(vec![TypedValue::Uint(1), TypedValue::Bool(true)]).to_string(),
+            "[1, true]"
+        );
+        assert_eq!(
+            TypedValue::JsonIetf(br#"{"mtu":9000}"#.to_vec()).to_string(),
+            r#"{"mtu":9000}"#
+        );
+    }
+
+    #[test]
+    fn ascii_values() {
+        let value = TypedValue::try_from(GnmiValue {
+            value: b"mtu 9000\n\xff".to_vec(),
+            r#type: Encoding::Ascii as i32,
+        })
+        .unwrap();
+        assert_eq!(value.as_ascii(), Some("mtu 9000\n\u{fffd}"));
+        assert_eq!(TypedValue::Json(b"{}".to_vec()).as_ascii(), None);
+    }
+
+    #[test]
+    fn coerce_values() {
+        let json = |s: &str| TypedValue::JsonIetf(s.as_bytes().to_vec());
+
+        assert_eq!(TypedValue::Uint(9000).as_i64(), Some(9000));
+        assert_eq!(TypedValue::Uint(u64::MAX).as_i64(), None);
+        assert_eq!(TypedValue::Int(-1).as_u64(), None);
+        assert_eq!(json("9000").as_u64(), Some(9000));
+        assert_eq!(json(r#""18446744073709551615""#).as_u64(), Some(u64::MAX));
+        assert_eq!(json(r#""18446744073709551615""#).as_i64(), None);
+        assert_eq!(json("-3").as_i64(), Some(-3));
+        assert_eq!(json("1.5").as_i64(), None);
+        assert_eq!(TypedValue::Json(b"true".to_vec()).as_bool(), Some(true));
+        assert_eq!(json(r#"{"enabled":true}"#).as_bool(), None);
+        assert_eq!(TypedValue::String("true".to_string()).as_bool(), None);
+        let decimal = TypedValue::Decimal {
+            digits: 1250,
+            precision: 2,
+        };
+        assert_eq!(decimal.as_f64(), Some(12.5));
+        assert_eq!(json(r#""12.50""#).as_f64(), Some(12.5));
+        assert_eq!(TypedValue::Double(f64::NAN).as_f64(), None);
+        assert_eq!(TypedValue::Bytes(vec![1]).as_i64(), None);
+    }
+
+    #[test]
+    fn decode_proto_messages() {
+        use crate::gen::gnmi::PathElem;
+        use prost::Message;
+
+        let elem = PathElem {
+            name: "interfaces".to_string(),
+            ..Default::default()
+        };
+        let bytes = TypedValue::ProtoBytes(elem.encode_to_vec());
+        let any = TypedValue::Any {
+            type_url: "type.googleapis.com/gnmi.PathElem".to_string(),
+            value: elem.encode_to_vec(),
+        };
+
+        assert_eq!(bytes.decode_proto::<PathElem>().unwrap(), elem);
+        assert_eq!(any.decode_proto::<PathElem>().unwrap(), elem);
+        assert!(matches!(
+            TypedValue::ProtoBytes(vec![0xff]).decode_proto::<PathElem>(),
+            Err(GinmiError::DecodeError(_))
+        ));
+        assert!(matches!(
+            TypedValue::Uint(1).decode_proto::<PathElem>(),
+            Err(GinmiError::DecodeError(_))
+        ));
+    }
+
+    #[test]
+    fn flatten_json_documents() {
+        let value = TypedValue::JsonIetf(
+            serde_json::to_vec(&serde_json::json!({
+                "openconfig-interfaces:interfaces": {
+                    "interface": [
+                        {
+                            "name": "eth0",
+                            "config": { "mtu": 9000, "description": "uplink" },
+                            "subinterfaces": { "subinterface": [{ "index": 0 }] }
+                        }
+                    ]
+                },
+                "counters": { "in-octets": "18446744073709551615" },
+                "servers": ["10.0.0.1", "10.0.0.2"]
+            }))
+            .unwrap(),
+        );
+
+        let leaves: Vec<(String, TypedValue)> = value
+            .flatten_json()
+            .into_iter()
+            .map(|(path, value)| (path.to_string(), value))
+            .collect();
+        let interface = "/openconfig-interfaces:interfaces/interface[name=eth0]";
+        let json = |json: &str| TypedValue::JsonIetf(json.as_bytes().to_vec());
+
+        assert_eq!(
+            leaves,
+            [
+                (
+                    "/counters/in-octets".to_string(),
+                    json("\"18446744073709551615\"")
+                ),
+                (
+                    format!("{}/config/description", interface),
+                    json("\"uplink\"")
+                ),
+                (format!("{}/config/mtu", interface), json("9000")),
+                (format!("{}/name", interface), json("\"eth0\"")),
+                (
+                    format!("{}/subinterfaces/subinterface[index=0]/index", interface),
+                    json("0")
+                ),
+                ("/servers".to_string(), json("[\"10.0.0.1\",\"10.0.0.2\"]")),
+            ]
+        );
+        assert_eq!(leaves[0].1.as_u64(), Some(u64::MAX));
+
+        let scalar = TypedValue::Uint(42);
+        assert_eq!(scalar.flatten_json(), [(Path::new(), scalar)]);
+    }
+
+    #[test]
+    fn json_auto_encoding() {
+        use crate::gen::gnmi::CapabilityResponse;
+
+        let capabilities = |encodings: &[Encoding]| {
+            Capabilities(CapabilityResponse {
+                supported_encodings: encodings.iter().map(|e| *e as i32).collect(),
+                ..Default::default()
+            })
+        };
+        let config = serde_json::json!({ "mtu": 9000 });
+
+        assert_eq!(
+            TypedValue::json_auto(
+                &config,
+                &capabilities(&[Encoding::Json, Encoding::JsonIetf])
+            )
+            .unwrap(),
+            TypedValue::JsonIetf(br#"{"mtu":9000}"#.to_vec())
+        );
+        assert_eq!(
+            TypedValue::json_auto(&config, &capabilities(&[Encoding::Json])).unwrap(),
+            TypedValue::Json(br#"{"mtu":9000}"#.to_vec())
+        );
+    }
+}